@@ -5,8 +5,9 @@ use std::marker::PhantomData;
 use std::path::Path;
 
 use super::{
-    Document, DocumentsIterator, Filter, Identifier, IndexKind, Info, KeyFields, KeyType, Modify,
-    Options, Order, Primary, Stats,
+    Accumulators, CollectionDescription, Document, DocumentsIterator, Filter, Identifier,
+    IndexKind, Info, KeyData, KeyFields, KeyType, Lookup, Modify, Options, Order, Page, Primary,
+    PrimaryType, Projection, Stats, StorageDescription, Usage, Value,
 };
 
 /// Storage actor
@@ -64,6 +65,38 @@ impl Handler<GetInfo> for Storage {
     }
 }
 
+/// Get database usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetUsage;
+
+impl Message for GetUsage {
+    type Result = LeResult<Usage>;
+}
+
+impl Handler<GetUsage> for Storage {
+    type Result = <GetUsage as Message>::Result;
+
+    fn handle(&mut self, _: GetUsage, _: &mut Self::Context) -> Self::Result {
+        self.0.get_usage()
+    }
+}
+
+/// Get storage description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Describe;
+
+impl Message for Describe {
+    type Result = LeResult<StorageDescription>;
+}
+
+impl Handler<Describe> for Storage {
+    type Result = <Describe as Message>::Result;
+
+    fn handle(&mut self, _: Describe, _: &mut Self::Context) -> Self::Result {
+        self.0.describe()
+    }
+}
+
 /// Get collections request
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct GetCollections;
@@ -86,14 +119,23 @@ impl Handler<GetCollections> for Storage {
 /// Ensure collection in storage
 #[allow(non_snake_case)]
 pub fn EnsureCollection<C: Into<Identifier>>(coll: C) -> EnsureCollectionMsg {
-    EnsureCollectionMsg(coll.into())
+    EnsureCollectionMsg(coll.into(), None)
+}
+
+/// Ensure collection with specified type of primary key in storage
+#[allow(non_snake_case)]
+pub fn EnsureCollectionWithKey<C: Into<Identifier>>(
+    coll: C,
+    key: PrimaryType,
+) -> EnsureCollectionMsg {
+    EnsureCollectionMsg(coll.into(), Some(key))
 }
 
 /// Ensure collection in storage
 ///
-/// *NOTE: Use `EnsureCollection` function instead*
+/// *NOTE: Use `EnsureCollection` or `EnsureCollectionWithKey` function instead*
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct EnsureCollectionMsg(Identifier);
+pub struct EnsureCollectionMsg(Identifier, Option<PrimaryType>);
 
 impl Message for EnsureCollectionMsg {
     type Result = LeResult<bool>;
@@ -104,15 +146,16 @@ impl Handler<EnsureCollectionMsg> for Storage {
 
     fn handle(
         &mut self,
-        EnsureCollectionMsg(name): EnsureCollectionMsg,
+        EnsureCollectionMsg(name, key): EnsureCollectionMsg,
         _: &mut Self::Context,
     ) -> Self::Result {
-        Ok(if self.0.has_collection(&name)? {
-            false
+        let exists = self.0.has_collection(&name)?;
+        if let Some(key) = key {
+            self.0.collection_with_key(name, key)?;
         } else {
             self.0.collection(name)?;
-            true
-        })
+        }
+        Ok(!exists)
     }
 }
 
@@ -144,6 +187,68 @@ impl Handler<DropCollectionMsg> for Storage {
     }
 }
 
+/// Rename collection
+#[allow(non_snake_case)]
+pub fn RenameCollection<O: Into<Identifier>, N: Into<Identifier>>(
+    old: O,
+    new: N,
+) -> RenameCollectionMsg {
+    RenameCollectionMsg(old.into(), new.into())
+}
+
+/// Rename collection
+///
+/// *NOTE: Use `RenameCollection` function instead*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameCollectionMsg(Identifier, Identifier);
+
+impl Message for RenameCollectionMsg {
+    type Result = LeResult<()>;
+}
+
+impl Handler<RenameCollectionMsg> for Storage {
+    type Result = <RenameCollectionMsg as Message>::Result;
+
+    fn handle(
+        &mut self,
+        RenameCollectionMsg(old, new): RenameCollectionMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.0.rename_collection(old, new)
+    }
+}
+
+/// Copy collection
+#[allow(non_snake_case)]
+pub fn CopyCollection<S: Into<Identifier>, D: Into<Identifier>>(
+    src: S,
+    dst: D,
+) -> CopyCollectionMsg {
+    CopyCollectionMsg(src.into(), dst.into())
+}
+
+/// Copy collection
+///
+/// *NOTE: Use `CopyCollection` function instead*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyCollectionMsg(Identifier, Identifier);
+
+impl Message for CopyCollectionMsg {
+    type Result = LeResult<()>;
+}
+
+impl Handler<CopyCollectionMsg> for Storage {
+    type Result = <CopyCollectionMsg as Message>::Result;
+
+    fn handle(
+        &mut self,
+        CopyCollectionMsg(src, dst): CopyCollectionMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.0.copy_collection(src, dst)
+    }
+}
+
 /// Get indexes of collection
 #[allow(non_snake_case)]
 pub fn GetIndexes<C: Into<Identifier>>(coll: C) -> GetIndexesMsg {
@@ -172,6 +277,34 @@ impl Handler<GetIndexesMsg> for Storage {
     }
 }
 
+/// Get collection description
+#[allow(non_snake_case)]
+pub fn DescribeCollection<C: Into<Identifier>>(coll: C) -> DescribeCollectionMsg {
+    DescribeCollectionMsg(coll.into())
+}
+
+/// Get collection description
+///
+/// *NOTE: Use `DescribeCollection` function instead*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescribeCollectionMsg(Identifier);
+
+impl Message for DescribeCollectionMsg {
+    type Result = LeResult<CollectionDescription>;
+}
+
+impl Handler<DescribeCollectionMsg> for Storage {
+    type Result = <DescribeCollectionMsg as Message>::Result;
+
+    fn handle(
+        &mut self,
+        DescribeCollectionMsg(collection): DescribeCollectionMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.0.collection(collection)?.describe()
+    }
+}
+
 /// Set indexes for collection
 #[allow(non_snake_case)]
 pub fn SetIndexes<C: Into<Identifier>, I: Into<KeyFields>>(coll: C, indexes: I) -> SetIndexesMsg {
@@ -201,21 +334,23 @@ impl Handler<SetIndexesMsg> for Storage {
 }
 
 /// Ensure new index for collection
+///
+/// The compound index can be ensured using the paths of fields separated by comma with the key types of each field.
 #[allow(non_snake_case)]
-pub fn EnsureIndex<C: Into<Identifier>, F: Into<Identifier>>(
+pub fn EnsureIndex<C: Into<Identifier>, F: Into<Identifier>, K: AsRef<[KeyType]>>(
     coll: C,
     field: F,
     kind: IndexKind,
-    key: KeyType,
+    key: K,
 ) -> EnsureIndexMsg {
-    EnsureIndexMsg(coll.into(), field.into(), kind, key)
+    EnsureIndexMsg(coll.into(), field.into(), kind, key.as_ref().to_vec())
 }
 
 /// Ensure new index for collection
 ///
 /// *NOTE: Use `EnsureIndex` for creating message*
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct EnsureIndexMsg(Identifier, Identifier, IndexKind, KeyType);
+pub struct EnsureIndexMsg(Identifier, Identifier, IndexKind, Vec<KeyType>);
 
 impl Message for EnsureIndexMsg {
     type Result = LeResult<bool>;
@@ -293,8 +428,8 @@ impl<T: Serialize + Document> Handler<InsertMsg<T>> for Storage {
 
 /// Get the previously inserted document by primary key
 #[allow(non_snake_case)]
-pub fn Get<C: Into<Identifier>, T>(coll: C, id: Primary) -> GetMsg<T> {
-    GetMsg(coll.into(), id, PhantomData)
+pub fn Get<C: Into<Identifier>, T>(coll: C, id: impl Into<Primary>) -> GetMsg<T> {
+    GetMsg(coll.into(), id.into(), PhantomData)
 }
 
 /// Get the previously inserted document by primary key
@@ -349,8 +484,8 @@ impl<T: Serialize + Document> Handler<PutMsg<T>> for Storage {
 
 /// Delete the previously inserted document
 #[allow(non_snake_case)]
-pub fn Delete<C: Into<Identifier>>(coll: C, id: Primary) -> DeleteMsg {
-    DeleteMsg(coll.into(), id)
+pub fn Delete<C: Into<Identifier>>(coll: C, id: impl Into<Primary>) -> DeleteMsg {
+    DeleteMsg(coll.into(), id.into())
 }
 
 /// Delete the previously inserted document
@@ -431,17 +566,194 @@ impl Handler<RemoveMsg> for Storage {
     }
 }
 
+/// Count documents using filter
+#[allow(non_snake_case)]
+pub fn Count<C: Into<Identifier>>(coll: C, filter: Option<Filter>) -> CountMsg {
+    CountMsg(coll.into(), filter)
+}
+
+/// Count documents using filter
+///
+/// *NOTE: Use `Count` for creating message*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMsg(Identifier, Option<Filter>);
+
+impl Message for CountMsg {
+    type Result = LeResult<usize>;
+}
+
+impl Handler<CountMsg> for Storage {
+    type Result = <CountMsg as Message>::Result;
+
+    fn handle(
+        &mut self,
+        CountMsg(collection, filter): CountMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.0.collection(collection)?.count(filter)
+    }
+}
+
+/// Aggregate documents using filter
+#[allow(non_snake_case)]
+pub fn Aggregate<C: Into<Identifier>>(
+    coll: C,
+    filter: Option<Filter>,
+    group: Vec<Identifier>,
+    fields: Accumulators,
+) -> AggregateMsg {
+    AggregateMsg(coll.into(), filter, group, fields)
+}
+
+/// Aggregate documents using filter
+///
+/// *NOTE: Use `Aggregate` for creating message*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateMsg(Identifier, Option<Filter>, Vec<Identifier>, Accumulators);
+
+impl Message for AggregateMsg {
+    type Result = LeResult<Vec<Value>>;
+}
+
+impl Handler<AggregateMsg> for Storage {
+    type Result = <AggregateMsg as Message>::Result;
+
+    fn handle(
+        &mut self,
+        AggregateMsg(collection, filter, group, fields): AggregateMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.0
+            .collection(collection)?
+            .aggregate(filter, group, fields)
+    }
+}
+
+/// Get the distinct keys of indexed field
+#[allow(non_snake_case)]
+pub fn Distinct<C: Into<Identifier>, P: Into<Identifier>>(
+    coll: C,
+    field: P,
+    filter: Option<Filter>,
+) -> DistinctMsg {
+    DistinctMsg(coll.into(), field.into(), filter)
+}
+
+/// Get the distinct keys of indexed field
+///
+/// *NOTE: Use `Distinct` for creating message*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistinctMsg(Identifier, Identifier, Option<Filter>);
+
+impl Message for DistinctMsg {
+    type Result = LeResult<Vec<(KeyData, usize)>>;
+}
+
+impl Handler<DistinctMsg> for Storage {
+    type Result = <DistinctMsg as Message>::Result;
+
+    fn handle(
+        &mut self,
+        DistinctMsg(collection, field, filter): DistinctMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.0.collection(collection)?.distinct(field, filter)
+    }
+}
+
+/// Get the most frequent keys of indexed field
+#[allow(non_snake_case)]
+pub fn Facets<C: Into<Identifier>, P: Into<Identifier>>(
+    coll: C,
+    field: P,
+    filter: Option<Filter>,
+    limit: Option<usize>,
+) -> FacetsMsg {
+    FacetsMsg(coll.into(), field.into(), filter, limit)
+}
+
+/// Get the most frequent keys of indexed field
+///
+/// *NOTE: Use `Facets` for creating message*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetsMsg(Identifier, Identifier, Option<Filter>, Option<usize>);
+
+impl Message for FacetsMsg {
+    type Result = LeResult<Vec<(KeyData, usize)>>;
+}
+
+impl Handler<FacetsMsg> for Storage {
+    type Result = <FacetsMsg as Message>::Result;
+
+    fn handle(
+        &mut self,
+        FacetsMsg(collection, field, filter, limit): FacetsMsg,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.0.collection(collection)?.facets(field, filter, limit)
+    }
+}
+
 /// Find documents using filter and ordering
 #[allow(non_snake_case)]
 pub fn Find<C: Into<Identifier>, T>(coll: C, filter: Option<Filter>, order: Order) -> FindMsg<T> {
-    FindMsg(coll.into(), filter, order, PhantomData)
+    FindMsg(
+        coll.into(),
+        filter,
+        order,
+        Page::default(),
+        None,
+        Vec::new(),
+        PhantomData,
+    )
+}
+
+/// Find the page of documents using filter and ordering
+#[allow(non_snake_case)]
+pub fn FindPage<C: Into<Identifier>, T>(
+    coll: C,
+    filter: Option<Filter>,
+    order: Order,
+    page: Page,
+) -> FindMsg<T> {
+    FindMsg(
+        coll.into(),
+        filter,
+        order,
+        page,
+        None,
+        Vec::new(),
+        PhantomData,
+    )
 }
 
 /// Find documents using filter and ordering
 ///
-/// *NOTE: Use `Find` for creating message*
+/// *NOTE: Use `Find` or `FindPage` for creating message*
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct FindMsg<T>(Identifier, Option<Filter>, Order, PhantomData<T>);
+pub struct FindMsg<T>(
+    Identifier,
+    Option<Filter>,
+    Order,
+    Page,
+    Option<Projection>,
+    Vec<Lookup>,
+    PhantomData<T>,
+);
+
+impl<T> FindMsg<T> {
+    /// Apply projection to the found documents
+    pub fn project(mut self, projection: Projection) -> Self {
+        self.4 = Some(projection);
+        self
+    }
+
+    /// Embed the referenced documents into the found documents
+    pub fn lookup(mut self, lookup: Lookup) -> Self {
+        self.5.push(lookup);
+        self
+    }
+}
 
 impl<T: 'static> Message for FindMsg<T> {
     type Result = LeResult<DocumentsIterator<T>>;
@@ -452,18 +764,29 @@ impl<T: DeserializeOwned + Document + 'static> Handler<FindMsg<T>> for Storage {
 
     fn handle(
         &mut self,
-        FindMsg(collection, filter, order, ..): FindMsg<T>,
+        FindMsg(collection, filter, order, page, projection, lookups, ..): FindMsg<T>,
         _: &mut Self::Context,
     ) -> Self::Result {
-        self.0.collection(collection)?.find(filter, order)
+        let mut docs = self
+            .0
+            .collection(collection)?
+            .find_page(filter, order, page)?;
+        for lookup in lookups {
+            docs = docs.lookup(lookup)?;
+        }
+        Ok(if let Some(projection) = projection {
+            docs.project(projection)
+        } else {
+            docs
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        Document, EnsureIndex, Find, Identifier, IndexKind, Insert, KeyType, Options, Primary,
-        Storage,
+        Aggregate, Document, EnsureIndex, Facets, Find, Identifier, IndexKind, Insert, KeyData,
+        KeyType, Options, Primary, Storage, Value,
     };
     use serde::{Deserialize, Serialize};
     use serde_json::{from_value, json};
@@ -550,5 +873,32 @@ mod tests {
         });
         assert_eq!(&doc, &doc_data);
         assert!(docs.next().is_none());
+
+        let groups: Vec<Value> = addr
+            .send(Aggregate(
+                "blog",
+                None,
+                vec!["tags".into()],
+                json_val!({ "count": "$count" }),
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let groups_data: Vec<Value> = json_val!([
+            { "tags": "absurd", "count": 1 },
+            { "tags": "ipsum", "count": 1 },
+            { "tags": "lorem", "count": 1 },
+            { "tags": "psychology", "count": 1 },
+        ]);
+        assert_eq!(groups, groups_data);
+
+        assert_eq!(
+            addr.send(Facets("blog", "tags", None, Some(1)))
+                .await
+                .unwrap()
+                .unwrap(),
+            vec![(KeyData::String("absurd".into()), 1)]
+        );
     }
 }
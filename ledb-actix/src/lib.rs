@@ -117,6 +117,14 @@ __GET__ /info
 
 __GET__ /stats
 
+#### get database map size usage
+
+__GET__ /usage
+
+#### get storage description
+
+__GET__ /describe
+
 ### Collection API
 
 #### get list of collections
@@ -127,10 +135,20 @@ __GET__ /collection
 
 __POST__ /collection?name=_$collection_name_
 
+#### create new empty collection with specified primary key type
+
+__POST__ /collection?name=_$collection_name_&key=_$primary_type_
+
+The primary key type is one of `u32` (default), `u64`, `string`, `uuid` or `ulid`.
+
 #### drop collection with all documents
 
 __DELETE__ /collection/_$collection_name_
 
+#### get collection description
+
+__GET__ /collection/_$collection_name_/describe
+
 ### Index API
 
 #### get indexes of collection
@@ -141,6 +159,10 @@ __GET__ /collection/_$collection_name_/index
 
 __POST__ /collection/_$collection_name_/index?path=_$field_name_&kind=_$index_kind_&key=_$key_type_
 
+#### create new compound index over several fields for collection
+
+__POST__ /collection/_$collection_name_/index?path=_$field_name_,_$other_field_name_&kind=_$index_kind_&key=_$key_type_&rest=_$other_key_types_
+
 #### drop index of collection
 
 __DELETE__ /collection/_$collection_name_/document/_$index_name_
@@ -153,6 +175,48 @@ __GET__ /collection/_$collection_name_/document?filter=_$query_&order=_$ordering
 
 __GET__ /collection/_$collection_name_?filter=_$query_&order=_$ordering_&offset=_$skip_&length=_$take_
 
+#### find next page of documents after the last found document
+
+__GET__ /collection/_$collection_name_/document?filter=_$query_&order=_$ordering_&after=_$position_&length=_$take_
+
+__GET__ /collection/_$collection_name_?filter=_$query_&order=_$ordering_&after=_$position_&length=_$take_
+
+The position is a JSON object like `{"key":$field_key,"id":$primary_key}` which contains the key of ordering field (when ordered by field) and the primary key of the last found document.
+
+#### find documents with the specified fields only
+
+__GET__ /collection/_$collection_name_/document?filter=_$query_&order=_$ordering_&project=_$projection_
+
+__GET__ /collection/_$collection_name_?filter=_$query_&order=_$ordering_&project=_$projection_
+
+The projection is a JSON object like `{"$include":["title","meta.author"]}` which keeps the specified fields only, or `{"$exclude":["content"]}` which removes the specified fields.
+
+#### find documents with the embedded documents of other collections
+
+__GET__ /collection/_$collection_name_/document?filter=_$query_&order=_$ordering_&lookup=_$lookups_
+
+__GET__ /collection/_$collection_name_?filter=_$query_&order=_$ordering_&lookup=_$lookups_
+
+The lookups is a JSON array like `[{"from":"users","local":"author_id","path":"author"}]` which embeds the documents of other collections referenced by primary keys, or by the keys of indexed `foreign` field when it specified.
+
+#### count documents using query
+
+__GET__ /collection/_$collection_name_/count?filter=_$query_
+
+#### aggregate documents using query
+
+__GET__ /collection/_$collection_name_/aggregate?filter=_$query_&group=_$fields_&fields=_$accumulators_
+
+The group is a JSON array of field names like `["status"]`, the accumulators is a JSON object like `{"count":"$count","total":{"$sum":"price"}}`.
+
+#### get distinct keys of indexed field with the numbers of documents
+
+__GET__ /collection/_$collection_name_/distinct?field=_$field_name_&filter=_$query_
+
+#### get most frequent keys of indexed field with the numbers of documents
+
+__GET__ /collection/_$collection_name_/facets?field=_$field_name_&filter=_$query_&limit=_$take_
+
 #### modify documents using query
 
 __PUT__ /collection/_$collection_name_/document?filter=_$query_&modify=_$modifications_
@@ -198,9 +262,10 @@ mod macros;
 mod scope;
 
 pub use ledb::{
-    KeyType, Modify, Options, Order, OrderKind, Primary, Stats, _query_impl, query_extr, Action,
-    Comp, Cond, Document, DocumentsIterator, Filter, Identifier, IndexKind, Info, KeyData,
-    KeyField, KeyFields, Value,
+    _query_impl, query_extr, Accumulator, Accumulators, Action, CollectionDescription, Comp, Cond, Document,
+    DocumentsIterator, Filter, Identifier, IndexDescription, IndexKind, Info, KeyData, KeyField,
+    KeyFields, KeyType, Lookup, Modify, Options, Order, OrderKind, Page, Position, Primary, PrimaryType, Projection,
+    Stats, StorageDescription, Usage, Value,
 };
 
 pub use actor::*;
@@ -22,6 +22,9 @@ macro_rules! _query_actix {
     (@find $type:tt, $coll:expr, $filter:expr, $order:expr) => (
         $crate::Find::<_, $type>($crate::_query_impl!(@stringify $coll), $filter, $order)
     );
+    (@find $type:tt, $coll:expr, $filter:expr, $order:expr, $project:expr) => (
+        $crate::Find::<_, $type>($crate::_query_impl!(@stringify $coll), $filter, $order).project($project)
+    );
     (@insert $coll:expr, $doc:expr) => (
         $crate::Insert($crate::_query_impl!(@stringify $coll), $doc)
     );
@@ -87,6 +90,25 @@ mod test {
         );
     }
 
+    #[test]
+    fn find_project() {
+        assert_eq!(
+            query!(find Value in collection project field, other.field),
+            Find("collection", None, query!(@order))
+                .project(query!(@project field, other.field))
+        );
+
+        assert_eq!(
+            query!(find Value in collection where field == "abc" order desc project -other.*),
+            Find(
+                "collection",
+                query!(@filter field == "abc"),
+                query!(@order desc)
+            )
+            .project(query!(@project -other.*))
+        );
+    }
+
     #[test]
     fn update() {
         assert_eq!(
@@ -8,12 +8,14 @@ use actix_web::{
 };
 use serde::{Deserialize, Serialize};
 use serde_with::json::nested as json_str;
-use std::usize;
 
 use super::{
-    Delete, Document, DropCollection, DropIndex, EnsureCollection, EnsureIndex, Filter, Find, Get,
-    GetCollections, GetIndexes, GetInfo, GetStats, Info, Insert, KeyField, ListCollections, Modify,
-    Order, Primary, Put, Remove, Stats, Storage, Update, Value,
+    Accumulators, Aggregate, CollectionDescription, Count, Delete, Describe, DescribeCollection,
+    Distinct, Document, DropCollection, DropIndex, EnsureCollection, EnsureCollectionWithKey,
+    EnsureIndex, Facets, Filter, FindPage, Get, GetCollections, GetIndexes, GetInfo, GetStats,
+    GetUsage, IndexKind, Info, Insert, KeyData, KeyField, KeyType, ListCollections, Lookup, Modify,
+    Order, Page, Position, Primary, PrimaryType, Projection, Put, Remove, Stats, Storage,
+    StorageDescription, Update, Usage, Value,
 };
 
 /// Storage actor address type
@@ -25,6 +27,16 @@ pub fn storage() -> Scope {
         .service(resource("/").name("usage").route(get().to(get_usage)))
         .service(resource("/info").name("info").route(get().to(get_info)))
         .service(resource("/stats").name("stats").route(get().to(get_stats)))
+        .service(
+            resource("/usage")
+                .name("map_usage")
+                .route(get().to(get_map_usage)),
+        )
+        .service(
+            resource("/describe")
+                .name("describe")
+                .route(get().to(get_description)),
+        )
         .service(
             resource("/collection")
                 .name("collections")
@@ -45,6 +57,31 @@ pub fn storage() -> Scope {
                 )
                 .service(
                     scope("/{collection}")
+                        .service(
+                            resource("/describe")
+                                .name("collection_describe")
+                                .route(get().to(get_collection_description)),
+                        )
+                        .service(
+                            resource("/count")
+                                .name("count")
+                                .route(get().to(count_documents)),
+                        )
+                        .service(
+                            resource("/aggregate")
+                                .name("aggregate")
+                                .route(get().to(aggregate_documents)),
+                        )
+                        .service(
+                            resource("/distinct")
+                                .name("distinct")
+                                .route(get().to(distinct_keys)),
+                        )
+                        .service(
+                            resource("/facets")
+                                .name("facets")
+                                .route(get().to(facet_keys)),
+                        )
                         .service(
                             resource("/index")
                                 .name("indexes")
@@ -97,6 +134,10 @@ Storage API:
     GET {info}
     # get database statistics
     GET {stats}
+    # get database map size usage
+    GET {map_usage}
+    # get storage description
+    GET {describe}
 
 Collection API:
 
@@ -104,8 +145,12 @@ Collection API:
     GET {collections}
     # create new empty collection
     POST {collections}?name=$collection_name
+    # create new empty collection with specified primary key type (u32, u64, string, uuid, ulid)
+    POST {collections}?name=$collection_name&key=$primary_type
     # drop collection with all documents
     DELETE {collection}
+    # get collection description
+    GET {collection_describe}
 
 Index API:
 
@@ -113,6 +158,8 @@ Index API:
     GET {indexes}
     # create new index for collection
     POST {indexes}?path=$field_path&kind=$index_kind&key=$key_type
+    # create new compound index over several fields for collection
+    POST {indexes}?path=$field_path,$other_field_path&kind=$index_kind&key=$key_type&rest=$other_key_types
     # drop index of collection
     DELETE {index}
 
@@ -121,6 +168,23 @@ Document API:
     # find documents using query
     GET {documents}?filter=$query&order=$ordering&offset=10&length=10
     GET {collection}?filter=$query&order=$ordering&offset=10&length=10
+    # find next page of documents after the last found document
+    GET {documents}?filter=$query&order=$ordering&after=$position&length=10
+    GET {collection}?filter=$query&order=$ordering&after=$position&length=10
+    # find documents with the specified fields only
+    GET {documents}?filter=$query&order=$ordering&project=$projection
+    GET {collection}?filter=$query&order=$ordering&project=$projection
+    # find documents with the embedded documents of other collections
+    GET {documents}?filter=$query&order=$ordering&lookup=$lookups
+    GET {collection}?filter=$query&order=$ordering&lookup=$lookups
+    # count documents using query
+    GET {count}?filter=$query
+    # aggregate documents using query
+    GET {aggregate}?filter=$query&group=$fields&fields=$accumulators
+    # get distinct keys of indexed field with the numbers of documents
+    GET {distinct}?field=$field_path&filter=$query
+    # get most frequent keys of indexed field with the numbers of documents
+    GET {facets}?field=$field_path&filter=$query&limit=10
     # modify documents using query
     PUT {documents}?filter=$query&modify=$modifications
     PATCH {collection}?filter=$query&modify=$modifications
@@ -145,6 +209,7 @@ Supported index kinds:
 
     index -- Normal index which may contain duplicated keys
     unique -- Index which contains unique keys only
+    fulltext -- Index which contains the words of string keys
 
 Supported key types:
 
@@ -160,13 +225,22 @@ See documentation: {documentation}
         documentation = env!("CARGO_PKG_HOMEPAGE"),
         info = req.url_for_static("info").unwrap(),
         stats = req.url_for_static("stats").unwrap(),
+        map_usage = req.url_for_static("map_usage").unwrap(),
+        describe = req.url_for_static("describe").unwrap(),
         collections = req.url_for_static("collections").unwrap(),
         collection = req.url_for("collection", &["$collection_name"]).unwrap(),
+        collection_describe = req
+            .url_for("collection_describe", &["$collection_name"])
+            .unwrap(),
         indexes = req.url_for("indexes", &["$collection_name"]).unwrap(),
         index = req
             .url_for("document", &["$collection_name", "$index_name"])
             .unwrap(),
         documents = req.url_for("documents", &["$collection_name"]).unwrap(),
+        count = req.url_for("count", &["$collection_name"]).unwrap(),
+        aggregate = req.url_for("aggregate", &["$collection_name"]).unwrap(),
+        distinct = req.url_for("distinct", &["$collection_name"]).unwrap(),
+        facets = req.url_for("facets", &["$collection_name"]).unwrap(),
         document = req
             .url_for("document", &["$collection_name", "$document_id"])
             .unwrap(),
@@ -192,6 +266,22 @@ pub async fn get_stats(addr: Data<StorageAddr>) -> Result<Json<Stats>> {
         .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
 }
 
+/// Storage map usage handler
+pub async fn get_map_usage(addr: Data<StorageAddr>) -> Result<Json<Usage>> {
+    addr.send(GetUsage)
+        .await
+        .map_err(ErrorServiceUnavailable)
+        .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
+}
+
+/// Storage description handler
+pub async fn get_description(addr: Data<StorageAddr>) -> Result<Json<StorageDescription>> {
+    addr.send(Describe)
+        .await
+        .map_err(ErrorServiceUnavailable)
+        .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
+}
+
 /// Storage collections handler
 pub async fn get_collections(addr: Data<StorageAddr>) -> Result<Json<ListCollections>> {
     addr.send(GetCollections)
@@ -200,10 +290,23 @@ pub async fn get_collections(addr: Data<StorageAddr>) -> Result<Json<ListCollect
         .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
 }
 
+/// Collection description handler
+pub async fn get_collection_description(
+    addr: Data<StorageAddr>,
+    coll: Path<String>,
+) -> Result<Json<CollectionDescription>> {
+    addr.send(DescribeCollection(coll.into_inner()))
+        .await
+        .map_err(ErrorServiceUnavailable)
+        .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
+}
+
 /// Collection parameters
 #[derive(Serialize, Deserialize)]
 pub struct CollectionParams {
     pub name: String,
+    #[serde(default)]
+    pub key: Option<PrimaryType>,
 }
 
 /// Ensure collection handler
@@ -212,10 +315,14 @@ pub async fn ensure_collection(
     params: Query<CollectionParams>,
     req: HttpRequest,
 ) -> Result<HttpResponse> {
-    let CollectionParams { name } = params.into_inner();
+    let CollectionParams { name, key } = params.into_inner();
     match req.url_for("collection", &[&name]) {
         Ok(url) => addr
-            .send(EnsureCollection(name))
+            .send(if let Some(key) = key {
+                EnsureCollectionWithKey(name, key)
+            } else {
+                EnsureCollection(name)
+            })
             .await
             .map_err(ErrorServiceUnavailable)
             .and_then(|res| res.map_err(ErrorInternalServerError))
@@ -262,16 +369,36 @@ pub async fn get_indexes(
         .map(|indexes| Json(indexes.into_iter().collect()))
 }
 
+/// Ensure index parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexParams {
+    pub path: String,
+    #[serde(default)]
+    pub kind: IndexKind,
+    #[serde(default)]
+    pub key: KeyType,
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub rest: Vec<KeyType>,
+}
+
 /// Ensure index handler
 pub async fn ensure_index(
     addr: Data<StorageAddr>,
     coll: Path<String>,
-    params: Query<KeyField>,
+    params: Query<IndexParams>,
     req: HttpRequest,
 ) -> Result<HttpResponse> {
-    let KeyField { path, kind, key } = params.into_inner();
+    let IndexParams {
+        path,
+        kind,
+        key,
+        rest,
+    } = params.into_inner();
+    let mut keys = vec![key];
+    keys.extend(rest);
     if let Ok(url) = req.url_for("index", &[&coll, &path]) {
-        addr.send(EnsureIndex(coll.into_inner(), path, kind, key))
+        addr.send(EnsureIndex(coll.into_inner(), path, kind, keys))
             .await
             .map_err(ErrorServiceUnavailable)
             .and_then(|res| res.map_err(ErrorInternalServerError))
@@ -343,6 +470,15 @@ pub struct FindParams {
     pub offset: Option<usize>,
     #[serde(default)]
     pub length: Option<usize>,
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub after: Option<Position>,
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub project: Option<Projection>,
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub lookup: Vec<Lookup>,
 }
 
 /// Find documents query handler
@@ -356,21 +492,138 @@ pub async fn find_documents(
         order,
         offset,
         length,
+        after,
+        project,
+        lookup,
     } = query.into_inner();
-    addr.send(Find::<_, Value>(coll.into_inner(), filter, order))
+    let page = Page {
+        skip: offset.unwrap_or(0),
+        limit: length,
+        after,
+    };
+    let msg = FindPage::<_, Value>(coll.into_inner(), filter, order, page);
+    let msg = if let Some(project) = project {
+        msg.project(project)
+    } else {
+        msg
+    };
+    let msg = lookup
+        .into_iter()
+        .fold(msg, |msg, lookup| msg.lookup(lookup));
+    addr.send(msg)
         .await
         .map_err(ErrorServiceUnavailable)
         .and_then(|res| res.map_err(ErrorInternalServerError))
-        .and_then(move |docs| {
-            docs.skip(offset.unwrap_or(0))
-                .take(length.unwrap_or(usize::MAX))
-                .collect::<Result<Vec<_>, _>>()
+        .and_then(|docs| {
+            docs.collect::<Result<Vec<_>, _>>()
                 .map_err(ErrorInternalServerError)
                 .map(Json)
         })
         .map_err(ErrorInternalServerError)
 }
 
+/// Count query parameters
+#[derive(Serialize, Deserialize)]
+pub struct CountParams {
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub filter: Option<Filter>,
+}
+
+/// Count documents query handler
+pub async fn count_documents(
+    addr: Data<StorageAddr>,
+    coll: Path<String>,
+    query: Query<CountParams>,
+) -> Result<Json<usize>> {
+    let CountParams { filter } = query.into_inner();
+    addr.send(Count(coll.into_inner(), filter))
+        .await
+        .map_err(ErrorServiceUnavailable)
+        .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
+}
+
+/// Aggregate query parameters
+#[derive(Serialize, Deserialize)]
+pub struct AggregateParams {
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub filter: Option<Filter>,
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub group: Vec<String>,
+    #[serde(with = "json_str")]
+    pub fields: Accumulators,
+}
+
+/// Aggregate documents query handler
+pub async fn aggregate_documents(
+    addr: Data<StorageAddr>,
+    coll: Path<String>,
+    query: Query<AggregateParams>,
+) -> Result<Json<Vec<Value>>> {
+    let AggregateParams {
+        filter,
+        group,
+        fields,
+    } = query.into_inner();
+    let group = group.into_iter().map(Into::into).collect();
+    addr.send(Aggregate(coll.into_inner(), filter, group, fields))
+        .await
+        .map_err(ErrorServiceUnavailable)
+        .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
+}
+
+/// Distinct query parameters
+#[derive(Serialize, Deserialize)]
+pub struct DistinctParams {
+    pub field: String,
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub filter: Option<Filter>,
+}
+
+/// Distinct keys query handler
+pub async fn distinct_keys(
+    addr: Data<StorageAddr>,
+    coll: Path<String>,
+    query: Query<DistinctParams>,
+) -> Result<Json<Vec<(KeyData, usize)>>> {
+    let DistinctParams { field, filter } = query.into_inner();
+    addr.send(Distinct(coll.into_inner(), field, filter))
+        .await
+        .map_err(ErrorServiceUnavailable)
+        .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
+}
+
+/// Facets query parameters
+#[derive(Serialize, Deserialize)]
+pub struct FacetsParams {
+    pub field: String,
+    #[serde(default)]
+    #[serde(with = "json_str")]
+    pub filter: Option<Filter>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Facet keys query handler
+pub async fn facet_keys(
+    addr: Data<StorageAddr>,
+    coll: Path<String>,
+    query: Query<FacetsParams>,
+) -> Result<Json<Vec<(KeyData, usize)>>> {
+    let FacetsParams {
+        field,
+        filter,
+        limit,
+    } = query.into_inner();
+    addr.send(Facets(coll.into_inner(), field, filter, limit))
+        .await
+        .map_err(ErrorServiceUnavailable)
+        .and_then(|res| res.map(Json).map_err(ErrorInternalServerError))
+}
+
 /// Update query parameters
 #[derive(Serialize, Deserialize)]
 pub struct UpdateParams {
@@ -425,9 +678,10 @@ pub async fn remove_documents(
 /// Get document handler
 pub async fn get_document(
     addr: Data<StorageAddr>,
-    path: Path<(String, Primary)>,
+    path: Path<(String, String)>,
 ) -> Result<Json<Value>> {
     let (coll, id) = path.into_inner();
+    // the id will be converted to the primary key type of collection
     addr.send(Get(coll, id))
         .await
         .map_err(ErrorServiceUnavailable)
@@ -451,12 +705,12 @@ impl Document for DocumentWithId {}
 /// Put document handler
 pub async fn put_document(
     addr: Data<StorageAddr>,
-    path: Path<(String, Primary)>,
+    path: Path<(String, String)>,
     data: Json<Value>,
 ) -> Result<HttpResponse> {
     let (coll, id) = path.into_inner();
     let doc = DocumentWithId {
-        id,
+        id: id.into(),
         val: data.into_inner(),
     };
     addr.send(Put(coll, doc))
@@ -469,7 +723,7 @@ pub async fn put_document(
 /// Delete document handler
 pub async fn delete_document(
     addr: Data<StorageAddr>,
-    path: Path<(String, Primary)>,
+    path: Path<(String, String)>,
 ) -> Result<HttpResponse> {
     let (coll, id) = path.into_inner();
     addr.send(Delete(coll, id))
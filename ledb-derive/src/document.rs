@@ -1,5 +1,5 @@
 use crate::wrapper::wrap_in_const;
-use proc_macro2::{Span, TokenStream, TokenTree};
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
 use quote::quote;
 use syn::{Data, DeriveInput, Field, Fields, Lit, LitStr, Type};
 
@@ -13,16 +13,19 @@ fn derive_document(input: &DeriveInput) -> Result<TokenStream, String> {
     let type_name = &input.ident;
     let is_nested = has_nested_attribute(input);
     let mut primary_field = None;
+    let mut primary_type = None;
     let mut index_fields = Vec::new();
+    let mut compound_indexes = Vec::new();
     let mut nested_docs = Vec::new();
 
     match &input.data {
         Data::Struct(data) => match &data.fields {
             Fields::Named(fields) => {
                 for field in &fields.named {
-                    if let Some(primary_field_name) = get_primary_attribute(&field) {
+                    if let Some((primary_field_name, key_type)) = get_primary_attribute(&field)? {
                         if primary_field.is_none() {
                             primary_field = get_serde_rename(&field).or(Some(primary_field_name));
+                            primary_type = key_type;
                         } else {
                             return Err("Only one primary key field per document allowed".into());
                         }
@@ -45,6 +48,33 @@ fn derive_document(input: &DeriveInput) -> Result<TokenStream, String> {
                         ));
                     }
                 }
+
+                for (index_kind, names) in get_compound_attributes(input) {
+                    let compound_fields = names
+                        .into_iter()
+                        .map(|name| {
+                            let field = fields
+                                .named
+                                .iter()
+                                .find(|field| {
+                                    field
+                                        .ident
+                                        .as_ref()
+                                        .map(|ident| ident == &name)
+                                        .unwrap_or(false)
+                                })
+                                .ok_or_else(|| {
+                                    format!("Unknown field '{}' of compound index", name)
+                                })?;
+                            let field_type = match get_index_attribute(field) {
+                                Some((_, Err(key_type), _)) => Err(key_type),
+                                _ => Ok(field.ty.clone()),
+                            };
+                            Ok((get_serde_rename(field).unwrap_or(name), field_type))
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+                    compound_indexes.push((compound_fields, index_kind));
+                }
             }
             _ => return Err("Only struct with named fields can be represented as document".into()),
         },
@@ -67,30 +97,54 @@ fn derive_document(input: &DeriveInput) -> Result<TokenStream, String> {
         }
     };
 
-    let key_fields_fn = if index_fields.is_empty() && nested_docs.is_empty() {
+    let primary_type_fn = if let Some(primary_type) = primary_type {
+        quote! {
+            fn primary_type() -> _ledb_types::PrimaryType {
+                _ledb_types::PrimaryType::#primary_type
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+
+    let key_fields_fn = if index_fields.is_empty()
+        && compound_indexes.is_empty()
+        && nested_docs.is_empty()
+    {
         TokenStream::new()
     } else {
         let index_fields = index_fields
             .into_iter()
             .map(|(field_name, field_type, index_kind)| {
                 let field_name = Lit::Str(LitStr::new(&field_name, Span::call_site()));
-                let field_type = match field_type {
-                    Ok(field_type) => {
-                        quote! { <#field_type as _ledb_types::DocumentKeyType>::key_type() }
-                    }
-                    Err(key_type) => quote! { _ledb_types::KeyType::#key_type },
-                };
-                let index_kind = match index_kind.as_str() {
-                    "unique" => quote! { _ledb_types::IndexKind::Unique },
-                    "index" => quote! { _ledb_types::IndexKind::Index },
-                    _ => unreachable!(),
-                };
+                let field_type = key_type_tokens(field_type);
+                let index_kind = index_kind_tokens(&index_kind);
 
                 quote! {
                     (#field_name, #field_type, #index_kind)
                 }
             });
 
+        let compound_indexes = compound_indexes
+            .into_iter()
+            .map(|(compound_fields, index_kind)| {
+                let compound_fields = compound_fields
+                    .into_iter()
+                    .map(|(field_name, field_type)| {
+                        let field_name = Lit::Str(LitStr::new(&field_name, Span::call_site()));
+                        let field_type = key_type_tokens(field_type);
+
+                        quote! {
+                            (#field_name, #field_type)
+                        }
+                    });
+                let index_kind = index_kind_tokens(&index_kind);
+
+                quote! {
+                    _ledb_types::KeyField::compound(vec![#(#compound_fields),*]).with_kind(#index_kind)
+                }
+            });
+
         let nested_docs = nested_docs
             .into_iter()
             .map(|(field_name, field_type, is_flatten)| {
@@ -111,6 +165,7 @@ fn derive_document(input: &DeriveInput) -> Result<TokenStream, String> {
             fn key_fields() -> _ledb_types::KeyFields {
                 _ledb_types::KeyFields::new()
                     #(.with_field(#index_fields))*
+                    #(.with_field(#compound_indexes))*
                     #(.with_fields(#nested_docs))*
             }
         }
@@ -119,39 +174,75 @@ fn derive_document(input: &DeriveInput) -> Result<TokenStream, String> {
     Ok(quote! {
         impl _ledb_types::Document for #type_name {
             #primary_field_fn
+            #primary_type_fn
             #key_fields_fn
         }
     })
 }
 
-fn has_primary_token(token: TokenTree) -> bool {
-    if let TokenTree::Group(group) = token {
-        group.stream().into_iter().any(|token| {
-            if let TokenTree::Ident(ident) = token {
-                ident == "primary"
-            } else {
-                false
+fn key_type_tokens(field_type: Result<Type, TokenStream>) -> TokenStream {
+    match field_type {
+        Ok(field_type) => quote! { <#field_type as _ledb_types::DocumentKeyType>::key_type() },
+        Err(key_type) => quote! { _ledb_types::KeyType::#key_type },
+    }
+}
+
+fn index_kind_tokens(index_kind: &str) -> TokenStream {
+    match index_kind {
+        "unique" => quote! { _ledb_types::IndexKind::Unique },
+        "index" => quote! { _ledb_types::IndexKind::Index },
+        "fulltext" => quote! { _ledb_types::IndexKind::FullText },
+        _ => unreachable!(),
+    }
+}
+
+fn get_primary_type(group: &Group) -> Option<Result<Option<TokenStream>, String>> {
+    let mut tokens = group.stream().into_iter();
+
+    while let Some(token) = tokens.next() {
+        if let TokenTree::Ident(ident) = token {
+            if ident == "primary" {
+                return Some(if let Some(TokenTree::Ident(key)) = &tokens.next() {
+                    match key.to_string().as_ref() {
+                        "u32" => Ok(Some(quote!(U32))),
+                        "u64" => Ok(Some(quote!(U64))),
+                        "str" | "string" => Ok(Some(quote!(String))),
+                        "uuid" => Ok(Some(quote!(Uuid))),
+                        "ulid" => Ok(Some(quote!(Ulid))),
+                        key => Err(format!(
+                            "Unknown primary key type '{}', expected one of u32, u64, str, string, uuid, ulid",
+                            key
+                        )),
+                    }
+                } else {
+                    Ok(None)
+                });
             }
-        })
-    } else {
-        false
+        }
     }
+
+    None
 }
 
-fn get_primary_attribute(field: &Field) -> Option<String> {
+fn get_primary_attribute(field: &Field) -> Result<Option<(String, Option<TokenStream>)>, String> {
     if let Some(ident) = &field.ident {
         for attr in &field.attrs {
             if attr.path.leading_colon.is_none()
                 && attr.path.segments.len() == 1
                 && attr.path.segments.first().unwrap().ident == "document"
-                && attr.tokens.clone().into_iter().any(has_primary_token)
             {
-                return Some(ident.to_string());
+                for token in attr.tokens.clone() {
+                    if let TokenTree::Group(group) = token {
+                        if let Some(key_type) = get_primary_type(&group) {
+                            return Ok(Some((ident.to_string(), key_type?)));
+                        }
+                    }
+                }
             }
         }
     }
 
-    None
+    Ok(None)
 }
 
 fn get_index_attribute(field: &Field) -> Option<(String, Result<Type, TokenStream>, String)> {
@@ -165,7 +256,9 @@ fn get_index_attribute(field: &Field) -> Option<(String, Result<Type, TokenStrea
                     if let TokenTree::Group(group) = token {
                         let mut tokens = group.stream().into_iter();
                         match &tokens.next() {
-                            Some(TokenTree::Ident(kind)) if kind == "unique" || kind == "index" => {
+                            Some(TokenTree::Ident(kind))
+                                if kind == "unique" || kind == "index" || kind == "fulltext" =>
+                            {
                                 let key_type = if let Some(TokenTree::Ident(key)) = &tokens.next() {
                                     match key.to_string().as_ref() {
                                         "int" | "integer" => Err(quote!(Int)),
@@ -173,6 +266,8 @@ fn get_index_attribute(field: &Field) -> Option<(String, Result<Type, TokenStrea
                                         "str" | "string" => Err(quote!(String)),
                                         "bin" | "binary" => Err(quote!(Binary)),
                                         "bool" | "boolean" => Err(quote!(Bool)),
+                                        "geo" => Err(quote!(Geo)),
+                                        "timestamp" => Err(quote!(Timestamp)),
                                         _ => Ok(field.ty.clone()),
                                     }
                                 } else {
@@ -215,6 +310,41 @@ fn has_nested_attribute(input: &DeriveInput) -> bool {
     false
 }
 
+fn get_compound_attributes(input: &DeriveInput) -> Vec<(String, Vec<String>)> {
+    let mut indexes = Vec::new();
+
+    for attr in &input.attrs {
+        if attr.path.leading_colon.is_none()
+            && attr.path.segments.len() == 1
+            && attr.path.segments.first().unwrap().ident == "document"
+        {
+            for token in attr.tokens.clone() {
+                if let TokenTree::Group(group) = token {
+                    let mut tokens = group.stream().into_iter();
+                    match (&tokens.next(), &tokens.next()) {
+                        (Some(TokenTree::Ident(kind)), Some(TokenTree::Group(fields)))
+                            if kind == "unique" || kind == "index" =>
+                        {
+                            let names = fields
+                                .stream()
+                                .into_iter()
+                                .filter_map(|token| match token {
+                                    TokenTree::Ident(name) => Some(name.to_string()),
+                                    _ => None,
+                                })
+                                .collect();
+                            indexes.push((kind.to_string(), names));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+
+    indexes
+}
+
 fn get_nested_attribute(field: &Field) -> Option<(String, Type)> {
     if let Some(ident) = &field.ident {
         for attr in &field.attrs {
@@ -329,6 +459,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn document_primary_type() {
+        let src: DeriveInput = parse_quote! {
+            #[derive(Document)]
+            struct TestDoc {
+                #[document(primary uuid)]
+                id: Option<String>,
+            }
+        };
+
+        let res = derive_document(&src).unwrap();
+
+        assert_eq!(
+            res.to_string(),
+            quote! {
+                impl _ledb_types::Document for TestDoc {
+                    fn primary_field() -> _ledb_types::Identifier {
+                        "id".into()
+                    }
+                    fn primary_type() -> _ledb_types::PrimaryType {
+                        _ledb_types::PrimaryType::Uuid
+                    }
+                }
+            }
+            .to_string()
+        );
+    }
+
     #[test]
     fn document_primary_missing() {
         let src: DeriveInput = parse_quote! {
@@ -489,6 +647,89 @@ mod test {
         );
     }
 
+    #[test]
+    fn document_fulltext_index() {
+        let src: DeriveInput = parse_quote! {
+            #[derive(Document)]
+            struct TestDoc {
+                #[document(primary)]
+                id: u32,
+                #[document(fulltext)]
+                content: String,
+            }
+        };
+
+        let res = derive_document(&src).unwrap();
+
+        assert_eq!(
+            res.to_string(),
+            quote! {
+                impl _ledb_types::Document for TestDoc {
+                    fn primary_field() -> _ledb_types::Identifier {
+                        "id".into()
+                    }
+
+                    fn key_fields() -> _ledb_types::KeyFields {
+                        _ledb_types::KeyFields::new()
+                            .with_field(("content", <String as _ledb_types::DocumentKeyType>::key_type(), _ledb_types::IndexKind::FullText))
+                    }
+                }
+            }.to_string()
+        );
+    }
+
+    #[test]
+    fn document_compound_index() {
+        let src: DeriveInput = parse_quote! {
+            #[derive(Document)]
+            #[document(unique(status, timestamp))]
+            struct TestDoc {
+                #[document(primary)]
+                id: u32,
+                #[document(index)]
+                status: String,
+                #[serde(rename = "created")]
+                timestamp: i64,
+            }
+        };
+
+        let res = derive_document(&src).unwrap();
+
+        assert_eq!(
+            res.to_string(),
+            quote! {
+                impl _ledb_types::Document for TestDoc {
+                    fn primary_field() -> _ledb_types::Identifier {
+                        "id".into()
+                    }
+
+                    fn key_fields() -> _ledb_types::KeyFields {
+                        _ledb_types::KeyFields::new()
+                            .with_field(("status", <String as _ledb_types::DocumentKeyType>::key_type(), _ledb_types::IndexKind::Index))
+                            .with_field(_ledb_types::KeyField::compound(vec![("status", <String as _ledb_types::DocumentKeyType>::key_type()), ("created", <i64 as _ledb_types::DocumentKeyType>::key_type())]).with_kind(_ledb_types::IndexKind::Unique))
+                    }
+                }
+            }.to_string()
+        );
+    }
+
+    #[test]
+    fn document_compound_index_unknown() {
+        let src: DeriveInput = parse_quote! {
+            #[derive(Document)]
+            #[document(index(status, created))]
+            struct TestDoc {
+                #[document(primary)]
+                id: u32,
+                status: String,
+            }
+        };
+
+        let res = derive_document(&src).unwrap_err();
+
+        assert_eq!(res, "Unknown field 'created' of compound index");
+    }
+
     #[test]
     fn document_nested() {
         let src: DeriveInput = parse_quote! {
@@ -23,9 +23,48 @@ struct MyDoc {
 
 This generates `Document` trait implementation for struct `MyDoc`.
 It requires single field marked as primary key per document.
-Currently primary key should be an integer only.
 Also it not needed to be an optional field, but in this case you should take care of parsing (for example add `serde(default)` annotation).
 
+## Choosing primary key type
+
+By default the primary key is a sequential 32-bit integer.
+You may declare the other type of primary key by adding it to primary annotation:
+
+```rust
+# extern crate serde;
+# extern crate ledb;
+#
+use serde::{Serialize, Deserialize};
+use ledb::{Document};
+
+#[derive(Serialize, Deserialize, Document)]
+struct MyEvent {
+    // 128-bit UUID primary key
+    #[document(primary uuid)]
+    id: Option<String>,
+    // other fields
+}
+```
+
+The supported types of primary key is `u32`, `u64`, `string`, `uuid` and `ulid`.
+The collection for such documents should be created using `Storage::collection_with_key()`.
+
+The unknown type of primary key is a compile error:
+
+```compile_fail
+# extern crate serde;
+# extern crate ledb;
+#
+use serde::{Serialize, Deserialize};
+use ledb::{Document};
+
+#[derive(Serialize, Deserialize, Document)]
+struct MyEvent {
+    #[document(primary u128)]
+    id: Option<String>,
+}
+```
+
 ## Defining key fields for indexing
 
 To turn document field into key you can add document index annotation to it:
@@ -52,6 +91,9 @@ struct MyDoc {
     // unique int key
     #[document(unique)]
     timestamp: u64,
+    // full-text index of words
+    #[document(fulltext)]
+    content: String,
 }
 ```
 
@@ -81,6 +123,33 @@ struct MyDoc {
 }
 ```
 
+The geographic points which stored as `[lon, lat]` arrays or `{"lat": .., "lon": ..}` objects can be indexed using geo keys by annotation like `#[document(index geo)]`.
+
+The fields of `SystemTime` type (also `DateTime`, `NaiveDateTime` and `NaiveDate` of `chrono` when `chrono` feature is enabled) are indexed using timestamp keys by default. The dates which stored as RFC 3339 strings or the numbers of seconds since epoch can be indexed same way by annotation like `#[document(index timestamp)]`.
+
+## Compound indexes
+
+The compound index over several fields can be defined using annotation of document itself with the names of fields in order:
+
+```rust
+# extern crate serde;
+# extern crate ledb;
+#
+use serde::{Serialize, Deserialize};
+use ledb::{Document};
+
+#[derive(Serialize, Deserialize, Document)]
+#[document(index(status, created))]
+struct MyDoc {
+    #[document(primary)]
+    id: u32,
+    status: String,
+    created: u64,
+}
+```
+
+Use `#[document(unique(status, created))]` to define unique compound index.
+
 ## Nested documents
 
 Of course you can add nested documents which may also have key fields:
@@ -1,4 +1,13 @@
-export type Primary = number;
+// The integer ids greater than Number.MAX_SAFE_INTEGER are represented by strings
+export type Primary = number | string;
+
+export type PrimaryType
+    = 'u32'
+    | 'u64'
+    | 'string'
+    | 'uuid'
+    | 'ulid'
+    ;
 
 export interface GenericDocument {}
 
@@ -21,21 +30,32 @@ export type KeyType
     | 'bool'
     | 'string'
     | 'binary'
+    | 'geo'
+    | 'timestamp'
     ;
 
-export type KeyData = number | string | boolean | ArrayBufferLike;
+export type KeyData = number | string | boolean | ArrayBufferLike | GeoPoint;
+
+export interface GeoPoint {
+    // latitude in degrees
+    lat: number,
+    // longitude in degrees
+    lon: number,
+}
 
 export type Value = any;
 
-export type IndexKind = 'index' | 'unique';
+export type IndexKind = 'index' | 'unique' | 'fulltext';
 
 export interface KeyField {
-    // field path
+    // field path (or paths separated by comma for compound index)
     path: string,
-    // key type
+    // key type (of the first field for compound index)
     key: KeyType,
     // index kind
     kind: IndexKind,
+    // key types of the rest fields of compound index
+    rest?: KeyType[],
 }
 
 export type KeyFields = KeyField[];
@@ -64,6 +84,12 @@ export type FilterComp
     | FilterGt
     | FilterGe
     | FilterBw
+    | FilterPrefix
+    | FilterRegex
+    | FilterLike
+    | FilterText
+    | FilterNear
+    | FilterWithin
     | FilterHas
     ;
 
@@ -74,6 +100,35 @@ export interface FilterLe { $le: KeyData }
 export interface FilterGt { $gt: KeyData }
 export interface FilterGe { $ge: KeyData }
 export interface FilterBw { $in: [KeyData, boolean, KeyData, boolean] }
+export interface FilterPrefix { $prefix: KeyData }
+export interface FilterRegex { $regex: string }
+export interface FilterLike { $like: string }
+export interface FilterText { $text: TextQuery }
+export interface FilterNear { $near: GeoNear }
+export interface FilterWithin { $within: GeoShape }
+
+export interface TextQuery {
+    // words to search
+    text: string,
+    // search any of words instead of all words
+    any?: boolean,
+    // skip the stop words
+    skip_stop_words?: boolean,
+}
+
+export interface GeoNear {
+    // center of circle
+    point: GeoPoint,
+    // radius of circle in meters
+    radius: number,
+}
+
+export type GeoShape
+    // box between south-west and north-east corners
+    = { $box: [GeoPoint, GeoPoint] }
+    // polygon with vertices
+    | { $polygon: GeoPoint[] }
+    ;
 
 export type FilterHas = '$has';
 
@@ -81,13 +136,55 @@ export type FilterNone = null;
 
 export type Order
     = OrderByPrimary
-    | OrderByField;
+    | OrderByField
+    | OrderByFields
+    | OrderByRelevance
+    | OrderByDistance;
 
 export type OrderByPrimary = OrderKind;
-export type OrderByField = [string, OrderKind];
+export type OrderByField = { [field: string]: OrderKind };
+export type OrderByFields = OrderByField[];
+export type OrderByRelevance = { $relevance: string };
+export type OrderByDistance = { $distance: { [field: string]: GeoPoint } };
 
 export type OrderKind = '$asc' | '$desc';
 
+export type Projection
+    = ProjectionInclude
+    | ProjectionExclude;
+
+// keep the specified fields only
+export type ProjectionInclude = { $include: string[] };
+// remove the specified fields
+export type ProjectionExclude = { $exclude: string[] };
+
+export interface Lookup {
+    // collection of referenced documents
+    from: string,
+    // field which contains the references
+    local: string,
+    // indexed field of referenced documents (primary key by default)
+    foreign?: string,
+    // field to embed the referenced documents into
+    path: string,
+}
+
+export interface Page {
+    // number of documents to skip
+    skip?: number,
+    // max number of documents
+    limit?: number,
+    // start after the document at position
+    after?: Position,
+}
+
+export interface Position {
+    // key of ordering field
+    key?: KeyData,
+    // primary key of document
+    id: Primary,
+}
+
 export type Modify = [string, Action][];
 
 export type Action
@@ -116,6 +213,30 @@ export interface ActionReplace { $replace: [string, string] }
 export interface ActionSplice { $splice: [number, number, ...Value[]] }
 export interface ActionMerge { $merge: Value }
 
+export type Accumulators = { [name: string]: Accumulator };
+
+export type Accumulator
+    = AccumulatorCount
+    | AccumulatorSum
+    | AccumulatorAvg
+    | AccumulatorMin
+    | AccumulatorMax
+    | AccumulatorFirst
+    | AccumulatorLast
+    ;
+
+export type AccumulatorCount = '$count';
+
+export interface AccumulatorSum { $sum: string }
+export interface AccumulatorAvg { $avg: string }
+export interface AccumulatorMin { $min: string }
+export interface AccumulatorMax { $max: string }
+export interface AccumulatorFirst { $first: string }
+export interface AccumulatorLast { $last: string }
+
+// Aggregated group
+export type Group = { [field: string]: Value };
+
 // Storage info
 export interface Info {
     map_size: number,
@@ -135,12 +256,49 @@ export interface Stats {
     data_entries: number,
 }
 
+// Storage map usage
+export interface Usage {
+    map_size: number,
+    used_size: number,
+    map_size_limit: number | null,
+}
+
+// Index description
+export interface IndexDescription {
+    path: string,
+    kind: IndexKind,
+    key: KeyType,
+    rest?: KeyType[],
+    created: number | null,
+    entries: number,
+}
+
+// Collection description
+export interface CollectionDescription {
+    name: string,
+    primary: PrimaryType,
+    sequence: number,
+    created: number | null,
+    documents: number,
+    indexes: IndexDescription[],
+}
+
+// Storage description
+export interface StorageDescription {
+    version: number,
+    created: number | null,
+    collections: CollectionDescription[],
+}
+
 // Storage options
 export interface Options {
     // options
     map_size?: number,
     max_readers?: number,
     max_dbs?: number,
+    // growth
+    map_grow_factor?: number,
+    map_size_limit?: number,
     // flags
     map_async?: boolean,
     no_lock?: boolean,
@@ -160,19 +318,29 @@ export class Storage {
     
     get_info(): Info;
     get_stats(): Stats;
+    get_usage(): Usage;
+    describe(): StorageDescription;
 
     has_collection(name: string): boolean;
-    collection(name: string): Collection;
+    collection(name: string, key?: PrimaryType): Collection;
     drop_collection(name: string): boolean;
+    rename_collection(old_name: string, new_name: string): void;
+    copy_collection(src_name: string, dst_name: string): void;
     get_collections(): string[];
+
+    backup(path: string, compact?: boolean): void;
 }
 
 // Collection handle interface
 export class Collection {
-    constructor(storage: Storage, name: string);
+    constructor(storage: Storage, name: string, key?: PrimaryType);
     
     insert<T extends GenericDocument>(doc: T): Primary;
-    find<T extends GenericDocument>(filter: Filter, order?: Order): Documents<T>;
+    find<T extends GenericDocument>(filter: Filter, order?: Order, page?: Page, project?: Projection, lookup?: Lookup[]): Documents<T>;
+    count(filter?: Filter): number;
+    aggregate(filter: Filter, group: string[], fields: Accumulators): Group[];
+    distinct(field: string, filter?: Filter): [KeyData, number][];
+    facets(field: string, filter?: Filter, limit?: number): [KeyData, number][];
     update(filter: Filter, modify: Modify): number;
     remove(filter: Filter): number;
 
@@ -186,10 +354,12 @@ export class Collection {
     put<T extends GenericDocument>(doc: T): void;
     delete(id: Primary): boolean;
 
+    describe(): CollectionDescription;
+
     get_indexes(): KeyFields;
     set_indexes(indexes: KeyFields): void;
     has_index(path: string): void;
-    ensure_index(path: string, kind: IndexKind, key: KeyType): boolean;
+    ensure_index(path: string, kind: IndexKind, key: KeyType | KeyType[]): boolean;
     drop_index(path: string): boolean;
 }
 
@@ -1,22 +1,59 @@
-use std::u32;
-
 use neon::prelude::*;
 use neon_serde::{from_value, to_value};
 
-use ledb::{Collection, Filter, Identifier, IndexKind, KeyType, Modify, Order, Primary, Value};
+use ledb::{Accumulators, Collection, Filter, Identifier, IndexKind, KeyType, Lookup, Modify, Order, Page, Primary, PrimaryType, Projection, Value};
 
 use super::{JsDocuments, JsStorage, refine};
 
+/// The max integer which can be represented exactly by number
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+fn id_argument(cx: &mut MethodContext<JsCollection>, i: i32) -> NeonResult<Primary> {
+    let id = cx.argument::<JsValue>(i)?;
+
+    if let Ok(id) = id.downcast::<JsNumber>() {
+        let id = id.value();
+
+        if id < 1.0 || id > MAX_SAFE_INTEGER || id.fract() != 0.0 {
+            return cx.throw_range_error("Document id must be in range 1..N");
+        }
+
+        Ok(Primary::Int(id as u64))
+    } else if let Ok(id) = id.downcast::<JsString>() {
+        Ok(Primary::String(id.value()))
+    } else {
+        cx.throw_type_error("Document id must be a number or a string")
+    }
+}
+
+fn id_value<'a, C: Context<'a>>(cx: &mut C, id: Primary) -> Handle<'a, JsValue> {
+    match id {
+        // the ids which cannot be represented exactly by number are returned as strings
+        Primary::Int(id) if id > MAX_SAFE_INTEGER as u64 => cx.string(id.to_string()).upcast(),
+        Primary::Int(id) => cx.number(id as f64).upcast(),
+        Primary::String(id) => cx.string(id).upcast(),
+    }
+}
+
 declare_types! {
     /// A collection class
     pub class JsCollection for Collection {
         init(mut cx) {
             let storage = cx.argument::<JsStorage>(0)?;
             let name = cx.argument::<JsString>(1)?.value();
+            let key: Option<PrimaryType> = if let Some(key) = cx.argument_opt(2) {
+                from_value(&mut cx, key)?
+            } else {
+                None
+            };
             let collection = js_try!(cx, {
                 let guard = cx.lock();
                 let storage = storage.borrow(&guard);
-                storage.collection(&name)
+                if let Some(key) = key {
+                    storage.collection_with_key(&name, key)
+                } else {
+                    storage.collection(&name)
+                }
             });
             Ok(collection)
         }
@@ -33,7 +70,7 @@ declare_types! {
                 collection.insert(&doc)
             });
 
-            Ok(cx.number(id).upcast())
+            Ok(id_value(&mut cx, id))
         }
 
         method find(mut cx) {
@@ -49,14 +86,39 @@ declare_types! {
                 Order::default()
             };
 
+            let page: Page = if let Some(page) = cx.argument_opt(2) {
+                from_value(&mut cx, page)?
+            } else {
+                Page::default()
+            };
+
+            let project: Option<Projection> = if let Some(project) = cx.argument_opt(3) {
+                from_value(&mut cx, project)?
+            } else {
+                None
+            };
+
+            let lookups: Vec<Lookup> = if let Some(lookups) = cx.argument_opt(4) {
+                from_value(&mut cx, lookups)?
+            } else {
+                Vec::new()
+            };
+
             let this = cx.this();
 
             let iter = js_try!(cx, {
                 let guard = cx.lock();
                 let collection = this.borrow(&guard);
-                collection.find(filter, order)
+                collection.find_page(filter, order, page)
+                    .and_then(|iter| lookups.into_iter().try_fold(iter, |iter, lookup| iter.lookup(lookup)))
             });
 
+            let iter = if let Some(project) = project {
+                iter.project(project)
+            } else {
+                iter
+            };
+
             let mut docs = JsDocuments::new(&mut cx, vec![JsUndefined::new()])?;
 
             {
@@ -68,6 +130,99 @@ declare_types! {
             Ok(docs.upcast())
         }
 
+        method count(mut cx) {
+            let filter: Option<Filter> = if let Some(filter) = cx.argument_opt(0) {
+                from_value(&mut cx, filter)?
+            } else {
+                None
+            };
+
+            let this = cx.this();
+
+            let count = js_try!(cx, {
+                let guard = cx.lock();
+                let collection = this.borrow(&guard);
+                collection.count(filter)
+            });
+
+            Ok(cx.number(count as f64).upcast())
+        }
+
+        method aggregate(mut cx) {
+            let filter: Option<Filter> = if let Some(filter) = cx.argument_opt(0) {
+                from_value(&mut cx, filter)?
+            } else {
+                None
+            };
+
+            let group: Vec<String> = if let Some(group) = cx.argument_opt(1) {
+                from_value(&mut cx, group)?
+            } else {
+                Vec::new()
+            };
+
+            let fields_raw = cx.argument(2)?;
+            let fields: Accumulators = from_value(&mut cx, fields_raw)?;
+
+            let this = cx.this();
+
+            let groups = js_try!(cx, {
+                let guard = cx.lock();
+                let collection = this.borrow(&guard);
+                collection.aggregate(filter, group.into_iter().map(Identifier::from).collect(), fields)
+            });
+
+            let groups: Vec<Value> = groups.into_iter().map(refine).collect();
+
+            Ok(js_try!(cx, to_value(&mut cx, &groups)).upcast())
+        }
+
+        method distinct(mut cx) {
+            let field = cx.argument::<JsString>(0)?.value();
+
+            let filter: Option<Filter> = if let Some(filter) = cx.argument_opt(1) {
+                from_value(&mut cx, filter)?
+            } else {
+                None
+            };
+
+            let this = cx.this();
+
+            let keys = js_try!(cx, {
+                let guard = cx.lock();
+                let collection = this.borrow(&guard);
+                collection.distinct(&field, filter)
+            });
+
+            Ok(js_try!(cx, to_value(&mut cx, &keys)).upcast())
+        }
+
+        method facets(mut cx) {
+            let field = cx.argument::<JsString>(0)?.value();
+
+            let filter: Option<Filter> = if let Some(filter) = cx.argument_opt(1) {
+                from_value(&mut cx, filter)?
+            } else {
+                None
+            };
+
+            let limit: Option<usize> = if let Some(limit) = cx.argument_opt(2) {
+                from_value(&mut cx, limit)?
+            } else {
+                None
+            };
+
+            let this = cx.this();
+
+            let keys = js_try!(cx, {
+                let guard = cx.lock();
+                let collection = this.borrow(&guard);
+                collection.facets(&field, filter, limit)
+            });
+
+            Ok(js_try!(cx, to_value(&mut cx, &keys)).upcast())
+        }
+
         method update(mut cx) {
             let filter: Option<Filter> = if let Some(filter) = cx.argument_opt(0) {
                 from_value(&mut cx, filter)?
@@ -86,7 +241,7 @@ declare_types! {
                 collection.update(filter, modify)
             });
 
-            Ok(cx.number(affected as u32).upcast())
+            Ok(cx.number(affected as f64).upcast())
         }
 
         method remove(mut cx) {
@@ -104,7 +259,7 @@ declare_types! {
                 collection.remove(filter)
             });
 
-            Ok(cx.number(affected as u32).upcast())
+            Ok(cx.number(affected as f64).upcast())
         }
 
         method dump(mut cx) {
@@ -142,13 +297,7 @@ declare_types! {
         }
 
         method has(mut cx) {
-            let id = cx.argument::<JsNumber>(0)?.value();
-
-            if id < 1.0 || id > f64::from(u32::MAX) {
-                return cx.throw_range_error("Document id must be in range 1..N");
-            }
-
-            let id: Primary = id as Primary;
+            let id = id_argument(&mut cx, 0)?;
 
             let this = cx.this();
 
@@ -162,13 +311,7 @@ declare_types! {
         }
 
         method get(mut cx) {
-            let id = cx.argument::<JsNumber>(0)?.value();
-
-            if id < 1.0 || id > f64::from(u32::MAX) {
-                return cx.throw_range_error("Document id must be in range 1..N");
-            }
-
-            let id: Primary = id as Primary;
+            let id = id_argument(&mut cx, 0)?;
 
             let this = cx.this();
 
@@ -199,13 +342,7 @@ declare_types! {
         }
 
         method delete(mut cx) {
-            let id = cx.argument::<JsNumber>(0)?.value();
-
-            if id < 1.0 || id > f64::from(u32::MAX) {
-                return cx.throw_range_error("Document id must be in range 1..N");
-            }
-
-            let id: Primary = id as Primary;
+            let id = id_argument(&mut cx, 0)?;
 
             let this = cx.this();
 
@@ -218,6 +355,18 @@ declare_types! {
             Ok(cx.boolean(status).upcast())
         }
 
+        method describe(mut cx) {
+            let this = cx.this();
+
+            let desc = js_try!(cx, {
+                let guard = cx.lock();
+                let collection = this.borrow(&guard);
+                collection.describe()
+            });
+
+            Ok(js_try!(cx, to_value(&mut cx, &desc)).upcast())
+        }
+
         method get_indexes(mut cx) {
             let this = cx.this();
 
@@ -261,8 +410,13 @@ declare_types! {
             let path = cx.argument::<JsString>(0)?.value();
             let kind = cx.argument(1)?;
             let kind = from_value(&mut cx, kind)?;
-            let key = cx.argument(2)?;
-            let key = from_value(&mut cx, key)?;
+            let key = cx.argument::<JsValue>(2)?;
+            // the key types of compound index
+            let key: Vec<KeyType> = if key.downcast::<JsArray>().is_ok() {
+                from_value(&mut cx, key)?
+            } else {
+                vec![from_value(&mut cx, key)?]
+            };
 
             let this = cx.this();
 
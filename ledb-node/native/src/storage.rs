@@ -38,6 +38,26 @@ declare_types! {
             Ok(to_value(&mut cx, &stats)?)
         }
 
+        method get_usage(mut cx) {
+            let this = cx.this();
+            let usage = js_try!(cx, {
+                let guard = cx.lock();
+                let storage = this.borrow(&guard);
+                storage.get_usage()
+            });
+            Ok(to_value(&mut cx, &usage)?)
+        }
+
+        method describe(mut cx) {
+            let this = cx.this();
+            let desc = js_try!(cx, {
+                let guard = cx.lock();
+                let storage = this.borrow(&guard);
+                storage.describe()
+            });
+            Ok(js_try!(cx, to_value(&mut cx, &desc)))
+        }
+
         method has_collection(mut cx) {
             let name = cx.argument::<JsString>(0)?.value();
             let this = cx.this();
@@ -52,7 +72,11 @@ declare_types! {
         method collection(mut cx) {
             let name = cx.argument::<JsString>(0)?;
             let this = cx.this();
-            Ok(JsCollection::new(&mut cx, vec![this.upcast::<JsValue>(), name.upcast::<JsValue>()])?.upcast())
+            let mut args = vec![this.upcast::<JsValue>(), name.upcast::<JsValue>()];
+            if let Some(key) = cx.argument_opt(1) {
+                args.push(key);
+            }
+            Ok(JsCollection::new(&mut cx, args)?.upcast())
         }
 
         method drop_collection(mut cx) {
@@ -66,6 +90,52 @@ declare_types! {
             Ok(cx.boolean(has).upcast())
         }
 
+        method backup(mut cx) {
+            let path = cx.argument::<JsString>(0)?.value();
+            let compact: bool = if let Some(compact) = cx.argument_opt(1) {
+                from_value(&mut cx, compact)?
+            } else {
+                false
+            };
+            let this = cx.this();
+
+            js_try!(cx, {
+                let guard = cx.lock();
+                let storage = this.borrow(&guard);
+                storage.backup(&path, compact)
+            });
+
+            Ok(cx.undefined().upcast())
+        }
+
+        method rename_collection(mut cx) {
+            let old = cx.argument::<JsString>(0)?.value();
+            let new = cx.argument::<JsString>(1)?.value();
+            let this = cx.this();
+
+            js_try!(cx, {
+                let guard = cx.lock();
+                let storage = this.borrow(&guard);
+                storage.rename_collection(&old, &new)
+            });
+
+            Ok(cx.undefined().upcast())
+        }
+
+        method copy_collection(mut cx) {
+            let src = cx.argument::<JsString>(0)?.value();
+            let dst = cx.argument::<JsString>(1)?.value();
+            let this = cx.this();
+
+            js_try!(cx, {
+                let guard = cx.lock();
+                let storage = this.borrow(&guard);
+                storage.copy_collection(&src, &dst)
+            });
+
+            Ok(cx.undefined().upcast())
+        }
+
         method get_collections(mut cx) {
             let this = cx.this();
             let list = js_try!(cx, {
@@ -115,6 +115,86 @@ describe('collection', () => {
         dse(coll.find({ tag: { $eq: "Foo" } }).count(), 3);
         dse(coll.find({ $or: [{ title: { $eq: "Foo" } }, { title: { $eq: "Bar" } }] }).count(), 2);
         dse(coll.find({ $not: { title: { $eq: "Foo" } } }).count(), 3);
+        dse(coll.find({ title: { $prefix: "Ba" } }).count(), 2);
+        dse(coll.find({ tag: { $prefix: "B" } }).count(), 3);
+        dse(coll.find({ title: { $regex: "(?i)^ba" } }).count(), 2);
+        dse(coll.find({ title: { $like: "%a_" } }).count(), 2);
+        dse(coll.find(null, [{ timestamp: "$asc" }, { title: "$asc" }]).next().$, 4);
+        dse(coll.find(null, [{ timestamp: "$asc" }, { title: "$desc" }]).next().$, 3);
+        dse(coll.find({ title: { $eq: "Baz" } }, "$asc", {}, { $include: ["title"] }).next(), { $: 3, title: "Baz" });
+        dse(coll.find({ title: { $eq: "Baz" } }, "$asc", {}, { $exclude: ["tag"] }).next(), { $: 3, title: "Baz", timestamp: 1234567819 });
+        dse(coll.count(null), 4);
+        dse(coll.count({ tag: { $eq: "Foo" } }), 3);
+        dse(coll.count({ $not: { title: { $eq: "Foo" } } }), 3);
+    });
+
+    it('aggregate', () => {
+        const coll = storage.collection("post");
+
+        dse(coll.aggregate(null, ["tag"], { count: "$count" }), [
+            { tag: "Bar", count: 2 },
+            { tag: "Baz", count: 2 },
+            { tag: "Eff", count: 1 },
+            { tag: "Foo", count: 3 },
+        ]);
+        dse(coll.aggregate({ tag: { $eq: "Foo" } }, [], { count: "$count", since: { $min: "timestamp" } }), [
+            { count: 3, since: 1234567819 },
+        ]);
+    });
+
+    it('distinct facets', () => {
+        const coll = storage.collection("post");
+
+        dse(coll.distinct("tag"), [["Bar", 2], ["Baz", 2], ["Eff", 1], ["Foo", 3]]);
+        dse(coll.distinct("tag", { timestamp: { $gt: 1234567819 } }), [["Bar", 1], ["Baz", 2], ["Foo", 1]]);
+        dse(coll.facets("tag", null, 2), [["Foo", 3], ["Bar", 2]]);
+    });
+
+    it('compound index', () => {
+        const coll = storage.collection("post");
+
+        dse(coll.ensure_index("tag,timestamp", "index", ["string", "int"]), true);
+        dse(coll.has_index("tag,timestamp"), true);
+        dse(coll.find({ $and: [{ tag: { $eq: "Foo" } }, { timestamp: { $gt: 1234567819 } }] }).count(), 1);
+        dse(coll.find({ tag: { $eq: "Foo" } }, [{ tag: "$desc" }, { timestamp: "$desc" }]).next().$, 2);
+        dse(coll.drop_index("tag,timestamp"), true);
+    });
+
+    it('fulltext index', () => {
+        const coll = storage.collection("note");
+
+        coll.insert({ text: "The quick brown fox" });
+        coll.insert({ text: "A lazy brown dog" });
+        coll.insert({ text: "The fox and the hound" });
+
+        dse(coll.ensure_index("text", "fulltext", "string"), true);
+        dse(coll.find({ text: { $text: { text: "Brown fox" } } }).count(), 1);
+        dse(coll.find({ text: { $text: { text: "fox dog", any: true } } }).count(), 3);
+        dse(coll.find({ text: { $text: { text: "hound brown", any: true } } }, { $relevance: "text" }).next().$, 3);
+    });
+
+    it('geo index', () => {
+        const coll = storage.collection("place");
+
+        coll.insert({ loc: { lat: 55.7539, lon: 37.6208 } });
+        coll.insert({ loc: [37.5307, 55.7033] });
+        coll.insert({ loc: [-0.1278, 51.5074] });
+
+        dse(coll.ensure_index("loc", "index", "geo"), true);
+        dse(coll.find({ loc: { $near: { point: { lat: 55.75, lon: 37.62 }, radius: 1000 } } }).count(), 1);
+        dse(coll.find({ loc: { $within: { $box: [{ lat: 55, lon: 37 }, { lat: 56, lon: 38 }] } } }).count(), 2);
+        dse(coll.find(null, { $distance: { loc: { lat: 51.5, lon: 0 } } }).next().$, 3);
+    });
+
+    it('lookup', () => {
+        const authors = storage.collection("author");
+        const books = storage.collection("book");
+
+        authors.insert({ name: "Leo" });
+        books.insert({ title: "War and Peace", author_id: 1 });
+
+        dse(books.find(null, "$asc", {}, null, [{ from: "author", local: "author_id", path: "author" }]).next(),
+            { $: 1, title: "War and Peace", author_id: 1, author: [{ $: 1, name: "Leo" }] });
     });
 
     // TODO: more tests
@@ -167,4 +247,14 @@ describe('documents', () => {
         dse(coll.find(null).take(3).skip(1).count(), 2);
         dse(coll.find(null).take(2).skip(1).count(), 1);
     });
+
+    it('page', () => {
+        const coll = storage.collection("post");
+
+        dse(coll.find(null, "$asc", { skip: 1, limit: 2 }).count(), 2);
+        dse(coll.find(null, "$asc", { skip: 1, limit: 2 }).next().$, 2);
+        dse(coll.find(null, "$asc", { after: { id: 2 } }).count(), 2);
+        dse(coll.find(null, "$desc", { after: { id: 3 }, limit: 1 }).next().$, 2);
+        dse(coll.find(null, "$desc", { after: { id: 1 } }).count(), 0);
+    });
 });
@@ -6,6 +6,7 @@ use std::{
     ops::{Deref, DerefMut},
     rc::{Rc, Weak as RcWeak},
     sync::{Arc, Mutex, RwLock, Weak as ArcWeak},
+    time::SystemTime,
     vec::IntoIter as VecIntoIter,
 };
 
@@ -14,7 +15,13 @@ use serde::{Serialize, Deserialize};
 #[cfg(feature = "bytes")]
 use bytes::{Bytes, BytesMut};
 
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone};
+
 /// Indexed field definition
+///
+/// The compound index over several fields has the paths of that fields separated by comma, like `status,created`. The key type of the first field is `key` and the key types of the rest fields is `rest`.
+///
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KeyField {
     pub path: String,
@@ -22,6 +29,8 @@ pub struct KeyField {
     pub key: KeyType,
     #[serde(default)]
     pub kind: IndexKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rest: Vec<KeyType>,
 }
 
 impl KeyField {
@@ -32,7 +41,22 @@ impl KeyField {
             path: path.to_string(),
             key: KeyType::default(),
             kind: IndexKind::default(),
+            rest: Vec::new(),
+        }
+    }
+
+    /// Create key field of compound index from the field names with key types
+    pub fn compound<S: ToString, I: IntoIterator<Item = (S, KeyType)>>(fields: I) -> Self {
+        let mut fields = fields.into_iter();
+        let mut field = if let Some((path, key)) = fields.next() {
+            Self::new(path).with_type(key)
+        } else {
+            Self::new("")
+        };
+        for (path, key) in fields {
+            field = field.with_next(path, key);
         }
+        field
     }
 
     /// Add key type
@@ -49,13 +73,41 @@ impl KeyField {
         self
     }
 
+    /// Add next field to compound index
+    #[inline]
+    pub fn with_next<S: ToString>(mut self, path: S, key: KeyType) -> Self {
+        self.path.push(',');
+        self.path.push_str(&path.to_string());
+        self.rest.push(key);
+        self
+    }
+
+    /// Check that the index is compound
+    #[inline]
+    pub fn is_compound(&self) -> bool {
+        !self.rest.is_empty()
+    }
+
+    /// Get the key types of all fields
+    pub fn keys(&self) -> Vec<KeyType> {
+        let mut keys = Vec::with_capacity(1 + self.rest.len());
+        keys.push(self.key);
+        keys.extend(&self.rest);
+        keys
+    }
+
     /// Set parent path
     ///
     /// This makes key field to be child for parent path
     #[inline]
     pub fn set_parent<S: AsRef<str>>(&mut self, parent: S) {
-        self.path.insert_str(0, ".");
-        self.path.insert_str(0, parent.as_ref());
+        let parent = parent.as_ref();
+        self.path = self
+            .path
+            .split(',')
+            .map(|path| format!("{}.{}", parent, path))
+            .collect::<Vec<_>>()
+            .join(",");
     }
 
     /// Add parent path
@@ -110,6 +162,7 @@ impl<S: ToString> From<(S, KeyType, IndexKind)> for KeyField {
             path: path.to_string(),
             key,
             kind,
+            rest: Vec::new(),
         }
     }
 }
@@ -120,6 +173,7 @@ impl<'a, S: ToString> From<&'a (S, KeyType, IndexKind)> for KeyField {
             path: path.to_string(),
             key: *key,
             kind: *kind,
+            rest: Vec::new(),
         }
     }
 }
@@ -130,6 +184,7 @@ impl<S: ToString> From<(S, IndexKind, KeyType)> for KeyField {
             path: path.to_string(),
             key,
             kind,
+            rest: Vec::new(),
         }
     }
 }
@@ -140,20 +195,21 @@ impl<'a, S: ToString> From<&'a (S, IndexKind, KeyType)> for KeyField {
             path: path.to_string(),
             key: *key,
             kind: *kind,
+            rest: Vec::new(),
         }
     }
 }
 
 impl Into<(String, KeyType, IndexKind)> for KeyField {
     fn into(self) -> (String, KeyType, IndexKind) {
-        let KeyField { path, key, kind } = self;
+        let KeyField { path, key, kind, .. } = self;
         (path, key, kind)
     }
 }
 
 impl Into<(String, IndexKind, KeyType)> for KeyField {
     fn into(self) -> (String, IndexKind, KeyType) {
-        let KeyField { path, key, kind } = self;
+        let KeyField { path, key, kind, .. } = self;
         (path, kind, key)
     }
 }
@@ -291,6 +347,10 @@ pub enum KeyType {
     Binary,
     #[serde(rename = "bool")]
     Bool,
+    #[serde(rename = "geo")]
+    Geo,
+    #[serde(rename = "timestamp")]
+    Timestamp,
 }
 
 impl Default for KeyType {
@@ -299,6 +359,13 @@ impl Default for KeyType {
     }
 }
 
+/// Single key type may be used where the key types of compound index is expected
+impl AsRef<[KeyType]> for KeyType {
+    fn as_ref(&self) -> &[KeyType] {
+        std::slice::from_ref(self)
+    }
+}
+
 /// The kind of index
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IndexKind {
@@ -308,6 +375,9 @@ pub enum IndexKind {
     /// Index which contains unique keys only
     #[serde(rename = "unique")]
     Unique,
+    /// Index which contains the words of text
+    #[serde(rename = "fulltext")]
+    FullText,
 }
 
 impl Default for IndexKind {
@@ -396,6 +466,33 @@ impl DocumentKeyType for String {
     }
 }
 
+impl DocumentKeyType for SystemTime {
+    fn key_type() -> KeyType {
+        KeyType::Timestamp
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: TimeZone> DocumentKeyType for DateTime<Tz> {
+    fn key_type() -> KeyType {
+        KeyType::Timestamp
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl DocumentKeyType for NaiveDateTime {
+    fn key_type() -> KeyType {
+        KeyType::Timestamp
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl DocumentKeyType for NaiveDate {
+    fn key_type() -> KeyType {
+        KeyType::Timestamp
+    }
+}
+
 #[cfg(feature = "bytes")]
 impl DocumentKeyType for Bytes {
     fn key_type() -> KeyType {
@@ -0,0 +1,155 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Primary key (document identifier)
+///
+/// The integer keys is used by collections with sequential identifiers (`u32` and `u64`), the string keys is used by collections with string, UUID and ULID identifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Primary {
+    Int(u64),
+    String(String),
+}
+
+impl Primary {
+    /// Get integer value of key if it is integer
+    pub fn as_int(&self) -> Option<u64> {
+        if let Primary::Int(id) = self {
+            Some(*id)
+        } else {
+            None
+        }
+    }
+
+    /// Get string value of key if it is string
+    pub fn as_str(&self) -> Option<&str> {
+        if let Primary::String(id) = self {
+            Some(id)
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialOrd for Primary {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Primary {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use self::Primary::*;
+        match (self, other) {
+            (Int(a), Int(b)) => a.cmp(b),
+            (String(a), String(b)) => a.cmp(b),
+            (Int(_), String(_)) => Ordering::Less,
+            (String(_), Int(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl Display for Primary {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        use self::Primary::*;
+        match self {
+            Int(id) => id.fmt(f),
+            String(id) => id.fmt(f),
+        }
+    }
+}
+
+impl Default for Primary {
+    fn default() -> Self {
+        Primary::Int(0)
+    }
+}
+
+impl From<u64> for Primary {
+    fn from(id: u64) -> Self {
+        Primary::Int(id)
+    }
+}
+
+impl From<String> for Primary {
+    fn from(id: String) -> Self {
+        Primary::String(id)
+    }
+}
+
+impl<'a> From<&'a str> for Primary {
+    fn from(id: &'a str) -> Self {
+        Primary::String(id.into())
+    }
+}
+
+impl<'a> From<&'a Primary> for Primary {
+    fn from(id: &'a Primary) -> Self {
+        id.clone()
+    }
+}
+
+impl PartialEq<u64> for Primary {
+    fn eq(&self, other: &u64) -> bool {
+        self.as_int() == Some(*other)
+    }
+}
+
+impl PartialEq<str> for Primary {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Primary {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
+
+/// The type of primary key
+///
+/// Each collection uses the single type of primary keys which is chosen when collection created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PrimaryType {
+    /// Sequential 32-bit unsigned integer (default)
+    #[default]
+    #[serde(rename = "u32")]
+    U32,
+    /// Sequential 64-bit unsigned integer
+    #[serde(rename = "u64")]
+    U64,
+    /// Arbitrary string
+    #[serde(rename = "string")]
+    String,
+    /// 128-bit UUID in canonical hyphenated form
+    #[serde(rename = "uuid")]
+    Uuid,
+    /// 128-bit ULID in canonical base32 form
+    #[serde(rename = "ulid")]
+    Ulid,
+}
+
+impl PrimaryType {
+    /// Check that keys of this type is generated from the sequence
+    pub fn is_sequential(self) -> bool {
+        matches!(self, PrimaryType::U32 | PrimaryType::U64)
+    }
+}
+
+impl Display for PrimaryType {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        use self::PrimaryType::*;
+        f.write_str(match self {
+            U32 => "u32",
+            U64 => "u64",
+            String => "string",
+            Uuid => "uuid",
+            Ulid => "ulid",
+        })
+    }
+}
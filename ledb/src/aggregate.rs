@@ -0,0 +1,373 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    result::Result as StdResult,
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{extract_field_list, Identifier, KeyData, Value};
+
+/// Accumulator of aggregation
+///
+/// The accumulators computes the single value using the documents of group.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Accumulator {
+    /// The number of documents
+    #[serde(rename = "$count")]
+    Count,
+    /// The sum of numeric values of field
+    ///
+    /// The sum is integer when all values are integers, and float when some values are floats or the integer sum overflows.
+    ///
+    #[serde(rename = "$sum", deserialize_with = "identifier")]
+    Sum(Identifier),
+    /// The average of numeric values of field
+    #[serde(rename = "$avg", deserialize_with = "identifier")]
+    Avg(Identifier),
+    /// The minimum value of field
+    #[serde(rename = "$min", deserialize_with = "identifier")]
+    Min(Identifier),
+    /// The maximum value of field
+    #[serde(rename = "$max", deserialize_with = "identifier")]
+    Max(Identifier),
+    /// The value of field of the first document
+    #[serde(rename = "$first", deserialize_with = "identifier")]
+    First(Identifier),
+    /// The value of field of the last document
+    #[serde(rename = "$last", deserialize_with = "identifier")]
+    Last(Identifier),
+}
+
+impl Accumulator {
+    /// Get the field which values are accumulated
+    pub fn field(&self) -> Option<&Identifier> {
+        use self::Accumulator::*;
+        match self {
+            Count => None,
+            Sum(field) | Avg(field) | Min(field) | Max(field) | First(field) | Last(field) => {
+                Some(field)
+            }
+        }
+    }
+}
+
+/// The accumulators of aggregation
+///
+/// The keys are the names of fields of the aggregated groups.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Accumulators(pub HashMap<Identifier, Accumulator>);
+
+impl<'de> Deserialize<'de> for Accumulators {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        let map: HashMap<String, Accumulator> = HashMap::deserialize(deserializer)?;
+        Ok(Accumulators(
+            map.into_iter()
+                .map(|(name, acc)| (name.into(), acc))
+                .collect(),
+        ))
+    }
+}
+
+impl Accumulators {
+    /// Create empty accumulators
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append accumulator
+    pub fn add<I: Into<Identifier>>(&mut self, name: I, acc: Accumulator) {
+        self.0.insert(name.into(), acc);
+    }
+
+    /// Add accumulator
+    pub fn with<I: Into<Identifier>>(mut self, name: I, acc: Accumulator) -> Self {
+        self.add(name, acc);
+        self
+    }
+
+    /// Check that the documents should be loaded to accumulate values
+    pub(crate) fn needs_documents(&self) -> bool {
+        self.0.values().any(|acc| acc.field().is_some())
+    }
+}
+
+fn identifier<'de, D: Deserializer<'de>>(deserializer: D) -> StdResult<Identifier, D::Error> {
+    String::deserialize(deserializer).map(Identifier::from)
+}
+
+/// The intermediate state of accumulator
+#[derive(Debug, Clone)]
+enum State {
+    Count(usize),
+    Sum(i64, f64, bool),
+    Avg(f64, usize),
+    Min(Option<KeyData>),
+    Max(Option<KeyData>),
+    First(Option<Vec<KeyData>>),
+    Last(Option<Vec<KeyData>>),
+}
+
+impl State {
+    fn new(acc: &Accumulator) -> Self {
+        use self::Accumulator::*;
+        match acc {
+            Count => State::Count(0),
+            Sum(..) => State::Sum(0, 0.0, false),
+            Avg(..) => State::Avg(0.0, 0),
+            Min(..) => State::Min(None),
+            Max(..) => State::Max(None),
+            First(..) => State::First(None),
+            Last(..) => State::Last(None),
+        }
+    }
+
+    fn update(&mut self, field: Option<&Identifier>, doc: Option<&Value>) {
+        use self::State::*;
+
+        if let Count(count) = self {
+            *count += 1;
+            return;
+        }
+
+        let vals = match (field, doc) {
+            (Some(field), Some(doc)) => extract_field_list(doc, field),
+            _ => return,
+        };
+
+        match self {
+            Count(..) => unreachable!(),
+            Sum(int, float, is_float) => {
+                for val in vals {
+                    match val {
+                        KeyData::Int(val) => match int.checked_add(val) {
+                            Some(sum) => *int = sum,
+                            None => {
+                                // the integer sum overflows, so the result will be float
+                                *float += val as f64;
+                                *is_float = true;
+                            }
+                        },
+                        KeyData::Float(val) => {
+                            *float += val.0;
+                            *is_float = true;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            Avg(sum, count) => {
+                for val in vals {
+                    match val {
+                        KeyData::Int(val) => *sum += val as f64,
+                        KeyData::Float(val) => *sum += val.0,
+                        _ => continue,
+                    }
+                    *count += 1;
+                }
+            }
+            Min(min) => {
+                for val in vals {
+                    match min {
+                        Some(cur) if compare_keys(&val, cur).is_ge() => (),
+                        _ => *min = Some(val),
+                    }
+                }
+            }
+            Max(max) => {
+                for val in vals {
+                    match max {
+                        Some(cur) if compare_keys(&val, cur).is_le() => (),
+                        _ => *max = Some(val),
+                    }
+                }
+            }
+            First(first) => {
+                if first.is_none() && !vals.is_empty() {
+                    *first = Some(vals);
+                }
+            }
+            Last(last) => {
+                if !vals.is_empty() {
+                    *last = Some(vals);
+                }
+            }
+        }
+    }
+
+    fn into_val(self) -> Value {
+        use self::State::*;
+        match self {
+            Count(count) => Value::Integer(count as i128),
+            Sum(int, float, true) => Value::Float(int as f64 + float),
+            Sum(int, _, false) => Value::Integer(int as i128),
+            Avg(sum, count) if count > 0 => Value::Float(sum / count as f64),
+            Min(Some(val)) | Max(Some(val)) => val.into_val(),
+            First(Some(mut vals)) | Last(Some(mut vals)) => {
+                if vals.len() == 1 {
+                    vals.remove(0).into_val()
+                } else {
+                    Value::Array(vals.into_iter().map(KeyData::into_val).collect())
+                }
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Compare the values of field
+///
+/// The keys of different types which cannot be converted will be compared by type.
+///
+fn compare_keys(a: &KeyData, b: &KeyData) -> Ordering {
+    a.compare(b).unwrap_or_else(|| a.cmp(b))
+}
+
+fn new_states(fields: &[(&Identifier, &Accumulator)]) -> Vec<State> {
+    fields.iter().map(|(_name, acc)| State::new(acc)).collect()
+}
+
+/// The key of group
+///
+/// The key contains the values of group fields, or nothing when the field is missing.
+///
+pub(crate) type GroupKey = Vec<Option<KeyData>>;
+
+/// The accumulation of groups of documents
+pub(crate) struct Aggregation<'a> {
+    group: &'a [Identifier],
+    fields: Vec<(&'a Identifier, &'a Accumulator)>,
+    groups: BTreeMap<GroupKey, Vec<State>>,
+}
+
+impl<'a> Aggregation<'a> {
+    pub fn new(group: &'a [Identifier], fields: &'a Accumulators) -> Self {
+        let mut aggregation = Self {
+            group,
+            fields: fields.0.iter().collect(),
+            groups: BTreeMap::new(),
+        };
+
+        if group.is_empty() {
+            // the single group exists even when no documents selected
+            let states = new_states(&aggregation.fields);
+            aggregation.groups.insert(Vec::new(), states);
+        }
+
+        aggregation
+    }
+
+    /// Get the keys of groups which document belongs to
+    ///
+    /// The document belongs to the all combinations of the keys of group fields.
+    ///
+    pub fn group_keys(&self, doc: &Value) -> Vec<GroupKey> {
+        let mut keys = vec![Vec::new()];
+        for field in self.group {
+            let mut vals = extract_field_list(doc, field);
+            vals.sort_unstable();
+            vals.dedup();
+            let vals = if vals.is_empty() {
+                vec![None]
+            } else {
+                vals.into_iter().map(Some).collect()
+            };
+            keys = keys
+                .iter()
+                .flat_map(|key| {
+                    vals.iter().map(move |val| {
+                        let mut key = key.clone();
+                        key.push(val.clone());
+                        key
+                    })
+                })
+                .collect();
+        }
+        keys
+    }
+
+    /// Accumulate the document of group
+    ///
+    /// The document may be omitted when it isn't needed by accumulators.
+    ///
+    pub fn update(&mut self, key: GroupKey, doc: Option<&Value>) {
+        let Self { fields, groups, .. } = self;
+        let states = groups.entry(key).or_insert_with(|| new_states(fields));
+        for ((_name, acc), state) in fields.iter().zip(states) {
+            state.update(acc.field(), doc);
+        }
+    }
+
+    /// Get the accumulated groups ordered by keys
+    pub fn finish(self) -> Vec<Value> {
+        let Self {
+            group,
+            fields,
+            groups,
+        } = self;
+
+        groups
+            .into_iter()
+            .map(|(key, states)| {
+                let mut out = BTreeMap::new();
+                for (field, val) in group.iter().zip(key) {
+                    out.insert(
+                        Value::Text(field.to_string()),
+                        val.map(KeyData::into_val).unwrap_or(Value::Null),
+                    );
+                }
+                for ((name, _acc), state) in fields.iter().zip(states) {
+                    out.insert(Value::Text(name.to_string()), state.into_val());
+                }
+                Value::Map(out)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Accumulator, Accumulators, State};
+    use crate::{to_value, Identifier, Value as Doc};
+    use serde_json::{from_str, json, to_string, Value};
+
+    #[test]
+    fn parse_accumulators() {
+        test_parse!(
+            Accumulators,
+            json!({ "total": "$count", "amount": { "$sum": "price" }, "since": { "$min": "date" } }),
+            Accumulators::new()
+                .with("total", Accumulator::Count)
+                .with("amount", Accumulator::Sum("price".into()))
+                .with("since", Accumulator::Min("date".into()))
+        );
+    }
+
+    #[test]
+    fn build_accumulators() {
+        test_build!(
+            Accumulators::new()
+                .with("average", Accumulator::Avg("price".into()))
+                .with("latest", Accumulator::Last("title".into())),
+            json!({ "average": { "$avg": "price" }, "latest": { "$last": "title" } })
+        );
+    }
+
+    #[test]
+    fn sum_overflow() {
+        let field = Identifier::from("n");
+        let mut state = State::new(&Accumulator::Sum(field.clone()));
+        for n in &[i64::MAX, 2, -3] {
+            state.update(Some(&field), Some(&to_value(json!({ "n": n })).unwrap()));
+        }
+        assert_eq!(state.into_val(), Doc::Float(i64::MAX as f64 - 1.0));
+
+        let mut state = State::new(&Accumulator::Sum(field.clone()));
+        for n in &[i64::MAX, -3] {
+            state.update(Some(&field), Some(&to_value(json!({ "n": n })).unwrap()));
+        }
+        assert_eq!(state.into_val(), Doc::Integer((i64::MAX - 3) as i128));
+    }
+}
@@ -0,0 +1,220 @@
+use std::io::{Read, Write};
+
+use lmdb::{Cursor, CursorIter, MaybeOwned};
+use serde::{Deserialize, Serialize};
+use serde_cbor::{to_writer, Deserializer};
+
+use super::{
+    Collection, KeyFields, Primary, PrimaryKey, PrimaryType, RawDocument, Result, ResultWrap,
+    Storage, Value,
+};
+
+/// The version of archive format
+const ARCHIVE_VERSION: u32 = 1;
+
+/// The number of documents which imported using single transaction
+const IMPORT_BATCH: usize = 1000;
+
+/// The record of storage archive
+///
+/// The archive is a sequence of CBOR encoded records which starts with header, and ends with trailer.
+/// Each collection record is followed by the documents of collection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Record {
+    #[serde(rename = "ledb")]
+    Header { version: u32 },
+    #[serde(rename = "collection")]
+    Collection {
+        name: String,
+        primary: PrimaryType,
+        seq: u64,
+        indexes: KeyFields,
+    },
+    #[serde(rename = "document")]
+    Document { id: Primary, doc: Value },
+    #[serde(rename = "end")]
+    End {
+        collections: usize,
+        documents: usize,
+    },
+}
+
+pub(crate) fn export<W: Write>(storage: &Storage, mut out: W) -> Result<usize> {
+    let collections = storage
+        .get_collections()?
+        .into_iter()
+        .filter_map(|name| storage.get_collection(name).transpose())
+        .collect::<Result<Vec<_>>>()?;
+
+    // all collections are exported from the same snapshot
+    let txn = storage.read_txn()?;
+    let mut documents = 0;
+
+    to_writer(
+        &mut out,
+        &Record::Header {
+            version: ARCHIVE_VERSION,
+        },
+    )?;
+
+    for coll in &collections {
+        to_writer(
+            &mut out,
+            &Record::Collection {
+                name: coll.name().into(),
+                primary: coll.primary_type(),
+                seq: coll.sequence_txn(&txn)?,
+                indexes: coll.get_indexes_txn(&txn)?,
+            },
+        )?;
+
+        let cursor = txn.cursor(coll.clone()).wrap_err()?;
+        let access = txn.access();
+
+        for res in CursorIter::new(
+            MaybeOwned::Owned(cursor),
+            &access,
+            |c, a| c.first(a),
+            Cursor::next::<[u8], [u8]>,
+        )
+        .wrap_err()?
+        {
+            let (key, val) = res.wrap_err()?;
+
+            to_writer(
+                &mut out,
+                &Record::Document {
+                    id: coll.primary_type().decode(key)?,
+                    doc: RawDocument::from_bin(val)?.into_inner(),
+                },
+            )?;
+
+            documents += 1;
+        }
+    }
+
+    to_writer(
+        &mut out,
+        &Record::End {
+            collections: collections.len(),
+            documents,
+        },
+    )?;
+
+    out.flush().wrap_err()?;
+
+    Ok(documents)
+}
+
+pub(crate) fn import<R: Read>(storage: &Storage, input: R) -> Result<usize> {
+    let mut created = Vec::new();
+
+    let res = import_records(storage, input, &mut created);
+
+    if res.is_err() {
+        // remove partially imported collections
+        for name in created {
+            let _ = storage.drop_collection(name);
+        }
+    }
+
+    res
+}
+
+fn import_records<R: Read>(
+    storage: &Storage,
+    input: R,
+    created: &mut Vec<String>,
+) -> Result<usize> {
+    let mut records = Deserializer::from_reader(input).into_iter::<Record>();
+
+    match records.next().transpose()? {
+        Some(Record::Header { version }) if version <= ARCHIVE_VERSION => (),
+        Some(Record::Header { version }) => {
+            return Err(format!("Unsupported archive version {}", version)).wrap_err();
+        }
+        _ => return Err("Missing archive header").wrap_err(),
+    }
+
+    let mut current: Option<(Collection, u64)> = None;
+    let mut batch = Vec::new();
+    let mut collections = 0;
+    let mut documents = 0;
+
+    for record in records {
+        match record? {
+            Record::Collection {
+                name,
+                primary,
+                seq,
+                indexes,
+            } => {
+                finish_collection(storage, current.take(), &mut batch)?;
+
+                if storage.has_collection(&name)? {
+                    return Err(format!("Collection '{}' already exists", name)).wrap_err();
+                }
+
+                let coll = storage.collection_with_key(&name, primary)?;
+                created.push(name);
+                coll.set_indexes(&*indexes)?;
+
+                current = Some((coll, seq));
+                collections += 1;
+            }
+            Record::Document { id, doc } => {
+                let (coll, _) = current
+                    .as_ref()
+                    .ok_or("Document outside of collection")
+                    .wrap_err()?;
+
+                batch.push(RawDocument::new(doc).with_id(id));
+                documents += 1;
+
+                if batch.len() >= IMPORT_BATCH {
+                    put_documents(storage, coll, &mut batch)?;
+                }
+            }
+            Record::End {
+                collections: total_collections,
+                documents: total_documents,
+            } => {
+                finish_collection(storage, current.take(), &mut batch)?;
+
+                return if collections == total_collections && documents == total_documents {
+                    Ok(documents)
+                } else {
+                    Err("Incomplete archive").wrap_err()
+                };
+            }
+            Record::Header { .. } => return Err("Unexpected archive header").wrap_err(),
+        }
+    }
+
+    Err("Unexpected end of archive").wrap_err()
+}
+
+fn finish_collection(
+    storage: &Storage,
+    current: Option<(Collection, u64)>,
+    batch: &mut Vec<RawDocument>,
+) -> Result<()> {
+    if let Some((coll, seq)) = current {
+        put_documents(storage, &coll, batch)?;
+        coll.reset_sequence(seq)?;
+    }
+    Ok(())
+}
+
+fn put_documents(storage: &Storage, coll: &Collection, batch: &mut Vec<RawDocument>) -> Result<()> {
+    storage.write(|txn| {
+        for doc in batch.iter() {
+            coll.put_txn(txn, doc.clone())?;
+        }
+        Ok(())
+    })?;
+
+    batch.clear();
+
+    Ok(())
+}
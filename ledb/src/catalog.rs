@@ -0,0 +1,400 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use lmdb::{
+    put::Flags as PutFlags, ConstTransaction, Cursor, CursorIter, Database, DatabaseOptions,
+    LmdbResultExt, MaybeOwned, WriteTransaction,
+};
+use ron::{de::from_str as from_db_name, ser::to_string as to_db_name};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    load_databases, CollectionDef, Definitions, IndexDef, IndexKind, KeyType, PrimaryType, Result,
+    ResultWrap, Serial, Storage, TxnEnv,
+};
+
+/// The version of storage format
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// The key of catalog entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum CatalogKey {
+    #[serde(rename = "s")]
+    Storage,
+    #[serde(rename = "c")]
+    Collection(String),
+    #[serde(rename = "i")]
+    Index(String, String),
+}
+
+/// The metadata of storage
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StorageMeta {
+    /// Storage format version
+    pub version: u32,
+    /// Creation time
+    #[serde(default)]
+    pub created: Option<u64>,
+}
+
+impl StorageMeta {
+    pub(crate) fn load(txn: &ConstTransaction, storage: &Storage) -> Result<Option<Self>> {
+        get_entry(txn, storage, &CatalogKey::Storage)
+    }
+
+    pub(crate) fn save(&self, txn: &WriteTransaction, storage: &Storage) -> Result<()> {
+        put_entry(txn, storage, &CatalogKey::Storage, self)
+    }
+}
+
+/// The metadata of collection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CollectionMeta {
+    /// Unique serial
+    #[serde(default)]
+    pub serial: Serial,
+    /// Primary key sequence
+    #[serde(default)]
+    pub seq: u64,
+    /// Primary key type
+    #[serde(default)]
+    pub primary: PrimaryType,
+    /// Creation time
+    #[serde(default)]
+    pub created: Option<u64>,
+}
+
+impl CollectionMeta {
+    pub(crate) fn load(txn: &ConstTransaction, storage: &Storage, name: &str) -> Result<Self> {
+        get_entry(txn, storage, &CatalogKey::Collection(name.into())).map(Option::unwrap_or_default)
+    }
+
+    pub(crate) fn save(&self, txn: &WriteTransaction, storage: &Storage, name: &str) -> Result<()> {
+        put_entry(txn, storage, &CatalogKey::Collection(name.into()), self)
+    }
+
+    pub(crate) fn remove(txn: &WriteTransaction, storage: &Storage, name: &str) -> Result<()> {
+        del_entry(txn, storage, &CatalogKey::Collection(name.into()))
+    }
+}
+
+/// The metadata of index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct IndexMeta {
+    /// Unique serial
+    #[serde(default)]
+    pub serial: Serial,
+    /// Index kind
+    #[serde(default)]
+    pub kind: IndexKind,
+    /// Key type
+    #[serde(default)]
+    pub key: KeyType,
+    /// The key types of the rest fields of compound index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rest: Vec<KeyType>,
+    /// Creation time
+    #[serde(default)]
+    pub created: Option<u64>,
+}
+
+impl IndexMeta {
+    pub(crate) fn load(
+        txn: &ConstTransaction,
+        storage: &Storage,
+        coll: &str,
+        path: &str,
+    ) -> Result<Self> {
+        get_entry(txn, storage, &CatalogKey::Index(coll.into(), path.into()))
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Load the metadata of all indexes of collection in the order of creation
+    pub(crate) fn load_all(
+        txn: &ConstTransaction,
+        storage: &Storage,
+        coll: &str,
+    ) -> Result<Vec<(String, Self)>> {
+        let catalog = match storage.catalog() {
+            Some(catalog) => catalog,
+            None => return Ok(Vec::new()),
+        };
+        let cursor = txn.cursor(catalog).wrap_err()?;
+        let access = txn.access();
+        let mut metas = Vec::new();
+
+        for res in CursorIter::new(
+            MaybeOwned::Owned(cursor),
+            &access,
+            |c, a| c.first(a),
+            Cursor::next::<str, [u8]>,
+        )
+        .wrap_err()?
+        {
+            let (key, val) = res.wrap_err()?;
+
+            if let CatalogKey::Index(name, path) = from_db_name(key).wrap_err()? {
+                if name == coll {
+                    metas.push((path, serde_cbor::from_slice::<Self>(val).wrap_err()?));
+                }
+            }
+        }
+
+        metas.sort_by_key(|(_path, meta)| meta.serial);
+
+        Ok(metas)
+    }
+
+    pub(crate) fn save(
+        &self,
+        txn: &WriteTransaction,
+        storage: &Storage,
+        coll: &str,
+        path: &str,
+    ) -> Result<()> {
+        put_entry(
+            txn,
+            storage,
+            &CatalogKey::Index(coll.into(), path.into()),
+            self,
+        )
+    }
+
+    pub(crate) fn remove(
+        txn: &WriteTransaction,
+        storage: &Storage,
+        coll: &str,
+        path: &str,
+    ) -> Result<()> {
+        del_entry(txn, storage, &CatalogKey::Index(coll.into(), path.into()))
+    }
+}
+
+/// Storage description
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageDescription {
+    /// Storage format version
+    pub version: u32,
+    /// Creation time in seconds since UNIX epoch (unknown for storages which created by older versions)
+    pub created: Option<u64>,
+    /// Collections of storage
+    pub collections: Vec<CollectionDescription>,
+}
+
+/// Collection description
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionDescription {
+    /// Collection name
+    pub name: String,
+    /// Primary key type
+    pub primary: PrimaryType,
+    /// Primary key sequence
+    pub sequence: u64,
+    /// Creation time in seconds since UNIX epoch (unknown for collections which created by older versions)
+    pub created: Option<u64>,
+    /// The number of documents
+    pub documents: usize,
+    /// Indexes of collection
+    pub indexes: Vec<IndexDescription>,
+}
+
+/// Index description
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexDescription {
+    /// Indexed field path (or paths separated by comma for compound index)
+    pub path: String,
+    /// Index kind
+    pub kind: IndexKind,
+    /// Key type (of the first field for compound index)
+    pub key: KeyType,
+    /// The key types of the rest fields of compound index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rest: Vec<KeyType>,
+    /// Creation time in seconds since UNIX epoch (unknown for indexes which created by older versions)
+    pub created: Option<u64>,
+    /// The number of index entries
+    pub entries: usize,
+}
+
+/// Current time in seconds since UNIX epoch
+pub(crate) fn now() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .ok()
+}
+
+/// Load collection and index definitions from catalog
+///
+/// The catalog will be filled using the names of databases when storage has been created by older version.
+///
+pub(crate) fn load_catalog(storage: &Storage) -> Result<(Serial, Definitions)> {
+    let meta = {
+        let txn = storage.read_txn()?;
+        StorageMeta::load(&txn, storage)?
+    };
+
+    match meta {
+        Some(StorageMeta { version, .. }) if version > FORMAT_VERSION => {
+            return Err(format!("Unsupported storage format version {}", version)).wrap_err();
+        }
+        Some(_) => (),
+        // the catalog cannot be filled when storage is read-only
+        None if storage.is_read_only()? => return load_legacy(storage),
+        None => migrate_catalog(storage)?,
+    }
+
+    let txn = storage.read_txn()?;
+    let cursor = txn.cursor(req_catalog(storage)?).wrap_err()?;
+    let access = txn.access();
+    let mut defs: HashMap<String, (CollectionDef, Vec<IndexDef>)> = HashMap::new();
+    let mut last_serial: Serial = 0;
+
+    for res in CursorIter::new(
+        MaybeOwned::Owned(cursor),
+        &access,
+        |c, a| c.first(a),
+        Cursor::next::<str, [u8]>,
+    )
+    .wrap_err()?
+    {
+        let (key, val) = res.wrap_err()?;
+
+        match from_db_name(key).wrap_err()? {
+            CatalogKey::Collection(name) => {
+                let CollectionMeta { serial, .. } = serde_cbor::from_slice(val).wrap_err()?;
+                last_serial = usize::max(last_serial, serial);
+                let def = CollectionDef(serial, name);
+                let entry = defs
+                    .entry(def.1.clone())
+                    .or_insert_with(|| (def.clone(), Vec::new()));
+                entry.0 = def;
+            }
+            CatalogKey::Index(coll, path) => {
+                let IndexMeta {
+                    serial,
+                    kind,
+                    key,
+                    rest,
+                    ..
+                } = serde_cbor::from_slice(val).wrap_err()?;
+                last_serial = usize::max(last_serial, serial);
+                defs.entry(coll.clone())
+                    .or_insert_with(|| (CollectionDef::new(&coll), Vec::new()))
+                    .1
+                    .push(IndexDef(serial, coll, path, kind, key, rest));
+            }
+            CatalogKey::Storage => (),
+        }
+    }
+
+    Ok((
+        last_serial,
+        defs.into_iter()
+            .map(|(_key, (def, mut index_defs))| {
+                // keep the order of indexes creation
+                index_defs.sort_by_key(|def| def.0);
+                (def, index_defs)
+            })
+            .collect(),
+    ))
+}
+
+/// Load collection and index definitions using the names of databases
+fn load_legacy(storage: &Storage) -> Result<(Serial, Definitions)> {
+    let env = TxnEnv::new(storage.clone())?;
+
+    let db = Database::open(&*env, None, &DatabaseOptions::defaults()).wrap_err()?;
+
+    load_databases(&env, &db)
+}
+
+/// Fill catalog using the names of databases
+fn migrate_catalog(storage: &Storage) -> Result<()> {
+    let (_last_serial, defs) = load_legacy(storage)?;
+
+    storage.write(|txn| {
+        for (CollectionDef(serial, name), index_defs) in &defs {
+            CollectionMeta {
+                serial: *serial,
+                ..CollectionMeta::default()
+            }
+            .save(txn, storage, name)?;
+
+            for IndexDef(serial, coll, path, kind, key, rest) in index_defs {
+                IndexMeta {
+                    serial: *serial,
+                    kind: *kind,
+                    key: *key,
+                    rest: rest.clone(),
+                    created: None,
+                }
+                .save(txn, storage, coll, path)?;
+            }
+        }
+
+        StorageMeta {
+            version: FORMAT_VERSION,
+            // the storage is new when it has no collections
+            created: if defs.is_empty() { now() } else { None },
+        }
+        .save(txn, storage)
+    })
+}
+
+fn get_entry<T: DeserializeOwned>(
+    txn: &ConstTransaction,
+    storage: &Storage,
+    key: &CatalogKey,
+) -> Result<Option<T>> {
+    let catalog = match storage.catalog() {
+        Some(catalog) => catalog,
+        None => return Ok(None),
+    };
+    let key = to_db_name(key).wrap_err()?;
+    let access = txn.access();
+
+    match access.get::<str, [u8]>(catalog, &key).to_opt().wrap_err()? {
+        Some(val) => serde_cbor::from_slice(val).map(Some).wrap_err(),
+        None => Ok(None),
+    }
+}
+
+fn put_entry<T: Serialize>(
+    txn: &WriteTransaction,
+    storage: &Storage,
+    key: &CatalogKey,
+    val: &T,
+) -> Result<()> {
+    let key = to_db_name(key).wrap_err()?;
+    let mut access = txn.access();
+
+    access
+        .put(
+            req_catalog(storage)?,
+            key.as_str(),
+            &serde_cbor::to_vec(val).wrap_err()?,
+            PutFlags::empty(),
+        )
+        .wrap_err()
+}
+
+fn del_entry(txn: &WriteTransaction, storage: &Storage, key: &CatalogKey) -> Result<()> {
+    let key = to_db_name(key).wrap_err()?;
+    let mut access = txn.access();
+
+    access
+        .del_key(req_catalog(storage)?, key.as_str())
+        .to_opt()
+        .map(|_| ())
+        .wrap_err()
+}
+
+fn req_catalog(storage: &Storage) -> Result<&Database<'static>> {
+    storage
+        .catalog()
+        .ok_or("Missing storage catalog")
+        .wrap_err()
+}
@@ -1,25 +1,30 @@
 use std::{
     cmp::Ordering,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    iter::once,
     marker::PhantomData,
     ops::Deref,
     sync::{
         atomic::{AtomicBool, Ordering as AtomicOrdering},
         Arc, RwLock,
     },
+    vec::IntoIter,
 };
 
 use lmdb::{
-    put::Flags as PutFlags, traits::CreateCursor, Cursor, CursorIter, Database, DatabaseOptions,
-    LmdbResultExt, MaybeOwned, ReadTransaction, Unaligned, WriteTransaction,
+    put::Flags as PutFlags, ConstAccessor, ConstTransaction, Cursor, CursorIter, Database,
+    DatabaseOptions, LmdbResultExt, MaybeOwned, Unaligned, WriteAccessor, WriteTransaction,
 };
 use ron::ser::to_string as to_db_name;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use supercow::{ext::ConstDeref, Supercow};
 
 use super::{
-    DatabaseDef, Document, Enumerable, Filter, Index, IndexDef, IndexKind, KeyField, KeyFields,
-    KeyType, Modify, Order, OrderKind, Primary, RawDocument, Result, ResultWrap, Serial, Storage,
+    now, reference_id, Accumulators, Aggregation, CollectionDescription, CollectionMeta, Comp,
+    DatabaseDef, Document, Enumerable, Explain, Filter, Identifier, Index, IndexDef,
+    IndexDescription, IndexKind, IndexMeta, KeyData, KeyField, KeyFields, KeyType, Lookup, Modify,
+    Order, OrderKind, OrderPlan, Page, Position, Primary, PrimaryKey, PrimaryType, Projection,
+    RawDocument, Result, ResultWrap, Selection, Serial, Storage, Value, SORT_COST,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -44,6 +49,7 @@ impl Enumerable for CollectionDef {
 
 struct CollectionData {
     name: String,
+    primary: PrimaryType,
     indexes: RwLock<Vec<Index>>,
     storage: Storage,
     db: Database<'static>,
@@ -60,24 +66,48 @@ impl Collection {
         storage: Storage,
         def: CollectionDef,
         index_defs: Vec<IndexDef>,
+    ) -> Result<Self> {
+        let primary = {
+            let txn = storage.read_txn()?;
+            CollectionMeta::load(&txn, &storage, &def.1)?.primary
+        };
+
+        Self::with_primary(storage, def, primary, index_defs)
+    }
+
+    /// Open the databases of collection with specified type of primary key
+    ///
+    /// The metadata of collection isn't used so it may be saved later.
+    ///
+    pub(crate) fn with_primary(
+        storage: Storage,
+        def: CollectionDef,
+        primary: PrimaryType,
+        index_defs: Vec<IndexDef>,
     ) -> Result<Self> {
         let db_name = to_db_name(&DatabaseDef::Collection(def.clone())).wrap_err()?;
 
         let CollectionDef(_serial, name) = def;
 
-        let db_opts = DatabaseOptions::create_map::<Unaligned<Primary>>();
+        let db_opts = match primary {
+            PrimaryType::U32 => DatabaseOptions::create_map::<Unaligned<u32>>(),
+            PrimaryType::U64 => DatabaseOptions::create_map::<Unaligned<u64>>(),
+            PrimaryType::String => DatabaseOptions::create_map::<str>(),
+            PrimaryType::Uuid | PrimaryType::Ulid => DatabaseOptions::create_map::<[u8]>(),
+        };
 
-        let db = Database::open(storage.clone(), Some(&db_name), &db_opts).wrap_err()?;
+        let db = storage.open_db(&db_name, &db_opts)?;
 
         let indexes = RwLock::new(
             index_defs
                 .into_iter()
-                .map(|def| Index::new(storage.clone(), def))
+                .map(|def| Index::new(storage.clone(), def, primary))
                 .collect::<Result<Vec<_>>>()?,
         );
 
         Ok(Collection(Some(Arc::new(CollectionData {
             name,
+            primary,
             indexes,
             storage,
             db,
@@ -97,16 +127,68 @@ impl Collection {
         &self.handle().name
     }
 
+    /// Get the type of primary key/identifier of documents
+    pub fn primary_type(&self) -> PrimaryType {
+        self.handle().primary
+    }
+
+    fn raw_id(&self, id: &Primary) -> Result<Vec<u8>> {
+        self.handle().primary.encode(id)
+    }
+
     /// Insert document into collection
     ///
-    /// The primary key/identifier of document will be selected by auto incrementing the id of last inserted document.
+    /// The primary key/identifier of document will be selected by auto incrementing the primary key sequence of collection when it has integer primary keys.
+    /// Otherwise the primary key/identifier of document will be used when present, or generated when missing.
     ///
     /// Primary key/identifier of new inserted document will be returned.
     ///
     pub fn insert<T: Serialize + Document>(&self, doc: T) -> Result<Primary> {
-        let id = self.new_id()?;
+        let handle = self.handle();
+
+        let doc = RawDocument::from_doc(&doc)?;
 
-        self.put_raw(RawDocument::from_doc(&doc)?.with_id(id))?;
+        handle
+            .storage
+            .write(|txn| self.insert_txn(txn, doc.clone()))
+    }
+
+    pub(crate) fn insert_txn(&self, txn: &WriteTransaction, doc: RawDocument) -> Result<Primary> {
+        let handle = self.handle();
+
+        let id = if handle.primary.is_sequential() {
+            let seq = self
+                .sequence_txn(txn)?
+                .checked_add(1)
+                .ok_or("Primary key sequence overflow")
+                .wrap_err()?;
+
+            let id = handle
+                .primary
+                .check(Primary::Int(seq))
+                .map_err(|_| "Primary key sequence overflow")
+                .wrap_err()?;
+
+            let mut meta = self.get_meta_txn(txn)?;
+            meta.seq = seq;
+            self.put_meta_txn(txn, &meta)?;
+
+            id
+        } else {
+            let id = match doc.get_id() {
+                Some(id) => handle.primary.check(id.clone())?,
+                None => handle.primary.generate()?,
+            };
+
+            if self.has_txn(txn, &id)? {
+                return Err(format!("Document with primary key '{}' already exists", id))
+                    .wrap_err();
+            }
+
+            id
+        };
+
+        self.put_txn(txn, doc.with_id(id.clone()))?;
 
         Ok(id)
     }
@@ -124,49 +206,512 @@ impl Collection {
         filter: Option<Filter>,
         order: Order,
     ) -> Result<DocumentsIterator<T>> {
-        let handle = if let Some(handle) = &self.0 {
-            handle
+        self.find_page(filter, order, Page::default())
+    }
+
+    /// Find the page of documents using optional filter and ordering
+    ///
+    /// Unlike skipping the documents using iterator the page applies while iterating primary keys or index, so the rest of entries will not be touched.
+    ///
+    /// Use `Page::after()` to start the page after the last document of previous page (keyset pagination), so the deep pages will not cost more than the first one.
+    ///
+    pub fn find_page<T: DeserializeOwned + Document>(
+        &self,
+        filter: Option<Filter>,
+        order: Order,
+        page: Page,
+    ) -> Result<DocumentsIterator<T>> {
+        let handle = self.handle();
+
+        let ids = {
+            let txn = handle.storage.read_txn()?;
+            self.find_txn(&txn, filter, order, page)?
+        };
+
+        DocumentsIterator::new(handle.storage.clone(), self.clone(), ids)
+    }
+
+    pub(crate) fn find_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        filter: Option<Filter>,
+        order: Order,
+        page: Page,
+    ) -> Result<Vec<Primary>> {
+        self.query_txn(txn, filter, order, page)
+            .map(|(ids, _explain)| ids)
+    }
+
+    /// Explain the finding of documents using optional filter and ordering
+    ///
+    /// The query will be executed to get the actual numbers of selected documents.
+    ///
+    pub fn explain(&self, filter: Option<Filter>, order: Order) -> Result<Explain> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.query_txn(&txn, filter, order, Page::default())
+            .map(|(_ids, explain)| explain)
+    }
+
+    fn query_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        filter: Option<Filter>,
+        order: Order,
+        page: Page,
+    ) -> Result<(Vec<Primary>, Explain)> {
+        let documents = self.documents_txn(txn)?;
+
+        let (filter, sel) = if let Some(filter) = filter {
+            let mut plan = filter.plan(txn, self, documents)?;
+            let sel = plan.apply(txn, self, documents)?;
+            (Some(plan), Some(sel))
         } else {
-            unreachable!();
+            (None, None)
         };
 
-        let txn = Arc::new(ReadTransaction::new(handle.storage.clone())?);
+        let estimated = filter
+            .as_ref()
+            .map(|plan| plan.estimated)
+            .unwrap_or(documents);
 
-        let ids = match (filter, order) {
-            (None, Order::Primary(order)) => {
-                PrimaryIterator::new(txn.clone(), self.clone(), order)?
-                    .collect::<Result<Vec<_>>>()?
-            }
+        let Page { skip, limit, after } = page;
+        let limit = limit.unwrap_or(usize::MAX);
 
-            (None, Order::Field(field, order)) => self
-                .req_index(field)?
-                .query_iter(txn.clone(), order)?
-                .collect::<Result<Vec<_>>>()?,
+        // ordering by single field can use index
+        let order = match order {
+            Order::Fields(mut fields) if fields.len() < 2 => match fields.pop() {
+                Some((field, order)) => Order::Field(field, order),
+                None => Order::default(),
+            },
+            order => order,
+        };
 
-            (Some(filter), Order::Primary(order)) => {
-                let sel = filter.apply(&txn, &self)?;
+        // ordering by several fields can use compound index
+        let compound = if let Order::Fields(fields) = &order {
+            self.compound_order_index(txn, fields, sel.as_ref())?
+        } else {
+            None
+        };
 
-                if sel.inv {
-                    sel.filter(PrimaryIterator::new(txn.clone(), self.clone(), order)?)
+        let (order, ids) = match (sel, order, compound) {
+            (Some(sel), Order::Primary(order), _) if !sel.inv => {
+                let mut ids = sel.ids.into_iter().collect::<Vec<_>>();
+                ids.sort_unstable_by(if order == OrderKind::Asc {
+                    order_primary_asc
+                } else {
+                    order_primary_desc
+                });
+                let start = if let Some(Position { id, .. }) = after {
+                    let id = self.primary_type().check(id)?;
+                    ids.partition_point(|other| match order {
+                        OrderKind::Asc => *other <= id,
+                        OrderKind::Desc => *other >= id,
+                    })
+                } else {
+                    0
+                };
+                (
+                    OrderPlan::SortPrimary(order),
+                    ids.into_iter().skip(start).skip(skip).take(limit).collect(),
+                )
+            }
+
+            (sel, Order::Primary(order), _) => {
+                let mut iter = PrimaryIterator::new(txn, self.clone(), order)?;
+                if let Some(Position { id, .. }) = &after {
+                    iter = iter.after(id)?;
+                }
+                let ids = if let Some(sel) = sel {
+                    sel.filter(iter)
+                        .skip(skip)
+                        .take(limit)
                         .collect::<Result<Vec<_>>>()?
                 } else {
-                    let mut ids = sel.ids.into_iter().collect::<Vec<_>>();
-                    ids.sort_unstable_by(if order == OrderKind::Asc {
-                        order_primary_asc
-                    } else {
-                        order_primary_desc
-                    });
-                    ids
+                    iter.skip(skip).take(limit).collect::<Result<Vec<_>>>()?
+                };
+                (OrderPlan::Primary(order), ids)
+            }
+
+            (sel, Order::Field(field, order), _) => {
+                let index = self.req_order_index(&field)?;
+                let entries = txn.db_stat(&index).wrap_err()?.entries;
+
+                let after = if let Some(Position { key, id }) = after {
+                    let key = key
+                        .ok_or_else(|| format!("Missing key of field '{}' to start after", field))
+                        .wrap_err()?;
+                    let key = key
+                        .to_type(index.key())
+                        .ok_or_else(|| format!("Invalid key of field '{}' to start after", field))
+                        .wrap_err()?
+                        .into_owned();
+                    Some((key, self.primary_type().check(id)?))
+                } else {
+                    None
+                };
+
+                match sel {
+                    Some(sel) if !sel.inv && sel.ids.len().saturating_mul(SORT_COST) < entries => {
+                        let keys = self.sort_txn(txn, &index, sel.ids, order)?;
+                        let start = if let Some(after) = &after {
+                            keys.partition_point(|(key, id)| match order {
+                                OrderKind::Asc => (key, id) <= (&after.0, &after.1),
+                                OrderKind::Desc => (key, id) >= (&after.0, &after.1),
+                            })
+                        } else {
+                            0
+                        };
+                        (
+                            OrderPlan::SortField(field.to_string(), order),
+                            keys.into_iter()
+                                .skip(start)
+                                .skip(skip)
+                                .take(limit)
+                                .map(|(_key, id)| id)
+                                .collect(),
+                        )
+                    }
+                    Some(sel) => (
+                        OrderPlan::Index(field.to_string(), order),
+                        sel.filter(index.query_iter(txn, order, after.as_ref())?)
+                            .skip(skip)
+                            .take(limit)
+                            .collect::<Result<Vec<_>>>()?,
+                    ),
+                    None => (
+                        OrderPlan::Index(field.to_string(), order),
+                        index
+                            .query_iter(txn, order, after.as_ref())?
+                            .skip(skip)
+                            .take(limit)
+                            .collect::<Result<Vec<_>>>()?,
+                    ),
                 }
             }
 
-            (Some(filter), Order::Field(field, order)) => filter
-                .apply(&txn, &self)?
-                .filter(self.req_index(field)?.query_iter(txn.clone(), order)?)
-                .collect::<Result<Vec<_>>>()?,
+            (sel, Order::Fields(fields), Some(index)) => {
+                let order = fields[0].1;
+
+                let after = if let Some(Position { id, .. }) = after {
+                    let id = self.primary_type().check(id)?;
+                    let keys = self.sort_txn(txn, &index, once(id).collect(), order)?;
+                    Some(
+                        keys.into_iter()
+                            .next()
+                            .ok_or("Missing document to start after")
+                            .wrap_err()?,
+                    )
+                } else {
+                    None
+                };
+
+                let iter = index.query_iter(txn, order, after.as_ref())?;
+                (
+                    OrderPlan::Index(index.path().into(), order),
+                    if let Some(sel) = sel {
+                        sel.filter(iter)
+                            .skip(skip)
+                            .take(limit)
+                            .collect::<Result<Vec<_>>>()?
+                    } else {
+                        iter.skip(skip).take(limit).collect::<Result<Vec<_>>>()?
+                    },
+                )
+            }
+
+            (sel, Order::Fields(fields), None) => {
+                let indexes = fields
+                    .iter()
+                    .map(|(field, order)| Ok((self.req_order_index(field)?, *order)))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let ids = match sel {
+                    Some(sel) if !sel.inv => sel.ids.into_iter().collect(),
+                    sel => {
+                        let iter = PrimaryIterator::new(txn, self.clone(), OrderKind::Asc)?;
+                        if let Some(sel) = sel {
+                            sel.filter(iter).collect::<Result<Vec<_>>>()?
+                        } else {
+                            iter.collect::<Result<Vec<_>>>()?
+                        }
+                    }
+                };
+
+                let mut keys = self.sort_keys_txn(txn, &indexes, ids)?;
+                keys.sort_unstable_by(|a, b| order_keys(&indexes, a, b));
+
+                let start = if let Some(Position { id, .. }) = after {
+                    let id = self.primary_type().check(id)?;
+                    let after = self
+                        .sort_keys_txn(txn, &indexes, vec![id])?
+                        .pop()
+                        .ok_or("Missing document to start after")
+                        .wrap_err()?;
+                    keys.partition_point(|keys| {
+                        order_keys(&indexes, keys, &after) != Ordering::Greater
+                    })
+                } else {
+                    0
+                };
+
+                (
+                    OrderPlan::SortFields(
+                        fields
+                            .iter()
+                            .map(|(field, order)| (field.to_string(), *order))
+                            .collect(),
+                    ),
+                    keys.into_iter()
+                        .skip(start)
+                        .skip(skip)
+                        .take(limit)
+                        .map(|(_keys, id)| id)
+                        .collect(),
+                )
+            }
+
+            (sel, Order::Relevance(field), _) => {
+                let index = self.req_index(&field)?;
+                let terms = match filter.as_ref().and_then(|plan| plan.text_query(&field)) {
+                    Some(query) if index.is_text() => query.terms(),
+                    _ => {
+                        return Err(format!(
+                            "Ordering by relevance requires the text search on field '{}' which has full-text index",
+                            field
+                        ))
+                        .wrap_err()
+                    }
+                };
+
+                let scores = {
+                    let access = txn.access();
+                    index.score_text(txn, &access, &terms, documents)?
+                };
+
+                let mut ids = scores
+                    .into_iter()
+                    .filter(|(id, _)| sel.as_ref().map(|sel| sel.has(id)).unwrap_or(true))
+                    .collect::<Vec<_>>();
+                ids.sort_unstable_by(order_relevance);
+
+                let start = if let Some(Position { id, .. }) = after {
+                    let id = self.primary_type().check(id)?;
+                    ids.iter()
+                        .position(|(other, _)| *other == id)
+                        .map(|pos| pos + 1)
+                        .ok_or("Missing document to start after")
+                        .wrap_err()?
+                } else {
+                    0
+                };
+
+                (
+                    OrderPlan::Relevance(field.to_string()),
+                    ids.into_iter()
+                        .skip(start)
+                        .skip(skip)
+                        .take(limit)
+                        .map(|(id, _score)| id)
+                        .collect(),
+                )
+            }
+
+            (sel, Order::Distance(field, point), _) => {
+                let index = self.req_index(&field)?;
+                if index.key() != KeyType::Geo {
+                    return Err(format!(
+                        "Ordering by distance requires the geo index on field '{}'",
+                        field
+                    ))
+                    .wrap_err();
+                }
+                let entries = txn.db_stat(&index).wrap_err()?.entries;
+
+                let keys = match sel {
+                    Some(sel) if !sel.inv && sel.ids.len().saturating_mul(SORT_COST) < entries => {
+                        self.sort_txn(txn, &index, sel.ids, OrderKind::Asc)?
+                    }
+                    sel => {
+                        let access = txn.access();
+                        let mut keys = index.query_entries(txn, &access)?;
+                        if let Some(sel) = sel {
+                            keys.retain(|(_key, id)| sel.has(id));
+                        }
+                        keys
+                    }
+                };
+
+                // the distance to the nearest point of document
+                let mut distances: HashMap<Primary, f64> = HashMap::new();
+                for (key, id) in keys {
+                    if let KeyData::Geo(other) = key {
+                        let distance = point.distance(&other);
+                        let nearest = distances.entry(id).or_insert(distance);
+                        if distance < *nearest {
+                            *nearest = distance;
+                        }
+                    }
+                }
+
+                let mut ids = distances.into_iter().collect::<Vec<_>>();
+                ids.sort_unstable_by(order_distance);
+
+                let start = if let Some(Position { id, .. }) = after {
+                    let id = self.primary_type().check(id)?;
+                    ids.iter()
+                        .position(|(other, _)| *other == id)
+                        .map(|pos| pos + 1)
+                        .ok_or("Missing document to start after")
+                        .wrap_err()?
+                } else {
+                    0
+                };
+
+                (
+                    OrderPlan::Distance(field.to_string()),
+                    ids.into_iter()
+                        .skip(start)
+                        .skip(skip)
+                        .take(limit)
+                        .map(|(id, _distance)| id)
+                        .collect(),
+                )
+            }
         };
 
-        DocumentsIterator::new(handle.storage.clone(), self.clone(), ids)
+        let actual = ids.len();
+
+        Ok((
+            ids,
+            Explain {
+                filter,
+                order,
+                estimated,
+                actual,
+            },
+        ))
+    }
+
+    /// Find compound index which can be iterated to order documents by several fields
+    ///
+    /// The ordering fields must be the first fields of index and have same direction of ordering. The index will not be used when sorting of selected documents is cheaper.
+    ///
+    fn compound_order_index(
+        &self,
+        txn: &ConstTransaction,
+        fields: &[(Identifier, OrderKind)],
+        sel: Option<&Selection>,
+    ) -> Result<Option<Index>> {
+        if fields.iter().any(|(_, order)| *order != fields[0].1) {
+            return Ok(None);
+        }
+
+        let index = self.get_compound_indexes()?.into_iter().find(|index| {
+            let mut paths = index.path().split(',');
+            fields
+                .iter()
+                .all(|(field, _)| paths.next() == Some(&**field))
+        });
+
+        Ok(match (index, sel) {
+            (Some(index), Some(sel)) if !sel.inv => {
+                let entries = txn.db_stat(&index).wrap_err()?.entries;
+                if sel.ids.len().saturating_mul(SORT_COST) < entries {
+                    None
+                } else {
+                    Some(index)
+                }
+            }
+            (index, _) => index,
+        })
+    }
+
+    /// Sort documents by the keys of indexed field
+    ///
+    /// The keys with primary keys of documents will be ordered same as by iterating index.
+    ///
+    fn sort_txn(
+        &self,
+        txn: &ConstTransaction,
+        index: &Index,
+        ids: HashSet<Primary>,
+        order: OrderKind,
+    ) -> Result<Vec<(KeyData, Primary)>> {
+        let handle = self.handle();
+        let access = txn.access();
+        let mut keys = Vec::new();
+
+        for id in ids {
+            let raw_id = self.raw_id(&id)?;
+
+            if let Some(val) = access
+                .get::<[u8], [u8]>(&handle.db, raw_id.as_slice())
+                .to_opt()
+                .wrap_err()?
+            {
+                let doc = RawDocument::from_bin(val)?;
+                for key in index.extract(&doc) {
+                    keys.push((key, id.clone()));
+                }
+            }
+        }
+
+        keys.sort_unstable();
+
+        if order == OrderKind::Desc {
+            keys.reverse();
+        }
+
+        Ok(keys)
+    }
+
+    /// Get the keys of several indexed fields of documents
+    ///
+    /// The first key in order will be used when the field has several keys.
+    ///
+    fn sort_keys_txn(
+        &self,
+        txn: &ConstTransaction,
+        indexes: &[(Index, OrderKind)],
+        ids: Vec<Primary>,
+    ) -> Result<Vec<(Vec<Option<KeyData>>, Primary)>> {
+        let handle = self.handle();
+        let access = txn.access();
+        let mut keys = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let raw_id = self.raw_id(&id)?;
+
+            if let Some(val) = access
+                .get::<[u8], [u8]>(&handle.db, raw_id.as_slice())
+                .to_opt()
+                .wrap_err()?
+            {
+                let doc = RawDocument::from_bin(val)?;
+                let doc_keys = indexes
+                    .iter()
+                    .map(|(index, order)| {
+                        let keys = index.extract(&doc).into_iter();
+                        match order {
+                            OrderKind::Asc => keys.min(),
+                            OrderKind::Desc => keys.max(),
+                        }
+                    })
+                    .collect();
+                keys.push((doc_keys, id));
+            }
+        }
+
+        Ok(keys)
+    }
+
+    pub(crate) fn documents_txn(&self, txn: &ConstTransaction) -> Result<usize> {
+        let handle = self.handle();
+
+        Ok(txn.db_stat(&handle.db).wrap_err()?.entries)
     }
 
     /// Find documents using optional filter and ordering
@@ -185,10 +730,18 @@ impl Collection {
     pub fn find_ids(&self, filter: Option<Filter>) -> Result<HashSet<Primary>> {
         let handle = self.handle();
 
-        let txn = Arc::new(ReadTransaction::new(handle.storage.clone())?);
+        let txn = handle.storage.read_txn()?;
+
+        self.find_ids_txn(&txn, filter)
+    }
 
+    pub(crate) fn find_ids_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        filter: Option<Filter>,
+    ) -> Result<HashSet<Primary>> {
         if let Some(filter) = filter {
-            let sel = filter.apply(&txn, &self)?;
+            let sel = filter.apply(txn, &self)?;
             if !sel.inv {
                 Ok(sel.ids)
             } else {
@@ -202,45 +755,340 @@ impl Collection {
         }
     }
 
+    /// Count documents using optional filter
+    ///
+    /// When none filter specified then the number of all documents will be returned.
+    ///
+    /// The equality conditions on indexed fields will be counted using the number of duplicates of key, so neither documents nor primary keys will be loaded.
+    ///
+    pub fn count(&self, filter: Option<Filter>) -> Result<usize> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.count_txn(&txn, filter)
+    }
+
+    pub(crate) fn count_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        filter: Option<Filter>,
+    ) -> Result<usize> {
+        let documents = self.documents_txn(txn)?;
+
+        if let Some(filter) = filter {
+            if let Some(count) = filter.count(txn, self, documents)? {
+                return Ok(count);
+            }
+
+            let sel = filter
+                .plan(txn, self, documents)?
+                .apply(txn, self, documents)?;
+
+            Ok(if sel.inv {
+                documents.saturating_sub(sel.ids.len())
+            } else {
+                sel.ids.len()
+            })
+        } else {
+            Ok(documents)
+        }
+    }
+
+    /// Aggregate documents using optional filter
+    ///
+    /// The selected documents will be grouped by the values of group fields, and the accumulators will be applied to the documents of each group. When none group fields specified then all selected documents will be accumulated as single group.
+    ///
+    /// When the single group field has index, the keys of index are used for grouping, so the documents will be loaded only when some accumulators needs the values of fields.
+    ///
+    /// Returns the groups ordered by keys, which contains the keys of group fields and the accumulated values.
+    ///
+    pub fn aggregate(
+        &self,
+        filter: Option<Filter>,
+        group: Vec<Identifier>,
+        fields: Accumulators,
+    ) -> Result<Vec<Value>> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.aggregate_txn(&txn, filter, &group, &fields)
+    }
+
+    pub(crate) fn aggregate_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        filter: Option<Filter>,
+        group: &[Identifier],
+        fields: &Accumulators,
+    ) -> Result<Vec<Value>> {
+        let sel = if let Some(filter) = filter {
+            Some(filter.apply(txn, &self)?)
+        } else {
+            None
+        };
+
+        // the ids of selected documents is known, so the collection and the index will not be walked
+        let selective = matches!(&sel, Some(sel) if !sel.inv);
+
+        // selected documents in the order of primary keys
+        let ids = match sel {
+            Some(sel) if selective => {
+                let mut ids = sel.ids.into_iter().collect::<Vec<_>>();
+                ids.sort();
+                ids
+            }
+            _ => PrimaryIterator::new(txn, self.clone(), OrderKind::default())?
+                .filter(|res| match (res, &sel) {
+                    (Ok(id), Some(sel)) => sel.has(id),
+                    _ => true,
+                })
+                .collect::<Result<Vec<_>>>()?,
+        };
+
+        let index = match group {
+            [field] => self
+                .get_index(field)?
+                .filter(|index| !index.is_compound() && !index.is_text()),
+            _ => None,
+        };
+
+        let handle = self.handle();
+        let access = txn.access();
+        let mut aggregation = Aggregation::new(group, fields);
+
+        let get_doc = |id: &Primary| -> Result<Option<RawDocument>> {
+            let raw_id = self.raw_id(id)?;
+
+            access
+                .get::<[u8], [u8]>(&handle.db, raw_id.as_slice())
+                .to_opt()
+                .wrap_err()?
+                .map(RawDocument::from_bin)
+                .transpose()
+        };
+
+        if let (Some(index), true) = (&index, selective) {
+            // the keys of selected documents are extracted same way as when indexing
+            for id in &ids {
+                if let Some(doc) = get_doc(id)? {
+                    let keys = index.extract(&doc);
+                    if keys.is_empty() {
+                        aggregation.update(vec![None], Some(&doc));
+                    }
+                    for key in keys {
+                        aggregation.update(vec![Some(key)], Some(&doc));
+                    }
+                }
+            }
+        } else if let Some(index) = index {
+            let load = fields.needs_documents();
+            let selected = ids.iter().collect::<HashSet<_>>();
+            let mut grouped = HashSet::new();
+
+            for (key, id) in index.query_entries(txn, &access)? {
+                if !selected.contains(&id) {
+                    continue;
+                }
+
+                let doc = if load { get_doc(&id)? } else { None };
+                aggregation.update(vec![Some(key)], doc.as_deref());
+                grouped.insert(id);
+            }
+
+            // the documents which has no keys in index
+            for id in ids.iter().filter(|id| !grouped.contains(*id)) {
+                let doc = if load { get_doc(id)? } else { None };
+                aggregation.update(vec![None], doc.as_deref());
+            }
+        } else {
+            for id in &ids {
+                if let Some(doc) = get_doc(id)? {
+                    for key in aggregation.group_keys(&doc) {
+                        aggregation.update(key, Some(&doc));
+                    }
+                }
+            }
+        }
+
+        Ok(aggregation.finish())
+    }
+
+    /// Get the distinct keys of indexed field with the numbers of documents
+    ///
+    /// The keys are walked using index, so the documents will not be loaded. When filter specified then only the selected documents will be counted, and the keys which has no selected documents will be omitted.
+    ///
+    /// Returns the keys in index order with the numbers of documents.
+    ///
+    pub fn distinct<P: AsRef<str>>(
+        &self,
+        field: P,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.distinct_txn(&txn, field.as_ref(), filter)
+    }
+
+    pub(crate) fn distinct_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        field: &str,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        let index = self.req_index(field)?;
+
+        if index.is_compound() {
+            return Err(format!(
+                "Unable to get distinct keys of compound index of fields '{}'",
+                field
+            ))
+            .wrap_err();
+        }
+
+        let sel = if let Some(filter) = filter {
+            Some(filter.apply(txn, &self)?)
+        } else {
+            None
+        };
+
+        let access = txn.access();
+
+        index.count_entries(txn, &access, sel.as_ref())
+    }
+
+    /// Get the most frequent keys of indexed field with the numbers of documents
+    ///
+    /// Same as [`distinct`](Collection::distinct), but the keys are ordered by descending numbers of documents, and optionally limited.
+    ///
+    pub fn facets<P: AsRef<str>>(
+        &self,
+        field: P,
+        filter: Option<Filter>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.facets_txn(&txn, field.as_ref(), filter, limit)
+    }
+
+    pub(crate) fn facets_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        field: &str,
+        filter: Option<Filter>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        let mut keys = self.distinct_txn(txn, field, filter)?;
+
+        // the stable sorting keeps the index order of keys with same counts
+        keys.sort_by(|(_, a), (_, b)| b.cmp(a));
+        keys.truncate(limit.unwrap_or(usize::MAX));
+
+        Ok(keys)
+    }
+
+    /// Select documents which satisfies the predicate by scanning
+    ///
+    /// Only the documents from the candidates selection will be checked.
+    ///
+    pub(crate) fn scan_txn(
+        &self,
+        txn: &ConstTransaction,
+        candidates: &Selection,
+        pred: impl Fn(&Value) -> bool,
+    ) -> Result<HashSet<Primary>> {
+        let handle = self.handle();
+        let access = txn.access();
+        let mut out = HashSet::new();
+
+        if !candidates.inv {
+            for id in &candidates.ids {
+                let raw_id = self.raw_id(id)?;
+
+                if let Some(val) = access
+                    .get::<[u8], [u8]>(&handle.db, raw_id.as_slice())
+                    .to_opt()
+                    .wrap_err()?
+                {
+                    if pred(&*RawDocument::from_bin(val)?) {
+                        out.insert(id.clone());
+                    }
+                }
+            }
+        } else {
+            let cursor = txn.cursor(self.clone()).wrap_err()?;
+
+            for res in CursorIter::new(
+                MaybeOwned::Owned(cursor),
+                &access,
+                |c, a| c.first(a),
+                Cursor::next::<[u8], [u8]>,
+            )
+            .wrap_err()?
+            {
+                let (key, val) = res.wrap_err()?;
+                let id = handle.primary.decode(key)?;
+
+                if candidates.has(&id) && pred(&*RawDocument::from_bin(val)?) {
+                    out.insert(id);
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
     /// Update documents using optional filter and modifier
     ///
     /// *Note*: When none filter specified then all documents will be modified.
     ///
+    /// The documents will be selected and modified atomically within single transaction.
+    ///
     /// Returns the number of affected documents.
     ///
     pub fn update(&self, filter: Option<Filter>, modify: Modify) -> Result<usize> {
         let handle = self.handle();
 
-        let found_ids = self.find_ids(filter)?;
+        handle
+            .storage
+            .write(|txn| self.update_txn(txn, filter.clone(), &modify))
+    }
 
+    pub(crate) fn update_txn(
+        &self,
+        txn: &WriteTransaction<'static>,
+        filter: Option<Filter>,
+        modify: &Modify,
+    ) -> Result<usize> {
+        let handle = self.handle();
+        let f = PutFlags::empty();
         let mut count = 0;
-        {
-            let txn = WriteTransaction::new(handle.storage.clone())?;
-            let f = PutFlags::empty();
-            {
-                for id in found_ids {
-                    let (old_doc, new_doc) = {
-                        let mut access = txn.access();
-                        let old_doc =
-                            RawDocument::from_bin(access.get(&handle.db, &Unaligned::new(id))?)?
-                                .with_id(id);
-                        let new_doc = RawDocument::new(modify.apply(old_doc.clone().into_inner()))
-                            .with_id(id);
 
-                        access
-                            .put(&handle.db, &Unaligned::new(id), &new_doc.to_bin()?, f)
-                            .wrap_err()?;
+        for id in self.find_ids_txn(txn, filter)? {
+            let (old_doc, new_doc) = {
+                let raw_id = self.raw_id(&id)?;
+                let mut access = txn.access();
+                let old_doc = RawDocument::from_bin(access.get(&handle.db, raw_id.as_slice())?)?
+                    .with_id(id.clone());
+                let new_doc =
+                    RawDocument::new(modify.apply(old_doc.clone().into_inner())).with_id(id);
 
-                        (old_doc, new_doc)
-                    };
+                access
+                    .put(&handle.db, raw_id.as_slice(), &new_doc.to_bin()?, f)
+                    .wrap_err()?;
 
-                    self.update_indexes(&txn, Some(&old_doc), Some(&new_doc))?;
+                (old_doc, new_doc)
+            };
 
-                    count += 1;
-                }
-            }
+            self.update_indexes(txn, Some(&old_doc), Some(&new_doc))?;
 
-            txn.commit().wrap_err()?;
+            count += 1;
         }
 
         Ok(count)
@@ -250,36 +1098,41 @@ impl Collection {
     ///
     /// *Note*: When none filter specified then all documents will be removed.
     ///
+    /// The documents will be selected and removed atomically within single transaction.
+    ///
     /// Returns the number of affected documents.
     ///
     pub fn remove(&self, filter: Option<Filter>) -> Result<usize> {
         let handle = self.handle();
 
-        let found_ids = self.find_ids(filter)?;
+        handle
+            .storage
+            .write(|txn| self.remove_txn(txn, filter.clone()))
+    }
 
+    pub(crate) fn remove_txn(
+        &self,
+        txn: &WriteTransaction<'static>,
+        filter: Option<Filter>,
+    ) -> Result<usize> {
+        let handle = self.handle();
         let mut count = 0;
-        {
-            let txn = WriteTransaction::new(handle.storage.clone())?;
-            {
-                for id in found_ids {
-                    let old_doc = {
-                        let mut access = txn.access();
-                        let old_doc =
-                            RawDocument::from_bin(access.get(&handle.db, &Unaligned::new(id))?)?
-                                .with_id(id);
 
-                        access.del_key(&handle.db, &Unaligned::new(id)).wrap_err()?;
+        for id in self.find_ids_txn(txn, filter)? {
+            let old_doc = {
+                let raw_id = self.raw_id(&id)?;
+                let mut access = txn.access();
+                let old_doc =
+                    RawDocument::from_bin(access.get(&handle.db, raw_id.as_slice())?)?.with_id(id);
 
-                        old_doc
-                    };
+                access.del_key(&handle.db, raw_id.as_slice()).wrap_err()?;
 
-                    self.update_indexes(&txn, Some(&old_doc), None)?;
+                old_doc
+            };
 
-                    count += 1;
-                }
-            }
+            self.update_indexes(txn, Some(&old_doc), None)?;
 
-            txn.commit().wrap_err()?;
+            count += 1;
         }
 
         Ok(count)
@@ -303,32 +1156,35 @@ impl Collection {
 
         let handle = self.handle();
 
-        let txn = WriteTransaction::new(handle.storage.clone())?;
-        let f = PutFlags::empty();
-        let mut count = 0;
+        let docs = docs
+            .into_iter()
+            .map(|doc| RawDocument::from_doc(&doc))
+            .collect::<Result<Vec<_>>>()?;
 
-        {
-            for doc in docs.into_iter() {
-                let doc = RawDocument::from_doc(&doc)?;
-                let id = doc.req_id()?;
+        handle.storage.write(|txn| {
+            let f = PutFlags::empty();
+            let mut count = 0;
+
+            for doc in docs.iter().cloned() {
+                let id = handle.primary.check(doc.req_id()?)?;
+                let raw_id = self.raw_id(&id)?;
+                let doc = doc.with_id(id);
 
                 {
                     let mut access = txn.access();
 
                     access
-                        .put(&handle.db, &Unaligned::new(id), &doc.to_bin()?, f)
+                        .put(&handle.db, raw_id.as_slice(), &doc.to_bin()?, f)
                         .wrap_err()?;
                 }
 
-                self.update_indexes(&txn, None, Some(&doc))?;
+                self.update_indexes(txn, None, Some(&doc))?;
 
                 count += 1;
             }
-        }
-
-        txn.commit().wrap_err()?;
 
-        Ok(count)
+            Ok(count)
+        })
     }
 
     /// Remove all documents from the collection
@@ -336,41 +1192,65 @@ impl Collection {
     pub fn purge(&self) -> Result<()> {
         let handle = self.handle();
 
-        let txn = WriteTransaction::new(handle.storage.clone()).wrap_err()?;
-        let mut access = txn.access();
+        handle.storage.write(|txn| {
+            let mut access = txn.access();
 
-        let indexes = handle.indexes.read().wrap_err()?;
-        for index in indexes.iter() {
-            index.purge(&mut access)?;
-        }
+            let indexes = handle.indexes.read().wrap_err()?;
+            for index in indexes.iter() {
+                index.purge(&mut access)?;
+            }
 
-        access.clear_db(&handle.db).wrap_err()
+            access.clear_db(&handle.db).wrap_err()
+        })
     }
 
     /// Checks the collection contains document with specified primary key
-    pub fn has(&self, id: Primary) -> Result<bool> {
+    pub fn has(&self, id: impl Into<Primary>) -> Result<bool> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.has_txn(&txn, &id.into())
+    }
+
+    pub(crate) fn has_txn(&self, txn: &ConstTransaction, id: &Primary) -> Result<bool> {
         let handle = self.handle();
 
-        let txn = ReadTransaction::new(handle.storage.clone()).wrap_err()?;
+        let raw_id = self.raw_id(id)?;
+
         let access = txn.access();
 
         access
-            .get::<Unaligned<Primary>, [u8]>(&handle.db, &Unaligned::new(id))
+            .get::<[u8], [u8]>(&handle.db, raw_id.as_slice())
             .to_opt()
             .map(|res| res != None)
             .wrap_err()
     }
 
-    /// Get document from collection using primary key/identifier
-    pub fn get<T: DeserializeOwned + Document>(&self, id: Primary) -> Result<Option<T>> {
+    /// Get document from collection using primary key/identifier
+    pub fn get<T: DeserializeOwned + Document>(&self, id: impl Into<Primary>) -> Result<Option<T>> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.get_txn(&txn, &id.into())
+    }
+
+    pub(crate) fn get_txn<T: DeserializeOwned + Document>(
+        &self,
+        txn: &ConstTransaction,
+        id: &Primary,
+    ) -> Result<Option<T>> {
         let handle = self.handle();
 
-        let txn = ReadTransaction::new(handle.storage.clone()).wrap_err()?;
+        let id = handle.primary.check(id.clone())?;
+        let raw_id = self.raw_id(&id)?;
+
         let access = txn.access();
 
         Ok(
             match access
-                .get::<Unaligned<Primary>, [u8]>(&handle.db, &Unaligned::new(id))
+                .get::<[u8], [u8]>(&handle.db, raw_id.as_slice())
                 .to_opt()
                 .wrap_err()?
             {
@@ -380,25 +1260,70 @@ impl Collection {
         )
     }
 
+    /// Get the documents referenced by keys
+    ///
+    /// The keys are primary keys of documents, or the keys of indexed field when it specified.
+    ///
+    pub(crate) fn lookup_txn(
+        &self,
+        txn: &ConstTransaction<'static>,
+        field: Option<&Identifier>,
+        keys: Vec<KeyData>,
+    ) -> Result<Vec<Value>> {
+        let handle = self.handle();
+
+        let ids = if let Some(field) = field {
+            if keys.is_empty() {
+                return Ok(Vec::new());
+            }
+            let filter = Filter::comp(field.clone(), Comp::In(keys));
+            self.find_txn(txn, Some(filter), Order::default(), Page::default())?
+        } else {
+            keys.into_iter()
+                .filter_map(reference_id)
+                .filter_map(|id| handle.primary.check(id).ok())
+                .collect()
+        };
+
+        let access = txn.access();
+        let mut docs = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            if let Some(val) = access
+                .get::<[u8], [u8]>(&handle.db, &self.raw_id(&id)?)
+                .to_opt()
+                .wrap_err()?
+            {
+                docs.push(RawDocument::from_bin(val)?.with_id(id).into_doc()?);
+            }
+        }
+
+        Ok(docs)
+    }
+
     /// Replace document in the collection
     ///
     /// *Note*: The document must have primary key/identifier.
     ///
     pub fn put<T: Serialize + Document>(&self, doc: T) -> Result<()> {
-        self.put_raw(RawDocument::from_doc(&doc)?)
-    }
+        let handle = self.handle();
 
-    fn put_raw(&self, doc: RawDocument) -> Result<()> {
-        let id = doc.req_id()?;
+        let doc = RawDocument::from_doc(&doc)?;
 
+        handle.storage.write(|txn| self.put_txn(txn, doc.clone()))
+    }
+
+    pub(crate) fn put_txn(&self, txn: &WriteTransaction, doc: RawDocument) -> Result<()> {
         let handle = self.handle();
 
-        let txn = WriteTransaction::new(handle.storage.clone()).wrap_err()?;
+        let id = handle.primary.check(doc.req_id()?)?;
+        let raw_id = self.raw_id(&id)?;
+        let doc = doc.with_id(id.clone());
 
         let old_doc = {
             let mut access = txn.access();
             let old_doc =
-                if let Some(old_doc) = access.get(&handle.db, &Unaligned::new(id)).to_opt()? {
+                if let Some(old_doc) = access.get(&handle.db, raw_id.as_slice()).to_opt()? {
                     Some(RawDocument::from_bin(old_doc)?.with_id(id))
                 } else {
                     None
@@ -407,7 +1332,7 @@ impl Collection {
             access
                 .put(
                     &handle.db,
-                    &Unaligned::new(id),
+                    raw_id.as_slice(),
                     &doc.to_bin()?,
                     PutFlags::empty(),
                 )
@@ -416,48 +1341,43 @@ impl Collection {
             old_doc
         };
 
-        self.update_indexes(
-            &txn,
-            if let Some(ref doc) = old_doc {
-                Some(&doc)
-            } else {
-                None
-            },
-            Some(&doc),
-        )?;
-
-        txn.commit().wrap_err()?;
+        self.update_indexes(txn, old_doc.as_ref(), Some(&doc))?;
 
         Ok(())
     }
 
     /// Delete document with specified primary key/identifier from the collection
-    pub fn delete(&self, id: Primary) -> Result<bool> {
+    pub fn delete(&self, id: impl Into<Primary>) -> Result<bool> {
+        let handle = self.handle();
+
+        let id = id.into();
+
+        handle.storage.write(|txn| self.delete_txn(txn, &id))
+    }
+
+    pub(crate) fn delete_txn(&self, txn: &WriteTransaction, id: &Primary) -> Result<bool> {
         let handle = self.handle();
 
-        let txn = WriteTransaction::new(handle.storage.clone()).wrap_err()?;
+        let id = handle.primary.check(id.clone())?;
+        let raw_id = self.raw_id(&id)?;
 
         let old_doc = {
             let mut access = txn.access();
 
             let old_doc =
-                if let Some(old_doc) = access.get(&handle.db, &Unaligned::new(id)).to_opt()? {
+                if let Some(old_doc) = access.get(&handle.db, raw_id.as_slice()).to_opt()? {
                     RawDocument::from_bin(old_doc)?.with_id(id)
                 } else {
                     // document not exists
                     return Ok(false);
                 };
 
-            access.del_key(&handle.db, &Unaligned::new(id)).wrap_err()?;
+            access.del_key(&handle.db, raw_id.as_slice()).wrap_err()?;
 
             old_doc
         };
 
-        let status = self.update_indexes(&txn, Some(&old_doc), None)?;
-
-        txn.commit().wrap_err()?;
-
-        Ok(status)
+        self.update_indexes(txn, Some(&old_doc), None)
     }
 
     fn update_indexes(
@@ -484,20 +1404,130 @@ impl Collection {
     pub fn last_id(&self) -> Result<Primary> {
         let handle = self.handle();
 
-        let txn = ReadTransaction::new(handle.storage.clone()).wrap_err()?;
+        let txn = handle.storage.read_txn()?;
+
+        self.last_id_txn(&txn)
+    }
+
+    pub(crate) fn last_id_txn(&self, txn: &ConstTransaction) -> Result<Primary> {
+        let handle = self.handle();
         let mut cursor = txn.cursor(self.clone()).wrap_err()?;
         let access = txn.access();
 
-        cursor
-            .last::<Unaligned<Primary>, [u8]>(&access)
-            .to_opt()
-            .map(|res| res.map(|(key, _val)| key.get()).unwrap_or(0))
-            .wrap_err()
+        match cursor.last::<[u8], [u8]>(&access).to_opt().wrap_err()? {
+            Some((key, _val)) => handle.primary.decode(key),
+            None => Ok(Primary::default()),
+        }
     }
 
     /// Get the new primary key/identifier
+    ///
+    /// *Note*: The new primary key/identifier will be generated when collection has not integer primary keys.
+    ///
     pub fn new_id(&self) -> Result<Primary> {
-        self.last_id().map(|id| id + 1)
+        let handle = self.handle();
+
+        if handle.primary.is_sequential() {
+            self.sequence().map(|id| Primary::Int(id + 1))
+        } else {
+            handle.primary.generate()
+        }
+    }
+
+    /// Get the current value of primary key sequence
+    ///
+    /// This is the primary key/identifier of last inserted document, which does not decrease when documents deleted.
+    ///
+    pub fn sequence(&self) -> Result<u64> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.sequence_txn(&txn)
+    }
+
+    pub(crate) fn sequence_txn(&self, txn: &ConstTransaction) -> Result<u64> {
+        let seq = self.get_meta_txn(txn)?.seq;
+        let last_id = self.last_id_txn(txn)?.as_int().unwrap_or_default();
+
+        Ok(u64::max(seq, last_id))
+    }
+
+    /// Reset the primary key sequence to specified value
+    ///
+    /// *Note*: The sequence cannot be less than the primary key/identifier of last document, so the existing documents never be overwritten by inserting new.
+    ///
+    pub fn reset_sequence(&self, seq: u64) -> Result<()> {
+        let handle = self.handle();
+
+        handle.storage.write(|txn| {
+            let mut meta = self.get_meta_txn(txn)?;
+            meta.seq = seq;
+            self.put_meta_txn(txn, &meta)
+        })
+    }
+
+    fn get_meta_txn(&self, txn: &ConstTransaction) -> Result<CollectionMeta> {
+        let handle = self.handle();
+
+        CollectionMeta::load(txn, &handle.storage, &handle.name)
+    }
+
+    fn put_meta_txn(&self, txn: &WriteTransaction, meta: &CollectionMeta) -> Result<()> {
+        let handle = self.handle();
+
+        meta.save(txn, &handle.storage, &handle.name)
+    }
+
+    /// Get the description of collection
+    ///
+    /// It includes the metadata from storage catalog and the actual number of documents and index entries.
+    ///
+    pub fn describe(&self) -> Result<CollectionDescription> {
+        let handle = self.handle();
+
+        let txn = handle.storage.read_txn()?;
+
+        self.describe_txn(&txn)
+    }
+
+    pub(crate) fn describe_txn(&self, txn: &ConstTransaction) -> Result<CollectionDescription> {
+        let handle = self.handle();
+
+        let CollectionMeta { created, .. } = self.get_meta_txn(txn)?;
+
+        let indexes = handle
+            .indexes
+            .read()
+            .wrap_err()?
+            .iter()
+            .map(|index| {
+                let KeyField {
+                    path,
+                    kind,
+                    key,
+                    rest,
+                } = index.field();
+                Ok(IndexDescription {
+                    path,
+                    kind,
+                    key,
+                    rest,
+                    created: IndexMeta::load(txn, &handle.storage, &handle.name, index.path())?
+                        .created,
+                    entries: txn.db_stat(index).wrap_err()?.entries,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CollectionDescription {
+            name: handle.name.clone(),
+            primary: handle.primary,
+            sequence: self.sequence_txn(txn)?,
+            created,
+            documents: self.documents_txn(txn)?,
+            indexes,
+        })
     }
 
     /// Get indexes info from the collection
@@ -508,6 +1538,21 @@ impl Collection {
         Ok(indexes.iter().map(Index::field).collect::<Vec<_>>().into())
     }
 
+    pub(crate) fn get_indexes_txn(&self, txn: &ConstTransaction) -> Result<KeyFields> {
+        let handle = self.handle();
+
+        Ok(IndexMeta::load_all(txn, &handle.storage, &handle.name)?
+            .into_iter()
+            .map(|(path, meta)| KeyField {
+                path,
+                key: meta.key,
+                kind: meta.kind,
+                rest: meta.rest,
+            })
+            .collect::<Vec<_>>()
+            .into())
+    }
+
     /// Set indexes of collection
     ///
     /// This method overrides collection indexes
@@ -517,8 +1562,8 @@ impl Collection {
         KeyField: From<T>,
     {
         for key_field in indexes.as_ref() {
-            let KeyField { path, kind, key } = KeyField::from(key_field.clone());
-            self.ensure_index(path, kind, key)?;
+            let key_field = KeyField::from(key_field.clone());
+            self.ensure_index(&key_field.path, key_field.kind, key_field.keys())?;
         }
         Ok(())
     }
@@ -531,14 +1576,21 @@ impl Collection {
     }
 
     /// Ensure index for the collection
-    pub fn ensure_index<P: AsRef<str>>(
+    ///
+    /// The compound index over several fields can be ensured using the paths of fields separated by comma with the key types of each field:
+    ///
+    /// ```ignore
+    /// collection.ensure_index("status,created", IndexKind::Index, [KeyType::String, KeyType::Int])
+    /// ```
+    ///
+    pub fn ensure_index<P: AsRef<str>, K: AsRef<[KeyType]>>(
         &self,
         path: P,
         kind: IndexKind,
-        key: KeyType,
+        key: K,
     ) -> Result<bool> {
         if let Some(index) = self.get_index(&path)? {
-            if index.kind() == kind && index.key() == key {
+            if index.kind() == kind && index.keys() == key.as_ref() {
                 return Ok(false);
             } else {
                 self.drop_index(&path)?;
@@ -560,13 +1612,31 @@ impl Collection {
     }
 
     /// Create index for the collection
-    pub fn create_index<P: AsRef<str>>(
+    pub fn create_index<P: AsRef<str>, K: AsRef<[KeyType]>>(
         &self,
         path: P,
         kind: IndexKind,
-        key: KeyType,
+        key: K,
     ) -> Result<bool> {
         let path = path.as_ref();
+        let (key, rest) = match key.as_ref() {
+            [key, rest @ ..] if path.split(',').count() == 1 + rest.len() => (*key, rest.to_vec()),
+            _ => {
+                return Err(format!(
+                    "The number of key types doesn't match the number of fields of index '{}'",
+                    path
+                ))
+                .wrap_err()
+            }
+        };
+
+        if kind == IndexKind::FullText && (key != KeyType::String || !rest.is_empty()) {
+            return Err(format!(
+                "The full-text index of field '{}' must have single string key",
+                path
+            ))
+            .wrap_err();
+        }
 
         let handle = self.handle();
 
@@ -579,20 +1649,28 @@ impl Collection {
         }
 
         // create new index
-        let index = Index::new(
-            handle.storage.clone(),
-            handle
-                .storage
-                .enumerate(IndexDef::new(handle.name.clone(), path, kind, key)),
-        )?;
+        let def = handle.storage.enumerate(IndexDef::new(
+            handle.name.clone(),
+            path,
+            kind,
+            key,
+            rest.clone(),
+        ));
+        let meta = IndexMeta {
+            serial: def.0,
+            kind,
+            key,
+            rest,
+            created: now(),
+        };
+        let index = Index::new(handle.storage.clone(), def, handle.primary)?;
 
-        {
-            // fulfill index
-            let txn = WriteTransaction::new(handle.storage.clone()).wrap_err()?;
+        // fulfill index
+        handle.storage.write(|txn| {
             {
                 let mut access = txn.access();
 
-                let txn2 = ReadTransaction::new(handle.storage.clone()).wrap_err()?;
+                let txn2 = handle.storage.read_txn()?;
                 let cursor2 = txn2.cursor(self.clone()).wrap_err()?;
                 let access2 = txn2.access();
 
@@ -600,18 +1678,18 @@ impl Collection {
                     MaybeOwned::Owned(cursor2),
                     &access2,
                     |c, a| c.first(a),
-                    Cursor::next::<Unaligned<Primary>, [u8]>,
+                    Cursor::next::<[u8], [u8]>,
                 )
                 .wrap_err()?
                 {
                     let (key, val) = res.wrap_err()?;
-                    let doc = RawDocument::from_bin(val)?.with_id(key.get());
+                    let doc = RawDocument::from_bin(val)?.with_id(handle.primary.decode(key)?);
                     index.update_index(&mut access, None, Some(&doc))?;
                 }
             }
 
-            txn.commit().wrap_err()?;
-        }
+            meta.save(txn, &handle.storage, &handle.name, path)
+        })?;
 
         // add index to collection indexes
         let mut indexes = handle.indexes.write().wrap_err()?;
@@ -634,9 +1712,13 @@ impl Collection {
         Ok(if let Some(pos) = found_pos {
             let mut indexes = handle.indexes.write().wrap_err()?;
             let index = indexes.remove(pos);
-            let txn = WriteTransaction::new(handle.storage.clone())?;
-            let mut access = txn.access();
-            index.to_delete(&mut access)?;
+            handle.storage.write(|txn| {
+                {
+                    let mut access = txn.access();
+                    index.to_delete(&mut access)?;
+                }
+                IndexMeta::remove(txn, &handle.storage, &handle.name, path)
+            })?;
             true
         } else {
             false
@@ -664,21 +1746,120 @@ impl Collection {
         }
     }
 
+    /// Get the index of field which can be iterated to order documents
+    fn req_order_index<P: AsRef<str>>(&self, path: P) -> Result<Index> {
+        let index = self.req_index(&path)?;
+        if index.is_text() {
+            return Err(format!(
+                "Unable to order by field '{}' which has full-text index",
+                path.as_ref()
+            ))
+            .wrap_err();
+        }
+        Ok(index)
+    }
+
+    pub(crate) fn get_compound_indexes(&self) -> Result<Vec<Index>> {
+        let handle = self.handle();
+
+        let indexes = handle.indexes.read().wrap_err()?;
+
+        Ok(indexes
+            .iter()
+            .filter(|index| index.is_compound())
+            .cloned()
+            .collect())
+    }
+
+    /// Copy all documents and index entries into the new empty collection
+    ///
+    /// The target collection must have the same type of primary key and the same indexes.
+    ///
+    pub(crate) fn copy_txn(
+        &self,
+        txn: &WriteTransaction<'static>,
+        target: &Collection,
+    ) -> Result<()> {
+        let handle = self.handle();
+
+        let seq = self.sequence_txn(txn)?;
+
+        {
+            let mut access = txn.access();
+
+            copy_db(txn, &handle.db, target, &mut access)?;
+
+            let indexes = handle.indexes.read().wrap_err()?;
+            for index in indexes.iter() {
+                copy_db(txn, index, &*target.req_index(index.path())?, &mut access)?;
+            }
+        }
+
+        let mut meta = target.get_meta_txn(txn)?;
+        meta.seq = seq;
+        target.put_meta_txn(txn, &meta)
+    }
+
     pub(crate) fn to_delete(&self) -> Result<()> {
         let handle = self.handle();
 
-        let txn = WriteTransaction::new(handle.storage.clone()).wrap_err()?;
-        let mut access = txn.access();
+        handle.storage.write(|txn| self.to_delete_txn(txn))?;
+        self.set_deleted()
+    }
+
+    /// Remove all documents, index entries and metadata of collection
+    ///
+    /// The databases are kept until the transaction is committed, so `set_deleted` should be called after that.
+    ///
+    pub(crate) fn to_delete_txn(&self, txn: &WriteTransaction<'static>) -> Result<()> {
+        let handle = self.handle();
+
+        let indexes = handle.indexes.read().wrap_err()?;
+        for index in indexes.iter() {
+            index.purge(&mut txn.access())?;
+            IndexMeta::remove(txn, &handle.storage, &handle.name, index.path())?;
+        }
+
+        CollectionMeta::remove(txn, &handle.storage, &handle.name)?;
+
+        txn.access().clear_db(&handle.db).wrap_err()
+    }
+
+    /// Delete the databases of collection and its indexes when the last handle is dropped
+    pub(crate) fn set_deleted(&self) -> Result<()> {
+        let handle = self.handle();
 
         let indexes = handle.indexes.read().wrap_err()?;
         for index in indexes.iter() {
-            index.purge(&mut access)?;
-            index.to_delete(&mut access)?;
+            index.set_deleted();
         }
 
         handle.delete.store(true, AtomicOrdering::SeqCst);
-        access.clear_db(&handle.db).wrap_err()
+        Ok(())
+    }
+}
+
+fn copy_db(
+    txn: &ConstTransaction,
+    src: &Database,
+    dst: &Database,
+    access: &mut WriteAccessor,
+) -> Result<()> {
+    let mut cursor = txn.cursor(src).wrap_err()?;
+
+    // the entries are copied out because the same accessor is used for reading and writing
+    let mut entry = owned_entry(cursor.first::<[u8], [u8]>(access).to_opt().wrap_err()?);
+
+    while let Some((key, val)) = entry {
+        access.put(dst, &key, &val, PutFlags::empty()).wrap_err()?;
+        entry = owned_entry(cursor.next::<[u8], [u8]>(access).to_opt().wrap_err()?);
     }
+
+    Ok(())
+}
+
+fn owned_entry(entry: Option<(&[u8], &[u8])>) -> Option<(Vec<u8>, Vec<u8>)> {
+    entry.map(|(key, val)| (key.to_vec(), val.to_vec()))
 }
 
 impl Drop for Collection {
@@ -727,66 +1908,139 @@ impl<'a> Into<Supercow<'a, Database<'a>>> for Collection {
     }
 }
 
-pub(crate) struct PrimaryIterator {
-    txn: Arc<ReadTransaction<'static>>,
-    cur: Cursor<'static, 'static>,
+pub(crate) struct PrimaryIterator<'txn> {
+    txn: &'txn ConstTransaction<'static>,
+    cur: Cursor<'txn, 'txn>,
+    primary: PrimaryType,
     order: OrderKind,
     init: bool,
+    after: Option<Vec<u8>>,
 }
 
-impl PrimaryIterator {
+impl<'txn> PrimaryIterator<'txn> {
     pub(crate) fn new(
-        txn: Arc<ReadTransaction<'static>>,
+        txn: &'txn ConstTransaction<'static>,
         coll: Collection,
         order: OrderKind,
     ) -> Result<Self> {
+        let primary = coll.primary_type();
         let cur = txn.cursor(coll)?;
 
         Ok(Self {
             txn,
             cur,
+            primary,
             order,
             init: false,
+            after: None,
         })
     }
+
+    /// Start iterating after the specified primary key
+    pub(crate) fn after(mut self, id: &Primary) -> Result<Self> {
+        self.after = Some(self.primary.encode(id)?);
+        Ok(self)
+    }
 }
 
-impl Iterator for PrimaryIterator {
+impl<'txn> Iterator for PrimaryIterator<'txn> {
     type Item = Result<Primary>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let access = self.txn.access();
         match if self.init {
             match self.order {
-                OrderKind::Asc => self.cur.next::<Unaligned<Primary>, [u8]>(&access),
-                OrderKind::Desc => self.cur.prev::<Unaligned<Primary>, [u8]>(&access),
+                OrderKind::Asc => self.cur.next::<[u8], [u8]>(&access),
+                OrderKind::Desc => self.cur.prev::<[u8], [u8]>(&access),
             }
         } else {
             self.init = true;
-            match self.order {
-                OrderKind::Asc => self.cur.first::<Unaligned<Primary>, [u8]>(&access),
-                OrderKind::Desc => self.cur.last::<Unaligned<Primary>, [u8]>(&access),
+            if let Some(id) = self.after.take() {
+                seek_after(&mut self.cur, &access, &id, None, self.order)
+            } else {
+                match self.order {
+                    OrderKind::Asc => self.cur.first::<[u8], [u8]>(&access),
+                    OrderKind::Desc => self.cur.last::<[u8], [u8]>(&access),
+                }
             }
         }
         .to_opt()
         {
-            Ok(Some((id, _val))) => Some(Ok(id.get())),
+            Ok(Some((id, _val))) => Some(self.primary.decode(id)),
             Ok(None) => None,
             Err(e) => Some(Err(e).wrap_err()),
         }
     }
 }
 
+/// Move cursor to the entry which follows the specified key in order
+///
+/// The value should be specified for the databases with duplicates to start after the entry with same key and value.
+///
+pub(crate) fn seek_after<'access>(
+    cur: &mut Cursor,
+    access: &'access ConstAccessor,
+    key: &[u8],
+    val: Option<&[u8]>,
+    order: OrderKind,
+) -> lmdb::Result<(&'access [u8], &'access [u8])> {
+    match (order, val) {
+        (OrderKind::Asc, None) => match cur.seek_range_k::<[u8], [u8]>(access, key) {
+            Ok((found, _)) if found == key => cur.next(access),
+            res => res,
+        },
+        (OrderKind::Desc, None) => match cur.seek_range_k::<[u8], [u8]>(access, key).to_opt()? {
+            Some(_) => cur.prev(access),
+            None => cur.last(access),
+        },
+        (OrderKind::Asc, Some(val)) => {
+            match cur
+                .seek_k_nearest_v::<[u8], [u8]>(access, key, val)
+                .to_opt()?
+            {
+                Some(found) if found == val => cur.next(access),
+                Some(_) => cur.get_current(access),
+                // no same key or all values of key are less
+                None => match cur.seek_range_k::<[u8], [u8]>(access, key) {
+                    Ok((found, _)) if found == key => cur.next_nodup(access),
+                    res => res,
+                },
+            }
+        }
+        (OrderKind::Desc, Some(val)) => {
+            match cur
+                .seek_k_nearest_v::<[u8], [u8]>(access, key, val)
+                .to_opt()?
+            {
+                Some(_) => cur.prev(access),
+                // no same key or all values of key are less
+                None => match cur.seek_range_k::<[u8], [u8]>(access, key).to_opt()? {
+                    Some((found, _)) if found == key => {
+                        cur.last_dup::<[u8]>(access)?;
+                        cur.get_current(access)
+                    }
+                    Some(_) => cur.prev(access),
+                    None => cur.last(access),
+                },
+            }
+        }
+    }
+}
+
 /// Iterator across found documents
 ///
 /// You can use that to extract documents contents
 ///
 /// The `DocumentsIterator::len()` method gets total number of found documents.
 ///
+/// *Note*: Each document will be read using separate transaction, so use `Storage::snapshot()` when you need consistent results.
+///
 pub struct DocumentsIterator<T> {
     storage: Storage,
     coll: Collection,
     ids_iter: Box<dyn Iterator<Item = Primary> + Send>,
+    projection: Option<Projection>,
+    lookups: Vec<(Lookup, Collection)>,
     phantom_doc: PhantomData<T>,
 }
 
@@ -800,9 +2054,30 @@ impl<T> DocumentsIterator<T> {
             storage,
             coll,
             ids_iter: Box::new(ids_iter.into_iter()),
+            projection: None,
+            lookups: Vec::new(),
             phantom_doc: PhantomData,
         })
     }
+
+    /// Apply projection to the documents before deserializing
+    ///
+    /// The type of documents must allow the missing fields when some fields are excluded.
+    ///
+    pub fn project(mut self, projection: Projection) -> Self {
+        self.projection = Some(projection);
+        self
+    }
+
+    /// Embed the documents of other collection which referenced by documents
+    ///
+    /// The referenced documents are read using the same transaction as the document which references it.
+    ///
+    pub fn lookup(mut self, lookup: Lookup) -> Result<Self> {
+        let coll = lookup_collection(&self.coll, &lookup)?;
+        self.lookups.push((lookup, coll));
+        Ok(self)
+    }
 }
 
 impl<T> Iterator for DocumentsIterator<T>
@@ -813,17 +2088,15 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         self.ids_iter.next().map(|id| {
-            let txn = ReadTransaction::new(self.storage.clone())?;
-            {
-                let access = txn.access();
-                access
-                    .get(&self.coll, &Unaligned::new(id))
-                    .wrap_err()
-                    .and_then(RawDocument::from_bin)
-                    .map(|doc| doc.with_id(id))
-                    .and_then(RawDocument::into_doc)
-                    .wrap_err()
-            }
+            let txn = self.storage.read_txn()?;
+            load_document(
+                &txn,
+                &self.coll,
+                id,
+                self.projection.as_ref(),
+                &self.lookups,
+            )
+            .and_then(RawDocument::into_doc)
         })
     }
 
@@ -834,12 +2107,172 @@ where
 
 impl<T> ExactSizeIterator for DocumentsIterator<T> where T: DeserializeOwned + Document {}
 
-#[allow(clippy::trivially_copy_pass_by_ref)]
+/// Iterator across found documents within transaction
+///
+/// Unlike the `DocumentsIterator` it reads documents using the transaction which the documents was found.
+///
+pub struct TxnDocumentsIterator<'txn, T> {
+    txn: &'txn ConstTransaction<'static>,
+    coll: Collection,
+    ids_iter: IntoIter<Primary>,
+    projection: Option<Projection>,
+    lookups: Vec<(Lookup, Collection)>,
+    phantom_doc: PhantomData<T>,
+}
+
+impl<'txn, T> TxnDocumentsIterator<'txn, T> {
+    pub(crate) fn new(
+        txn: &'txn ConstTransaction<'static>,
+        coll: Collection,
+        ids: Vec<Primary>,
+    ) -> Result<Self> {
+        Ok(Self {
+            txn,
+            coll,
+            ids_iter: ids.into_iter(),
+            projection: None,
+            lookups: Vec::new(),
+            phantom_doc: PhantomData,
+        })
+    }
+
+    /// Apply projection to the documents before deserializing
+    ///
+    /// The type of documents must allow the missing fields when some fields are excluded.
+    ///
+    pub fn project(mut self, projection: Projection) -> Self {
+        self.projection = Some(projection);
+        self
+    }
+
+    /// Embed the documents of other collection which referenced by documents
+    ///
+    /// The referenced documents are read using the same transaction as the document which references it.
+    ///
+    pub fn lookup(mut self, lookup: Lookup) -> Result<Self> {
+        let coll = lookup_collection(&self.coll, &lookup)?;
+        self.lookups.push((lookup, coll));
+        Ok(self)
+    }
+}
+
+impl<'txn, T> Iterator for TxnDocumentsIterator<'txn, T>
+where
+    T: DeserializeOwned + Document,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ids_iter.next().map(|id| {
+            load_document(
+                self.txn,
+                &self.coll,
+                id,
+                self.projection.as_ref(),
+                &self.lookups,
+            )
+            .and_then(RawDocument::into_doc)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids_iter.size_hint()
+    }
+}
+
+impl<'txn, T> ExactSizeIterator for TxnDocumentsIterator<'txn, T> where
+    T: DeserializeOwned + Document
+{
+}
+
+/// Get the collection of referenced documents
+fn lookup_collection(coll: &Collection, lookup: &Lookup) -> Result<Collection> {
+    let foreign = coll
+        .handle()
+        .storage
+        .get_collection(&lookup.from)?
+        .ok_or_else(|| format!("Missing collection '{}'", lookup.from))
+        .wrap_err()?;
+
+    if let Some(field) = &lookup.foreign {
+        foreign.req_index(field)?;
+    }
+
+    Ok(foreign)
+}
+
+/// Load document with the referenced documents using transaction
+///
+/// The lookups applies before projection, so the embedded documents can be projected too.
+///
+fn load_document(
+    txn: &ConstTransaction<'static>,
+    coll: &Collection,
+    id: Primary,
+    projection: Option<&Projection>,
+    lookups: &[(Lookup, Collection)],
+) -> Result<RawDocument> {
+    let mut doc = {
+        let access = txn.access();
+        access
+            .get::<[u8], [u8]>(coll, &coll.raw_id(&id)?)
+            .wrap_err()
+            .and_then(RawDocument::from_bin)?
+    };
+
+    for (lookup, foreign) in lookups {
+        let docs = foreign.lookup_txn(txn, lookup.foreign.as_ref(), lookup.references(&doc))?;
+        doc = RawDocument::new(lookup.embed(doc.into_inner(), docs));
+    }
+
+    if let Some(projection) = projection {
+        doc = doc.project(projection);
+    }
+
+    Ok(doc.with_id(id))
+}
+
+/// Compare the keys of documents by several fields
+///
+/// The documents which has no keys goes first in ascending order.
+///
+#[allow(clippy::type_complexity)]
+fn order_keys(
+    indexes: &[(Index, OrderKind)],
+    a: &(Vec<Option<KeyData>>, Primary),
+    b: &(Vec<Option<KeyData>>, Primary),
+) -> Ordering {
+    a.0.iter()
+        .zip(&b.0)
+        .zip(indexes)
+        .map(|((a, b), (_index, order))| match order {
+            OrderKind::Asc => a.cmp(b),
+            OrderKind::Desc => b.cmp(a),
+        })
+        .find(|ord| *ord != Ordering::Equal)
+        .unwrap_or_else(|| a.1.cmp(&b.1))
+}
+
 fn order_primary_asc(a: &Primary, b: &Primary) -> Ordering {
     a.cmp(b)
 }
 
-#[allow(clippy::trivially_copy_pass_by_ref)]
 fn order_primary_desc(a: &Primary, b: &Primary) -> Ordering {
     b.cmp(a)
 }
+
+/// Order the scored documents by descending score then by primary key
+fn order_relevance((a, a_score): &(Primary, f64), (b, b_score): &(Primary, f64)) -> Ordering {
+    b_score
+        .partial_cmp(a_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| a.cmp(b))
+}
+
+/// Order the documents by ascending distance then by primary key
+fn order_distance((a, a_dist): &(Primary, f64), (b, b_dist): &(Primary, f64)) -> Ordering {
+    a_dist
+        .partial_cmp(b_dist)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| a.cmp(b))
+}
@@ -1,12 +1,12 @@
 use std::{
-    convert::TryInto,
+    convert::TryFrom,
     ops::{Deref, DerefMut},
 };
 
 use serde::{de::DeserializeOwned, Serialize};
 pub use serde_cbor::Value;
 
-use super::{Document, Primary, Result, ResultWrap};
+use super::{Document, Primary, Projection, Result, ResultWrap};
 
 /// Raw document with id representation
 #[derive(Debug, Clone, PartialEq)]
@@ -49,10 +49,20 @@ impl RawDocument {
     #[inline]
     pub fn req_id(&self) -> Result<Primary> {
         self.get_id()
-            .ok_or_else(|| "Missing document id")
+            .clone()
+            .ok_or("Missing document id")
             .wrap_err()
     }
 
+    /// Apply projection to document
+    ///
+    /// The primary key/identifier is kept as is.
+    ///
+    #[inline]
+    pub fn project(self, projection: &Projection) -> Self {
+        RawDocument(self.0, projection.apply(self.1))
+    }
+
     /// Unwrap document value
     #[inline]
     pub fn into_inner(self) -> Value {
@@ -97,8 +107,13 @@ impl RawDocument {
         let id = match id {
             None => None,
             Some(Value::Null) => None,
-            Some(Value::Integer(id)) => Some(id as u32),
-            _ => return Err("Document primary must be an integer").wrap_err(),
+            Some(Value::Integer(id)) => Some(Primary::Int(
+                u64::try_from(id)
+                    .map_err(|_| "Document primary must be a non-negative integer")
+                    .wrap_err()?,
+            )),
+            Some(Value::Text(id)) => Some(Primary::String(id)),
+            _ => return Err("Document primary must be an integer or a string").wrap_err(),
         };
 
         Ok(RawDocument(id, raw))
@@ -114,10 +129,13 @@ impl RawDocument {
     {
         let RawDocument(id, mut raw) = self;
         if let Value::Map(ref mut obj) = &mut raw {
-            if let Some(id) = &id {
+            if let Some(id) = id {
                 obj.insert(
                     Value::Text(T::primary_field().as_ref().into()),
-                    Value::Integer(u64::from(*id).try_into().unwrap()),
+                    match id {
+                        Primary::Int(id) => Value::Integer(id.into()),
+                        Primary::String(id) => Value::Text(id),
+                    },
                 );
             }
         } else {
@@ -201,7 +219,7 @@ mod test {
     #[ignore]
     fn duplicate_id() {
         let src = User {
-            id: Some(1),
+            id: Some(1.into()),
             name: "ivan".into(),
             email: None,
         };
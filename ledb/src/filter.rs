@@ -1,9 +1,15 @@
-use std::iter::once;
+use std::{
+    collections::HashSet,
+    result::Result as StdResult,
+    str::FromStr,
+};
 
-use serde::{Serialize, Deserialize};
-use lmdb::{ReadTransaction};
+use regex::{Regex, escape};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use lmdb::{ConstTransaction};
 
-use super::{Identifier, Result, KeyData, Selection, Collection};
+use super::{Identifier, Result, KeyData, KeyType, Primary, Selection, Collection, Value, WrappedRegex, GeoNear, GeoPoint, GeoShape, extract_field_keys};
+use crate::text::{is_stop_word, text_terms};
 
 /// Comparison operator of filter
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -29,6 +35,24 @@ pub enum Comp {
     /// Between (in range)
     #[serde(rename = "$bw")]
     Bw(KeyData, bool, KeyData, bool),
+    /// Starts with (string or binary prefix)
+    #[serde(rename = "$prefix")]
+    Prefix(KeyData),
+    /// Matches regular expression (string)
+    #[serde(rename = "$regex")]
+    Regex(WrappedRegex),
+    /// Matches like pattern (string)
+    #[serde(rename = "$like")]
+    Like(LikePattern),
+    /// Contains the words of text (full-text search)
+    #[serde(rename = "$text")]
+    Text(TextQuery),
+    /// Within the radius around point (geo)
+    #[serde(rename = "$near")]
+    Near(GeoNear),
+    /// Within the box or polygon (geo)
+    #[serde(rename = "$within")]
+    Within(GeoShape),
     /// Field exists (not null)
     #[serde(rename = "$has")]
     Has,
@@ -68,47 +92,307 @@ impl Filter {
         Filter::Comp(field.into(), comp)
     }
 
-    pub(crate) fn apply(&self, txn: &ReadTransaction<'static>, coll: &Collection) -> Result<Selection> {
+    /// Apply filter to the collection
+    ///
+    /// The filter will be evaluated according to the plan.
+    pub(crate) fn apply(&self, txn: &ConstTransaction, coll: &Collection) -> Result<Selection> {
+        let documents = coll.documents_txn(txn)?;
+        self.plan(txn, coll, documents)?.apply(txn, coll, documents)
+    }
+
+    /// Check that document satisfies the filter
+    pub(crate) fn matches(&self, doc: &Value) -> bool {
         match self {
             Filter::Cond(cond) => {
                 use self::Cond::*;
-                Ok(match cond {
-                    Not(filter) => !filter.apply(txn, coll)?,
-                    And(filters) => {
-                        let mut res = !Selection::default(); // universe
-                        for filter in filters {
-                            res = res & filter.apply(txn, coll)?;
-                        }
-                        res
-                    },
-                    Or(filters) => {
-                        let mut res = Selection::default(); // empty
-                        for filter in filters {
-                            res = res | filter.apply(txn, coll)?;
-                        }
-                        res
-                    },
-                })
+                match cond {
+                    Not(filter) => !filter.matches(doc),
+                    And(filters) => filters.iter().all(|filter| filter.matches(doc)),
+                    Or(filters) => filters.iter().any(|filter| filter.matches(doc)),
+                }
+            },
+            Filter::Comp(path, comp) => comp.matches(&extract_field_keys(doc, path, comp.key_type())),
+        }
+    }
+}
+
+impl Comp {
+    /// The type of keys which the comparison requires
+    ///
+    /// The geo comparisons requires the points, and the comparisons with timestamps requires the timestamps, while the others accepts the keys of any types.
+    ///
+    pub(crate) fn key_type(&self) -> Option<KeyType> {
+        use self::Comp::*;
+        match self {
+            Near(..) | Within(..) => Some(KeyType::Geo),
+            Eq(val) | Gt(val) | Ge(val) | Lt(val) | Le(val) if is_timestamp(val) => Some(KeyType::Timestamp),
+            Bw(val1, _, val2, _) if is_timestamp(val1) || is_timestamp(val2) => Some(KeyType::Timestamp),
+            In(vals) if vals.iter().any(is_timestamp) => Some(KeyType::Timestamp),
+            _ => None,
+        }
+    }
+
+    /// Check that any of field keys satisfies the comparison
+    fn matches(&self, keys: &HashSet<KeyData>) -> bool {
+        use self::Comp::*;
+        use std::cmp::Ordering::*;
+        if let Text(query) = self {
+            // the words of all keys are treated as single text
+            let words = keys.iter().filter_map(|key| match key {
+                KeyData::String(text) => Some(text_terms(text)),
+                _ => None,
+            }).flatten().collect::<HashSet<_>>();
+            return query.matches(&words);
+        }
+        keys.iter().any(|key| match self {
+            Eq(val) => key.compare(val) == Some(Equal),
+            In(vals) => vals.iter().any(|val| key.compare(val) == Some(Equal)),
+            Gt(val) => key.compare(val) == Some(Greater),
+            Ge(val) => matches!(key.compare(val), Some(Greater) | Some(Equal)),
+            Lt(val) => key.compare(val) == Some(Less),
+            Le(val) => matches!(key.compare(val), Some(Less) | Some(Equal)),
+            Bw(val1, inc1, val2, inc2) => {
+                match (key.compare(val1), key.compare(val2)) {
+                    (Some(beg), Some(end)) =>
+                        (beg == Greater || *inc1 && beg == Equal) &&
+                        (end == Less || *inc2 && end == Equal),
+                    _ => false,
+                }
+            },
+            Prefix(val) => match (key, val.to_type(key.get_type()).as_deref()) {
+                (KeyData::String(key), Some(KeyData::String(val))) => key.starts_with(val.as_str()),
+                (KeyData::Binary(key), Some(KeyData::Binary(val))) => key.starts_with(val),
+                _ => false,
+            },
+            Regex(re) => match key {
+                KeyData::String(key) => re.is_match(key),
+                _ => false,
+            },
+            Like(pat) => match key {
+                KeyData::String(key) => pat.is_match(key),
+                _ => false,
             },
-            Filter::Comp(path, comp) => {
-                let index = coll.req_index(path)?;
-                let access = txn.access();
-                use self::Comp::*;
-                Ok(match comp {
-                    Eq(val) => Selection::new(index.query_set(&txn, &access, once(val))?, false),
-                    In(vals) => Selection::new(index.query_set(&txn, &access, vals.iter())?, false),
-                    Gt(val) => Selection::new(index.query_range(&txn, &access, Some((val, false)), None)?, false),
-                    Ge(val) => Selection::new(index.query_range(&txn, &access, Some((val, true)), None)?, false),
-                    Lt(val) => Selection::new(index.query_range(&txn, &access, None, Some((val, false)))?, false),
-                    Le(val) => Selection::new(index.query_range(&txn, &access, None, Some((val, true)))?, false),
-                    Bw(val1, inc1, val2, inc2) => Selection::new(index.query_range(&txn, &access, Some((val1, *inc1)), Some((val2, *inc2)))?, false),
-                    Has => Selection::new(index.query_range(&txn, &access, None, None)?, false),
-                })
+            Text(..) => false,
+            Near(near) => match key {
+                KeyData::Geo(point) => near.contains(point),
+                _ => false,
             },
+            Within(shape) => match key {
+                KeyData::Geo(point) => shape.contains(point),
+                _ => false,
+            },
+            Has => true,
+        })
+    }
+}
+
+fn is_timestamp(val: &KeyData) -> bool {
+    matches!(val, KeyData::Timestamp(..))
+}
+
+/// The pattern for matching strings like SQL does
+///
+/// The `%` matches any sequence of chars, the `_` matches any single char, the `\` escapes the next char.
+///
+#[derive(Debug, Clone)]
+pub struct LikePattern {
+    pattern: String,
+    prefix: String,
+    regex: Regex,
+}
+
+impl LikePattern {
+    pub fn new<S: Into<String>>(pattern: S) -> Self {
+        let pattern = pattern.into();
+        let mut prefix = String::new();
+        let mut regex = String::from("^(?s:");
+        let mut literal = true;
+        let mut chars = pattern.chars();
+
+        while let Some(chr) = chars.next() {
+            match chr {
+                '%' => { regex.push_str(".*"); literal = false; },
+                '_' => { regex.push('.'); literal = false; },
+                chr => {
+                    let chr = if chr == '\\' { chars.next().unwrap_or(chr) } else { chr };
+                    regex.push_str(&escape(chr.encode_utf8(&mut [0; 4])));
+                    if literal {
+                        prefix.push(chr);
+                    }
+                },
+            }
         }
+
+        regex.push_str(")$");
+
+        // the escaped pattern is always valid
+        let regex = Regex::new(&regex).unwrap();
+
+        LikePattern { pattern, prefix, regex }
+    }
+
+    /// Get the source pattern
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Get the literal prefix which all matched strings starts with
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Check that string matches pattern
+    pub fn is_match(&self, val: &str) -> bool {
+        self.regex.is_match(val)
+    }
+}
+
+impl<'a> From<&'a str> for LikePattern {
+    fn from(pattern: &'a str) -> Self {
+        LikePattern::new(pattern)
+    }
+}
+
+impl From<String> for LikePattern {
+    fn from(pattern: String) -> Self {
+        LikePattern::new(pattern)
+    }
+}
+
+impl FromStr for LikePattern {
+    type Err = ();
+
+    fn from_str(pattern: &str) -> StdResult<Self, Self::Err> {
+        Ok(LikePattern::new(pattern))
+    }
+}
+
+impl PartialEq for LikePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for LikePattern {}
+
+impl Serialize for LikePattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        serializer.serialize_str(&self.pattern)
+    }
+}
+
+impl<'de> Deserialize<'de> for LikePattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        String::deserialize(deserializer).map(LikePattern::new)
     }
 }
 
+/// The query of full-text search
+///
+/// The text of query is split to the terms like the text of indexed field. The documents which contains all of terms will be found, or any of terms when `any` is set.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextQuery {
+    /// The text to search
+    pub text: String,
+    /// Search the documents which contains any of terms
+    #[serde(default)]
+    pub any: bool,
+    /// Skip the stop words of text
+    #[serde(default)]
+    pub skip_stop_words: bool,
+}
+
+impl TextQuery {
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        TextQuery {
+            text: text.into(),
+            any: false,
+            skip_stop_words: false,
+        }
+    }
+
+    /// Search the documents which contains any of terms
+    pub fn any(mut self) -> Self {
+        self.any = true;
+        self
+    }
+
+    /// Skip the stop words of text
+    pub fn skip_stop_words(mut self) -> Self {
+        self.skip_stop_words = true;
+        self
+    }
+
+    /// Get the unique terms of text
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        for term in text_terms(&self.text) {
+            if (!self.skip_stop_words || !is_stop_word(&term)) && !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        terms
+    }
+
+    /// Check that the words of document satisfies the query
+    fn matches(&self, words: &HashSet<String>) -> bool {
+        let terms = self.terms();
+        if self.any {
+            terms.iter().any(|term| words.contains(term))
+        } else {
+            !terms.is_empty() && terms.iter().all(|term| words.contains(term))
+        }
+    }
+}
+
+impl<'a> From<&'a str> for TextQuery {
+    fn from(text: &'a str) -> Self {
+        TextQuery::new(text)
+    }
+}
+
+impl From<String> for TextQuery {
+    fn from(text: String) -> Self {
+        TextQuery::new(text)
+    }
+}
+
+/// Get the literal prefix of regular expression which all matched strings starts with
+///
+/// Only the patterns anchored to start without alternations has non-empty prefix.
+///
+pub(crate) fn regex_prefix(pattern: &str) -> String {
+    let mut prefix = String::new();
+
+    if !pattern.starts_with('^') || pattern.contains('|') {
+        return prefix;
+    }
+
+    let mut chars = pattern[1..].chars().peekable();
+
+    while let Some(chr) = chars.next() {
+        let chr = match chr {
+            '\\' => match chars.next() {
+                Some(chr) if chr.is_ascii_punctuation() => chr,
+                // the escaped classes
+                _ => break,
+            },
+            '.' | '[' | ']' | '(' | ')' | '{' | '}' | '*' | '+' | '?' | '^' | '$' => break,
+            chr => chr,
+        };
+
+        if let Some('*') | Some('+') | Some('?') | Some('{') = chars.peek() {
+            // the quantified char may be omitted or repeated
+            break;
+        }
+
+        prefix.push(chr);
+    }
+
+    prefix
+}
+
 /// The kind ot order
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderKind {
@@ -137,6 +421,27 @@ pub enum Order {
     /// Order by specified indexed field
     #[serde(with = "order")]
     Field(Identifier, OrderKind),
+
+    /// Order by several indexed fields
+    ///
+    /// The next field is used when the keys of previous fields are equal, and the primary key is used as the final tie-breaker.
+    ///
+    #[serde(with = "order_fields")]
+    Fields(Vec<(Identifier, OrderKind)>),
+
+    /// Order by relevance of full-text search on specified field
+    ///
+    /// The documents which contains more of rare terms of query goes first.
+    ///
+    #[serde(with = "order_relevance")]
+    Relevance(Identifier),
+
+    /// Order by distance from point to the nearest point of specified geo indexed field
+    ///
+    /// The documents which hasn't points are omitted.
+    ///
+    #[serde(with = "order_distance")]
+    Distance(Identifier, GeoPoint),
 }
 
 impl Default for Order {
@@ -151,6 +456,60 @@ impl Order {
     pub fn field<F: Into<Identifier>>(field: F, kind: OrderKind) -> Self {
         Order::Field(field.into(), kind)
     }
+
+    pub fn fields<F: Into<Identifier>, I: IntoIterator<Item = (F, OrderKind)>>(fields: I) -> Self {
+        Order::Fields(fields.into_iter().map(|(field, kind)| (field.into(), kind)).collect())
+    }
+
+    pub fn relevance<F: Into<Identifier>>(field: F) -> Self {
+        Order::Relevance(field.into())
+    }
+
+    pub fn distance<F: Into<Identifier>>(field: F, point: GeoPoint) -> Self {
+        Order::Distance(field.into(), point)
+    }
+}
+
+/// The page of found documents
+///
+/// The skipping and limiting applies while iterating primary keys or index, so the rest of entries will not be touched.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page {
+    /// The number of documents to skip
+    #[serde(default)]
+    pub skip: usize,
+    /// The max number of documents
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Start after the document at position (keyset pagination)
+    #[serde(default)]
+    pub after: Option<Position>,
+}
+
+impl Page {
+    pub fn new(skip: usize, limit: Option<usize>) -> Self {
+        Page { skip, limit, after: None }
+    }
+
+    /// Start after the document with specified sort key and primary key
+    ///
+    /// The sort key is required when documents ordered by field, but when documents ordered by several fields the keys will be taken from the document itself.
+    ///
+    pub fn after<I: Into<Primary>>(mut self, key: Option<KeyData>, id: I) -> Self {
+        self.after = Some(Position { key, id: id.into() });
+        self
+    }
+}
+
+/// The position of document in the order of found documents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    /// The key of ordering field
+    #[serde(default)]
+    pub key: Option<KeyData>,
+    /// The primary key of document
+    pub id: Primary,
 }
 
 mod comp {
@@ -196,9 +555,101 @@ mod order {
     }
 }
 
+mod order_fields {
+    use super::{Identifier, OrderKind};
+    use std::collections::HashMap;
+    use serde::{Serialize, Serializer, Deserializer, Deserialize, de::{self}, ser::{SerializeSeq}};
+
+    struct Field<'a>(&'a Identifier, &'a OrderKind);
+
+    impl<'a> Serialize for Field<'a> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::order::serialize(self.0, self.1, serializer)
+        }
+    }
+
+    #[allow(clippy::ptr_arg)]
+    pub fn serialize<S: Serializer>(fields: &Vec<(Identifier, OrderKind)>, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(fields.len()))?;
+        for (field, op) in fields {
+            seq.serialize_element(&Field(field, op))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(Identifier, OrderKind)>, D::Error> {
+        let maps: Vec<HashMap<String, OrderKind>> = Vec::deserialize(deserializer)?;
+        maps.into_iter().map(|map| {
+            let mut it = map.into_iter();
+            match (it.next(), it.next()) {
+                (Some((field, op)), None) => Ok((field.into(), op)),
+                _ => Err(de::Error::custom("Not an order kind"))
+            }
+        }).collect()
+    }
+}
+
+mod order_relevance {
+    use super::Identifier;
+    use std::collections::HashMap;
+    use serde::{Serializer, Deserializer, Deserialize, de::{self}, ser::{SerializeMap}};
+
+    pub fn serialize<S: Serializer>(field: &Identifier, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("$relevance", &field)?;
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Identifier, D::Error> {
+        let map: HashMap<String, String> = HashMap::deserialize(deserializer)?;
+        let mut it = map.into_iter();
+        match (it.next(), it.next()) {
+            (Some((op, field)), None) if op == "$relevance" => Ok(field.into()),
+            _ => Err(de::Error::custom("Not a relevance order"))
+        }
+    }
+}
+
+mod order_distance {
+    use super::{Identifier, GeoPoint};
+    use std::collections::HashMap;
+    use serde::{Serialize, Serializer, Deserializer, Deserialize, de::{self}, ser::{SerializeMap}};
+
+    struct Field<'a>(&'a Identifier, &'a GeoPoint);
+
+    impl<'a> Serialize for Field<'a> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry(self.0, self.1)?;
+            map.end()
+        }
+    }
+
+    pub fn serialize<S: Serializer>(field: &Identifier, point: &GeoPoint, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("$distance", &Field(field, point))?;
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(Identifier, GeoPoint), D::Error> {
+        let map: HashMap<String, HashMap<String, GeoPoint>> = HashMap::deserialize(deserializer)?;
+        let mut it = map.into_iter();
+        match (it.next(), it.next()) {
+            (Some((op, fields)), None) if op == "$distance" => {
+                let mut it = fields.into_iter();
+                match (it.next(), it.next()) {
+                    (Some((field, point)), None) => Ok((field.into(), point)),
+                    _ => Err(de::Error::custom("Not a distance order")),
+                }
+            },
+            _ => Err(de::Error::custom("Not a distance order"))
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{Filter, Comp, Cond, KeyData, Order, OrderKind};
+    use super::{Filter, Comp, Cond, KeyData, LikePattern, Order, OrderKind, Page, TextQuery, WrappedRegex, GeoNear, GeoPoint, GeoShape, regex_prefix};
     use serde_json::{from_str, to_string, Value, json};
 
     #[test]
@@ -223,6 +674,86 @@ mod test {
                     json!({ "name": { "$eq": "vlada" } }));
     }
 
+    #[test]
+    fn parse_comp_prefix() {
+        test_parse!(Filter, json!({ "path": { "$prefix": "a/b/" } }),
+                    Filter::Comp("path".into(),
+                                 Comp::Prefix(KeyData::String("a/b/".into()))
+                    ));
+    }
+
+    #[test]
+    fn build_comp_prefix() {
+        test_build!(Filter::Comp("path".into(),
+                                 Comp::Prefix(KeyData::String("a/b/".into()))),
+                    json!({ "path": { "$prefix": "a/b/" } }));
+    }
+
+    #[test]
+    fn parse_comp_regex() {
+        test_parse!(Filter, json!({ "name": { "$regex": "^foo.*bar$" } }),
+                    Filter::Comp("name".into(),
+                                 Comp::Regex(WrappedRegex("^foo.*bar$".parse().unwrap()))
+                    ));
+    }
+
+    #[test]
+    fn build_comp_like() {
+        test_build!(Filter::Comp("name".into(),
+                                 Comp::Like(LikePattern::new("a%b"))),
+                    json!({ "name": { "$like": "a%b" } }));
+    }
+
+    #[test]
+    fn parse_comp_text() {
+        test_parse!(Filter, json!({ "text": { "$text": { "text": "quick fox" } } }),
+                    Filter::Comp("text".into(),
+                                 Comp::Text(TextQuery::new("quick fox"))
+                    ));
+        test_parse!(Filter, json!({ "text": { "$text": { "text": "the fox", "any": true, "skip_stop_words": true } } }),
+                    Filter::Comp("text".into(),
+                                 Comp::Text(TextQuery::new("the fox").any().skip_stop_words())
+                    ));
+    }
+
+    #[test]
+    fn text_query_terms() {
+        assert_eq!(
+            TextQuery::new("The fox, the DOG").terms(),
+            vec!["the", "fox", "dog"]
+        );
+        assert_eq!(
+            TextQuery::new("The fox, the DOG").skip_stop_words().terms(),
+            vec!["fox", "dog"]
+        );
+    }
+
+    #[test]
+    fn like_pattern() {
+        let pat = LikePattern::new("ab_d%");
+        assert_eq!(pat.prefix(), "ab");
+        assert!(pat.is_match("abcd"));
+        assert!(pat.is_match("abcdef"));
+        assert!(!pat.is_match("abd"));
+        assert!(!pat.is_match("xabcd"));
+
+        let pat = LikePattern::new("100\\%.*");
+        assert_eq!(pat.prefix(), "100%.*");
+        assert!(pat.is_match("100%.*"));
+        assert!(!pat.is_match("1000.*"));
+    }
+
+    #[test]
+    fn regex_literal_prefix() {
+        assert_eq!(regex_prefix("^foo.*bar$"), "foo");
+        assert_eq!(regex_prefix("^a/b\\.c"), "a/b.c");
+        assert_eq!(regex_prefix("^abc?"), "ab");
+        assert_eq!(regex_prefix("^ab\\d"), "ab");
+        assert_eq!(regex_prefix("^ab|cd"), "");
+        assert_eq!(regex_prefix("(?i)^abc"), "");
+        assert_eq!(regex_prefix("abc"), "");
+    }
+
     #[test]
     fn parse_cond_not() {
         test_parse!(Filter, json!({ "$not": { "a":{ "$gt": 9 } } }),
@@ -303,4 +834,67 @@ mod test {
         test_build!(Order::Field("time".into(), OrderKind::Desc),
                    json!({ "time": "$desc" }));
     }
+
+    #[test]
+    fn parse_order_relevance() {
+        test_parse!(Order, json!({ "$relevance": "text" }),
+                    Order::Relevance("text".into()));
+    }
+
+    #[test]
+    fn build_order_relevance() {
+        test_build!(Order::Relevance("text".into()),
+                    json!({ "$relevance": "text" }));
+    }
+
+    #[test]
+    fn parse_comp_near() {
+        test_parse!(Filter, json!({ "loc": { "$near": { "point": { "lat": 55.75, "lon": 37.62 }, "radius": 500.0 } } }),
+                    Filter::Comp("loc".into(),
+                                 Comp::Near(GeoNear::new(GeoPoint::new(55.75, 37.62), 500.0))
+                    ));
+    }
+
+    #[test]
+    fn build_comp_within() {
+        test_build!(Filter::Comp("loc".into(),
+                                 Comp::Within(GeoShape::Box(GeoPoint::new(55.5, 37.0), GeoPoint::new(56.0, 38.0)))),
+                    json!({ "loc": { "$within": { "$box": [{ "lat": 55.5, "lon": 37.0 }, { "lat": 56.0, "lon": 38.0 }] } } }));
+    }
+
+    #[test]
+    fn parse_order_distance() {
+        test_parse!(Order, json!({ "$distance": { "loc": { "lat": 55.75, "lon": 37.62 } } }),
+                    Order::Distance("loc".into(), GeoPoint::new(55.75, 37.62)));
+    }
+
+    #[test]
+    fn build_order_distance() {
+        test_build!(Order::distance("loc", GeoPoint::new(55.75, 37.62)),
+                    json!({ "$distance": { "loc": { "lat": 55.75, "lon": 37.62 } } }));
+    }
+
+    #[test]
+    fn parse_page() {
+        test_parse!(Page, json!({}),
+                    Page::default());
+        test_parse!(Page, json!({ "skip": 10, "limit": 5 }),
+                    Page::new(10, Some(5)));
+        test_parse!(Page, json!({ "after": { "key": "abc", "id": 3 } }),
+                    Page::default().after(Some(KeyData::String("abc".into())), 3));
+        test_parse!(Page, json!({ "limit": 5, "after": { "id": "abc" } }),
+                    Page::new(0, Some(5)).after(None, "abc"));
+    }
+
+    #[test]
+    fn parse_order_fields() {
+        test_parse!(Order, json!([{ "name": "$asc" }, { "time": "$desc" }]),
+                    Order::Fields(vec![("name".into(), OrderKind::Asc), ("time".into(), OrderKind::Desc)]));
+    }
+
+    #[test]
+    fn build_order_fields() {
+        test_build!(Order::fields(vec![("name", OrderKind::Asc), ("time", OrderKind::Desc)]),
+                    json!([{ "name": "$asc" }, { "time": "$desc" }]));
+    }
 }
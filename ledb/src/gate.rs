@@ -0,0 +1,149 @@
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+use lmdb::Environment;
+use supercow::{ext::ConstDeref, NonSyncSupercow, Supercow};
+
+use super::{Result, ResultWrap, Storage};
+
+/// The maximum time of waiting for the active transactions to finish before resizing
+const RESIZE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The gate which counts active transactions
+///
+/// The map of environment can be resized only when no transactions is active in the process, so the resizing closes the gate and waits for the active transactions to finish.
+#[derive(Default)]
+pub(crate) struct TxnGate {
+    state: Mutex<GateState>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct GateState {
+    // the number of active transactions per thread
+    active: HashMap<ThreadId, usize>,
+    resizing: bool,
+}
+
+impl TxnGate {
+    /// Register new transaction
+    ///
+    /// The threads which already have active transactions are not blocked by resizing, because it waits for them.
+    fn enter(&self) -> Result<ThreadId> {
+        let thread = thread::current().id();
+        let mut state = self.state.lock().wrap_err()?;
+
+        while state.resizing && !state.active.contains_key(&thread) {
+            state = self.cond.wait(state).wrap_err()?;
+        }
+
+        *state.active.entry(thread).or_insert(0) += 1;
+
+        Ok(thread)
+    }
+
+    fn leave(&self, thread: ThreadId) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(count) = state.active.get_mut(&thread) {
+                *count -= 1;
+                if *count == 0 {
+                    state.active.remove(&thread);
+                }
+            }
+            self.cond.notify_all();
+        }
+    }
+
+    /// Run the function exclusively when no transactions is active
+    ///
+    /// Returns `None` when the current thread has active transactions, so waiting for them never ends.
+    /// Also returns `None` when the transactions of other threads isn't finished in time, because they may wait for the current thread.
+    pub(crate) fn exclusive<R>(&self, func: impl FnOnce() -> R) -> Result<Option<R>> {
+        let thread = thread::current().id();
+        let mut state = self.state.lock().wrap_err()?;
+
+        if state.active.contains_key(&thread) {
+            return Ok(None);
+        }
+
+        // wait for the concurrent resizing
+        while state.resizing {
+            state = self.cond.wait(state).wrap_err()?;
+        }
+
+        state.resizing = true;
+
+        let deadline = Instant::now() + RESIZE_TIMEOUT;
+
+        while !state.active.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                state.resizing = false;
+                self.cond.notify_all();
+                return Ok(None);
+            }
+            state = self.cond.wait_timeout(state, deadline - now).wrap_err()?.0;
+        }
+
+        let res = func();
+
+        state.resizing = false;
+        self.cond.notify_all();
+
+        Ok(Some(res))
+    }
+}
+
+struct TxnGuard {
+    storage: Storage,
+    thread: ThreadId,
+}
+
+impl Drop for TxnGuard {
+    fn drop(&mut self) {
+        self.storage.gate().leave(self.thread);
+    }
+}
+
+/// The environment handle for single transaction
+///
+/// It holds the transactions gate open while the transaction is alive.
+#[derive(Clone)]
+pub(crate) struct TxnEnv(Arc<TxnGuard>);
+
+impl TxnEnv {
+    pub(crate) fn new(storage: Storage) -> Result<Self> {
+        let thread = storage.gate().enter()?;
+
+        Ok(TxnEnv(Arc::new(TxnGuard { storage, thread })))
+    }
+}
+
+impl Deref for TxnEnv {
+    type Target = Environment;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0.storage
+    }
+}
+
+unsafe impl ConstDeref for TxnEnv {
+    type Target = Environment;
+
+    #[inline]
+    fn const_deref(&self) -> &Self::Target {
+        &self.0.storage
+    }
+}
+
+impl<'env> From<TxnEnv> for NonSyncSupercow<'env, Environment> {
+    fn from(env: TxnEnv) -> Self {
+        Supercow::shared(env)
+    }
+}
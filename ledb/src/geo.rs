@@ -0,0 +1,568 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    hash::{Hash, Hasher},
+    result::Result as StdResult,
+};
+
+use ledb_types::DocumentKeyType;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{KeyType, Result, Value};
+
+/// The mean radius of Earth in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// The number of levels of cells smaller than the bounds which used to cover the bounds by the ranges of keys
+///
+/// The more levels gives the more ranges but the less keys to check.
+const COVER_LEVELS: u32 = 3;
+
+/// Geographic point
+///
+/// The points are stored into index as 64-bit Z-order (Morton) codes which interleaves the bits of quantized longitude and latitude, so the points which are near usually have near keys. The precision of stored points is about one centimeter, and the points which have same codes are treated as equal.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct GeoPoint {
+    lat: f64,
+    lon: f64,
+    code: [u8; 8],
+}
+
+/// The coordinates of point in JSON representation
+#[derive(Serialize, Deserialize)]
+struct Coords {
+    lat: f64,
+    lon: f64,
+}
+
+impl GeoPoint {
+    /// Create point using latitude and longitude in degrees
+    ///
+    /// The coordinates out of range will be clamped.
+    ///
+    pub fn new(lat: f64, lon: f64) -> Self {
+        let lat = lat.clamp(-90.0, 90.0);
+        let lon = lon.clamp(-180.0, 180.0);
+        let code = interleave(quantize_lon(lon), quantize_lat(lat));
+        GeoPoint {
+            lat,
+            lon,
+            code: code.to_be_bytes(),
+        }
+    }
+
+    /// The latitude in degrees
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+
+    /// The longitude in degrees
+    pub fn lon(&self) -> f64 {
+        self.lon
+    }
+
+    /// The great-circle distance to other point in meters
+    pub fn distance(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+
+    /// Restore point from the code which stored in index
+    ///
+    /// The coordinates will be the center of the cell of code.
+    ///
+    pub(crate) fn from_raw(raw: &[u8]) -> Result<Self> {
+        if raw.len() != 8 {
+            return Err("Geo key must be 8 bytes length".into());
+        }
+        let mut code = [0u8; 8];
+        code.copy_from_slice(raw);
+        let (lon, lat) = deinterleave(u64::from_be_bytes(code));
+        Ok(GeoPoint {
+            lat: dequantize_lat(lat),
+            lon: dequantize_lon(lon),
+            code,
+        })
+    }
+
+    /// The code of point which stored in index
+    pub(crate) fn as_raw(&self) -> &[u8] {
+        &self.code
+    }
+
+    /// Converts generic value into point
+    ///
+    /// The points are represented as `{"lat": .., "lon": ..}` objects or `[lon, lat]` arrays.
+    ///
+    pub(crate) fn from_val(val: &Value) -> Option<Self> {
+        let (lat, lon) = match val {
+            Value::Map(val) => (
+                coord(val.get(&Value::Text("lat".into()))?)?,
+                coord(val.get(&Value::Text("lon".into()))?)?,
+            ),
+            Value::Array(val) if val.len() == 2 => (coord(&val[1])?, coord(&val[0])?),
+            _ => return None,
+        };
+        if is_valid(lat, lon) {
+            Some(GeoPoint::new(lat, lon))
+        } else {
+            None
+        }
+    }
+
+    /// Converts point into generic value
+    pub(crate) fn into_val(self) -> Value {
+        let mut map = BTreeMap::new();
+        map.insert(Value::Text("lat".into()), Value::Float(self.lat));
+        map.insert(Value::Text("lon".into()), Value::Float(self.lon));
+        Value::Map(map)
+    }
+}
+
+fn coord(val: &Value) -> Option<f64> {
+    match val {
+        Value::Integer(val) => Some(*val as f64),
+        Value::Float(val) => Some(*val),
+        _ => None,
+    }
+}
+
+fn is_valid(lat: f64, lon: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+}
+
+impl PartialEq for GeoPoint {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
+
+impl Eq for GeoPoint {}
+
+impl PartialOrd for GeoPoint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GeoPoint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.code.cmp(&other.code)
+    }
+}
+
+impl Hash for GeoPoint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code.hash(state);
+    }
+}
+
+impl Serialize for GeoPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        Coords {
+            lat: self.lat,
+            lon: self.lon,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GeoPoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        let Coords { lat, lon } = Coords::deserialize(deserializer)?;
+        if is_valid(lat, lon) {
+            Ok(GeoPoint::new(lat, lon))
+        } else {
+            Err(de::Error::custom("Invalid coordinates of geo point"))
+        }
+    }
+}
+
+impl DocumentKeyType for GeoPoint {
+    fn key_type() -> KeyType {
+        KeyType::Geo
+    }
+}
+
+/// The circle around the point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoNear {
+    /// The center of circle
+    pub point: GeoPoint,
+    /// The radius of circle in meters
+    pub radius: f64,
+}
+
+impl Eq for GeoNear {}
+
+impl GeoNear {
+    pub fn new(point: GeoPoint, radius: f64) -> Self {
+        GeoNear { point, radius }
+    }
+
+    /// Check that the point is within the circle
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        self.point.distance(point) <= self.radius
+    }
+
+    /// Get the bounds of circle
+    pub(crate) fn bounds(&self) -> Vec<GeoBounds> {
+        // the angular radius
+        let dist = self.radius.max(0.0) / EARTH_RADIUS;
+        let lat = self.point.lat.to_radians();
+        let (min_lat, max_lat) = (lat - dist, lat + dist);
+        let half_pi = std::f64::consts::FRAC_PI_2;
+
+        if min_lat <= -half_pi || max_lat >= half_pi {
+            // the circle contains pole so all longitudes are covered
+            return vec![GeoBounds::new(
+                min_lat.to_degrees(),
+                -180.0,
+                max_lat.to_degrees(),
+                180.0,
+            )];
+        }
+
+        let dlon = (dist.sin() / lat.cos()).asin().to_degrees();
+        GeoBounds::wrapped(
+            min_lat.to_degrees(),
+            self.point.lon - dlon,
+            max_lat.to_degrees(),
+            self.point.lon + dlon,
+        )
+    }
+}
+
+/// The area on the map
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeoShape {
+    /// The box between south-west and north-east corners
+    ///
+    /// The box which west longitude is greater than east one crosses the antimeridian.
+    ///
+    #[serde(rename = "$box")]
+    Box(GeoPoint, GeoPoint),
+    /// The polygon with vertices
+    ///
+    /// The edges of polygon are the straight lines on the plane of longitudes and latitudes.
+    ///
+    #[serde(rename = "$polygon")]
+    Polygon(Vec<GeoPoint>),
+}
+
+impl GeoShape {
+    /// Check that the point is within the shape
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        match self {
+            GeoShape::Box(sw, ne) => {
+                point.lat >= sw.lat
+                    && point.lat <= ne.lat
+                    && if sw.lon <= ne.lon {
+                        point.lon >= sw.lon && point.lon <= ne.lon
+                    } else {
+                        point.lon >= sw.lon || point.lon <= ne.lon
+                    }
+            }
+            GeoShape::Polygon(vertices) => {
+                // the ray casting
+                let mut inside = false;
+                let mut prev = match vertices.last() {
+                    Some(vertex) => vertex,
+                    None => return false,
+                };
+                for vertex in vertices {
+                    if (vertex.lat > point.lat) != (prev.lat > point.lat)
+                        && point.lon
+                            < (prev.lon - vertex.lon) * (point.lat - vertex.lat)
+                                / (prev.lat - vertex.lat)
+                                + vertex.lon
+                    {
+                        inside = !inside;
+                    }
+                    prev = vertex;
+                }
+                inside
+            }
+        }
+    }
+
+    /// Get the bounds of shape
+    pub(crate) fn bounds(&self) -> Vec<GeoBounds> {
+        match self {
+            GeoShape::Box(sw, ne) => GeoBounds::wrapped(sw.lat, sw.lon, ne.lat, ne.lon),
+            GeoShape::Polygon(vertices) if vertices.is_empty() => Vec::new(),
+            GeoShape::Polygon(vertices) => {
+                let (min_lat, min_lon, max_lat, max_lon) = vertices.iter().fold(
+                    (90.0f64, 180.0f64, -90.0f64, -180.0f64),
+                    |(min_lat, min_lon, max_lat, max_lon), vertex| {
+                        (
+                            min_lat.min(vertex.lat),
+                            min_lon.min(vertex.lon),
+                            max_lat.max(vertex.lat),
+                            max_lon.max(vertex.lon),
+                        )
+                    },
+                );
+                vec![GeoBounds::new(min_lat, min_lon, max_lat, max_lon)]
+            }
+        }
+    }
+}
+
+/// The bounding box which doesn't cross the antimeridian
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GeoBounds {
+    min_lat: u32,
+    min_lon: u32,
+    max_lat: u32,
+    max_lon: u32,
+}
+
+impl GeoBounds {
+    fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        GeoBounds {
+            min_lat: quantize_lat(min_lat.max(-90.0)),
+            min_lon: quantize_lon(min_lon.max(-180.0)),
+            max_lat: quantize_lat(max_lat.min(90.0)),
+            max_lon: quantize_lon(max_lon.min(180.0)),
+        }
+    }
+
+    /// Create bounds which may cross the antimeridian
+    ///
+    /// The bounds which crosses the antimeridian will be split into two.
+    ///
+    fn wrapped(min_lat: f64, west: f64, max_lat: f64, east: f64) -> Vec<Self> {
+        if east - west >= 360.0 {
+            return vec![GeoBounds::new(min_lat, -180.0, max_lat, 180.0)];
+        }
+        let west = if west < -180.0 { west + 360.0 } else { west };
+        let east = if east > 180.0 { east - 360.0 } else { east };
+        if west <= east {
+            vec![GeoBounds::new(min_lat, west, max_lat, east)]
+        } else {
+            vec![
+                GeoBounds::new(min_lat, west, max_lat, 180.0),
+                GeoBounds::new(min_lat, -180.0, max_lat, east),
+            ]
+        }
+    }
+
+    /// Get the ranges of codes which covers the bounds
+    ///
+    /// The bounds are covered by the cells of Z-order curve, so the ranges may contain the codes of points out of bounds, which should be checked.
+    ///
+    pub fn ranges(&self) -> Vec<(u64, u64)> {
+        let mut ranges = Vec::new();
+        if self.min_lat > self.max_lat || self.min_lon > self.max_lon {
+            return ranges;
+        }
+
+        // the level of cells which are comparable with the bounds
+        let size = u32::max(self.max_lat - self.min_lat, self.max_lon - self.min_lon);
+        let level = size.leading_zeros();
+
+        self.cover(0, u32::min(level + COVER_LEVELS, 32), 0, 0, &mut ranges);
+        ranges
+    }
+
+    fn cover(&self, level: u32, max_level: u32, lon: u32, lat: u32, ranges: &mut Vec<(u64, u64)>) {
+        let bits = 32 - level;
+        let last = ((1u64 << bits) - 1) as u32;
+        let (max_lon, max_lat) = (lon + last, lat + last);
+
+        if max_lon < self.min_lon
+            || lon > self.max_lon
+            || max_lat < self.min_lat
+            || lat > self.max_lat
+        {
+            // the cell is out of bounds
+            return;
+        }
+
+        if level < max_level
+            && (lon < self.min_lon
+                || max_lon > self.max_lon
+                || lat < self.min_lat
+                || max_lat > self.max_lat)
+        {
+            // the cell crosses the bounds
+            let half = 1u32 << (bits - 1);
+            self.cover(level + 1, max_level, lon, lat, ranges);
+            self.cover(level + 1, max_level, lon, lat + half, ranges);
+            self.cover(level + 1, max_level, lon + half, lat, ranges);
+            self.cover(level + 1, max_level, lon + half, lat + half, ranges);
+            return;
+        }
+
+        let beg = interleave(lon, lat);
+        let end = interleave(max_lon, max_lat);
+
+        match ranges.last_mut() {
+            // merge the adjacent ranges
+            Some((_, last)) if last.checked_add(1) == Some(beg) => *last = end,
+            _ => ranges.push((beg, end)),
+        }
+    }
+}
+
+fn quantize(val: f64, min: f64, max: f64) -> u32 {
+    let val = (val - min) / (max - min) * 4_294_967_296.0;
+    if val >= 4_294_967_295.0 {
+        u32::MAX
+    } else if val > 0.0 {
+        val as u32
+    } else {
+        0
+    }
+}
+
+fn dequantize(val: u32, min: f64, max: f64) -> f64 {
+    min + (f64::from(val) + 0.5) / 4_294_967_296.0 * (max - min)
+}
+
+fn quantize_lat(lat: f64) -> u32 {
+    quantize(lat, -90.0, 90.0)
+}
+
+fn quantize_lon(lon: f64) -> u32 {
+    quantize(lon, -180.0, 180.0)
+}
+
+fn dequantize_lat(lat: u32) -> f64 {
+    dequantize(lat, -90.0, 90.0)
+}
+
+fn dequantize_lon(lon: u32) -> f64 {
+    dequantize(lon, -180.0, 180.0)
+}
+
+/// Interleave the bits of longitude and latitude
+///
+/// The bit of longitude goes before the bit of latitude.
+///
+fn interleave(lon: u32, lat: u32) -> u64 {
+    spread(lon) << 1 | spread(lat)
+}
+
+fn deinterleave(code: u64) -> (u32, u32) {
+    (squeeze(code >> 1), squeeze(code))
+}
+
+fn spread(val: u32) -> u64 {
+    let mut val = u64::from(val);
+    val = (val | val << 16) & 0x0000_ffff_0000_ffff;
+    val = (val | val << 8) & 0x00ff_00ff_00ff_00ff;
+    val = (val | val << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+    val = (val | val << 2) & 0x3333_3333_3333_3333;
+    (val | val << 1) & 0x5555_5555_5555_5555
+}
+
+fn squeeze(val: u64) -> u32 {
+    let mut val = val & 0x5555_5555_5555_5555;
+    val = (val | val >> 1) & 0x3333_3333_3333_3333;
+    val = (val | val >> 2) & 0x0f0f_0f0f_0f0f_0f0f;
+    val = (val | val >> 4) & 0x00ff_00ff_00ff_00ff;
+    val = (val | val >> 8) & 0x0000_ffff_0000_ffff;
+    (val | val >> 16) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GeoBounds, GeoNear, GeoPoint, GeoShape};
+    use serde_json::{from_str, json, to_string, Value};
+    use std::convert::TryInto;
+
+    #[test]
+    fn parse_geo_shape() {
+        test_parse!(
+            GeoNear,
+            json!({ "point": { "lat": 55.75, "lon": 37.62 }, "radius": 1000.0 }),
+            GeoNear::new(GeoPoint::new(55.75, 37.62), 1000.0)
+        );
+        test_parse!(
+            GeoShape,
+            json!({ "$box": [{ "lat": 55.0, "lon": 37.0 }, { "lat": 56.0, "lon": 38.0 }] }),
+            GeoShape::Box(GeoPoint::new(55.0, 37.0), GeoPoint::new(56.0, 38.0))
+        );
+        assert!(from_str::<GeoPoint>(r#"{ "lat": 91.0, "lon": 0.0 }"#).is_err());
+    }
+
+    #[test]
+    fn build_geo_shape() {
+        test_build!(
+            GeoShape::Polygon(vec![
+                GeoPoint::new(0.0, 0.0),
+                GeoPoint::new(1.0, 0.5),
+                GeoPoint::new(0.0, 1.0)
+            ]),
+            json!({ "$polygon": [
+                { "lat": 0.0, "lon": 0.0 },
+                { "lat": 1.0, "lon": 0.5 },
+                { "lat": 0.0, "lon": 1.0 }
+            ] })
+        );
+    }
+
+    #[test]
+    fn point_code() {
+        let point = GeoPoint::new(55.75, 37.62);
+        let restored = GeoPoint::from_raw(point.as_raw()).unwrap();
+        assert_eq!(restored, point);
+        assert!((restored.lat() - 55.75).abs() < 1e-7);
+        assert!((restored.lon() - 37.62).abs() < 1e-7);
+        assert!(GeoPoint::new(-90.0, -180.0) < GeoPoint::new(90.0, 180.0));
+    }
+
+    #[test]
+    fn point_distance() {
+        let moscow = GeoPoint::new(55.7558, 37.6173);
+        let london = GeoPoint::new(51.5074, -0.1278);
+        assert!((moscow.distance(&london) / 1000.0 - 2500.0).abs() < 5.0);
+        assert_eq!(moscow.distance(&moscow), 0.0);
+    }
+
+    #[test]
+    fn shape_contains() {
+        let rect = GeoShape::Box(GeoPoint::new(10.0, 170.0), GeoPoint::new(20.0, -170.0));
+        assert!(rect.contains(&GeoPoint::new(15.0, 175.0)));
+        assert!(rect.contains(&GeoPoint::new(15.0, -175.0)));
+        assert!(!rect.contains(&GeoPoint::new(15.0, 0.0)));
+
+        let poly = GeoShape::Polygon(vec![
+            GeoPoint::new(0.0, 0.0),
+            GeoPoint::new(10.0, 5.0),
+            GeoPoint::new(0.0, 10.0),
+        ]);
+        assert!(poly.contains(&GeoPoint::new(2.0, 5.0)));
+        assert!(!poly.contains(&GeoPoint::new(8.0, 1.0)));
+    }
+
+    #[test]
+    fn bounds_ranges() {
+        let near = GeoNear::new(GeoPoint::new(55.75, 37.62), 1000.0);
+        let points = [
+            GeoPoint::new(55.75, 37.62),
+            GeoPoint::new(55.755, 37.625),
+            GeoPoint::new(55.7455, 37.6155),
+        ];
+        let ranges = near
+            .bounds()
+            .iter()
+            .flat_map(GeoBounds::ranges)
+            .collect::<Vec<_>>();
+        assert!(!ranges.is_empty());
+        for point in &points {
+            let code = u64::from_be_bytes(point.as_raw().try_into().unwrap());
+            assert!(ranges.iter().any(|(beg, end)| *beg <= code && code <= *end));
+        }
+
+        // the circle which crosses antimeridian
+        let near = GeoNear::new(GeoPoint::new(0.0, 179.999), 1000.0);
+        assert_eq!(near.bounds().len(), 2);
+    }
+}
@@ -1,27 +1,29 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    iter::once,
     ops::Deref,
+    str::from_utf8,
     sync::{
         atomic::{AtomicBool, Ordering as AtomicOrdering},
         Arc,
     },
 };
 
-use crate::float::F64;
+use crate::{float::F64, geo::GeoBounds, text::text_terms, timestamp};
 
 use lmdb::{
     put::{NODUPDATA, NOOVERWRITE},
-    traits::CreateCursor,
-    ConstAccessor, Cursor, CursorIter, Database, DatabaseOptions, LmdbResultExt, MaybeOwned,
-    ReadTransaction, Unaligned, WriteAccessor,
+    ConstAccessor, ConstTransaction, Cursor, CursorIter, Database, DatabaseOptions, LmdbResultExt,
+    MaybeOwned, Unaligned, WriteAccessor,
 };
 use ron::ser::to_string as to_db_name;
 use serde::{Deserialize, Serialize};
 use supercow::{ext::ConstDeref, Supercow};
 
 use super::{
-    DatabaseDef, Enumerable, IndexKind, KeyData, KeyField, KeyType, OrderKind, Primary,
-    RawDocument, Result, ResultWrap, Serial, Storage, Value,
+    seek_after, DatabaseDef, Enumerable, GeoPoint, IndexKind, KeyData, KeyField, KeyType,
+    OrderKind, Primary, PrimaryKey, PrimaryType, RawDocument, Result, ResultWrap, Selection,
+    Serial, Storage, TextQuery, Value,
 };
 
 /// The definition of index
@@ -31,10 +33,13 @@ pub(crate) struct IndexDef(
     pub Serial,
     /// Collection name
     pub String,
-    /// Field path
+    /// Field path (or paths separated by comma for compound index)
     pub String,
     pub IndexKind,
     pub KeyType,
+    /// The key types of the rest fields of compound index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub Vec<KeyType>,
 );
 
 impl IndexDef {
@@ -43,8 +48,9 @@ impl IndexDef {
         path: P,
         kind: IndexKind,
         key: KeyType,
+        rest: Vec<KeyType>,
     ) -> Self {
-        IndexDef(0, coll.into(), path.into(), kind, key)
+        IndexDef(0, coll.into(), path.into(), kind, key, rest)
     }
 }
 
@@ -57,7 +63,11 @@ impl Enumerable for IndexDef {
 struct IndexData {
     path: String,
     kind: IndexKind,
+    /// The type of stored keys
     key: KeyType,
+    /// The key types of fields
+    keys: Vec<KeyType>,
+    primary: PrimaryType,
     db: Database<'static>,
     // Remove marker
     delete: AtomicBool,
@@ -68,40 +78,81 @@ struct IndexData {
 pub(crate) struct Index(Option<Arc<IndexData>>);
 
 impl Index {
-    pub(crate) fn new(storage: Storage, def: IndexDef) -> Result<Self> {
+    pub(crate) fn new(storage: Storage, def: IndexDef, primary: PrimaryType) -> Result<Self> {
         let db_name = to_db_name(&DatabaseDef::Index(def.clone())).wrap_err()?;
 
-        let IndexDef(_serial, _coll, path, kind, key) = def;
+        let IndexDef(_serial, _coll, path, kind, key, rest) = def;
 
-        let db_opts = match (kind, key) {
-            (IndexKind::Unique, KeyType::Int) => DatabaseOptions::create_map::<Unaligned<i64>>(),
-            (IndexKind::Unique, KeyType::Float) => DatabaseOptions::create_map::<Unaligned<F64>>(),
-            (IndexKind::Unique, KeyType::String) => DatabaseOptions::create_map::<str>(),
-            (IndexKind::Unique, KeyType::Binary) => DatabaseOptions::create_map::<[u8]>(),
-            (IndexKind::Unique, KeyType::Bool) => DatabaseOptions::create_map::<u8>(),
-            (IndexKind::Index, KeyType::Int) => {
-                DatabaseOptions::create_multimap::<Unaligned<i64>, Unaligned<Primary>>()
-            }
-            (IndexKind::Index, KeyType::Float) => {
-                DatabaseOptions::create_multimap::<Unaligned<F64>, Unaligned<Primary>>()
-            }
-            (IndexKind::Index, KeyType::String) => {
-                DatabaseOptions::create_multimap::<str, Unaligned<Primary>>()
-            }
-            (IndexKind::Index, KeyType::Binary) => {
-                DatabaseOptions::create_multimap::<[u8], Unaligned<Primary>>()
-            }
-            (IndexKind::Index, KeyType::Bool) => {
-                DatabaseOptions::create_multimap::<u8, Unaligned<Primary>>()
-            }
+        let mut keys = vec![key];
+        keys.extend(rest);
+
+        // the keys of compound index is encoded into binary
+        let key = if keys.len() > 1 { KeyType::Binary } else { key };
+
+        let db_opts = match kind {
+            IndexKind::Unique => match key {
+                KeyType::Int | KeyType::Timestamp => {
+                    DatabaseOptions::create_map::<Unaligned<i64>>()
+                }
+                KeyType::Float => DatabaseOptions::create_map::<Unaligned<F64>>(),
+                KeyType::String => DatabaseOptions::create_map::<str>(),
+                // the geo keys are the codes of points
+                KeyType::Binary | KeyType::Geo => DatabaseOptions::create_map::<[u8]>(),
+                KeyType::Bool => DatabaseOptions::create_map::<u8>(),
+            },
+            IndexKind::Index | IndexKind::FullText => match (key, primary) {
+                (KeyType::Int, PrimaryType::U32) | (KeyType::Timestamp, PrimaryType::U32) => {
+                    DatabaseOptions::create_multimap::<Unaligned<i64>, Unaligned<u32>>()
+                }
+                (KeyType::Int, PrimaryType::U64) | (KeyType::Timestamp, PrimaryType::U64) => {
+                    DatabaseOptions::create_multimap::<Unaligned<i64>, Unaligned<u64>>()
+                }
+                (KeyType::Int, _) | (KeyType::Timestamp, _) => {
+                    DatabaseOptions::create_multimap_unsized::<Unaligned<i64>, [u8]>()
+                }
+                (KeyType::Float, PrimaryType::U32) => {
+                    DatabaseOptions::create_multimap::<Unaligned<F64>, Unaligned<u32>>()
+                }
+                (KeyType::Float, PrimaryType::U64) => {
+                    DatabaseOptions::create_multimap::<Unaligned<F64>, Unaligned<u64>>()
+                }
+                (KeyType::Float, _) => {
+                    DatabaseOptions::create_multimap_unsized::<Unaligned<F64>, [u8]>()
+                }
+                (KeyType::String, PrimaryType::U32) => {
+                    DatabaseOptions::create_multimap::<str, Unaligned<u32>>()
+                }
+                (KeyType::String, PrimaryType::U64) => {
+                    DatabaseOptions::create_multimap::<str, Unaligned<u64>>()
+                }
+                (KeyType::String, _) => DatabaseOptions::create_multimap_unsized::<str, [u8]>(),
+                (KeyType::Binary, PrimaryType::U32) | (KeyType::Geo, PrimaryType::U32) => {
+                    DatabaseOptions::create_multimap::<[u8], Unaligned<u32>>()
+                }
+                (KeyType::Binary, PrimaryType::U64) | (KeyType::Geo, PrimaryType::U64) => {
+                    DatabaseOptions::create_multimap::<[u8], Unaligned<u64>>()
+                }
+                (KeyType::Binary, _) | (KeyType::Geo, _) => {
+                    DatabaseOptions::create_multimap_unsized::<[u8], [u8]>()
+                }
+                (KeyType::Bool, PrimaryType::U32) => {
+                    DatabaseOptions::create_multimap::<u8, Unaligned<u32>>()
+                }
+                (KeyType::Bool, PrimaryType::U64) => {
+                    DatabaseOptions::create_multimap::<u8, Unaligned<u64>>()
+                }
+                (KeyType::Bool, _) => DatabaseOptions::create_multimap_unsized::<u8, [u8]>(),
+            },
         };
 
-        let db = Database::open(storage, Some(&db_name), &db_opts).wrap_err()?;
+        let db = storage.open_db(&db_name, &db_opts)?;
 
         Ok(Index(Some(Arc::new(IndexData {
             path,
             kind,
             key,
+            keys,
+            primary,
             db,
             delete: AtomicBool::new(false),
         }))))
@@ -123,16 +174,33 @@ impl Index {
         self.handle().kind
     }
 
+    /// The type of keys which stored in index
     pub fn key(&self) -> KeyType {
         self.handle().key
     }
 
+    /// The key types of indexed fields
+    pub fn keys(&self) -> &[KeyType] {
+        &self.handle().keys
+    }
+
+    pub fn is_compound(&self) -> bool {
+        self.handle().keys.len() > 1
+    }
+
+    /// Check that the index contains the words of text
+    pub fn is_text(&self) -> bool {
+        self.handle().kind == IndexKind::FullText
+    }
+
     pub fn field(&self) -> KeyField {
         let handle = self.handle();
 
-        KeyField::new(handle.path.clone())
-            .with_type(handle.key)
-            .with_kind(handle.kind)
+        let mut field = KeyField::new(handle.path.clone())
+            .with_type(handle.keys[0])
+            .with_kind(handle.kind);
+        field.rest = handle.keys[1..].to_vec();
+        field
     }
 
     pub(crate) fn update_index(
@@ -145,7 +213,8 @@ impl Index {
             .or_else(|| new_doc)
             .ok_or_else(|| "Either old_doc or new_doc or both must present")
             .wrap_err()?;
-        let id = doc.req_id()?;
+        let handle = self.handle();
+        let id = handle.primary.encode(&doc.req_id()?)?;
 
         let old_keys = old_doc.map(|doc| self.extract(doc)).unwrap_or_default();
         let new_keys = new_doc.map(|doc| self.extract(doc)).unwrap_or_default();
@@ -155,41 +224,42 @@ impl Index {
             new_keys.difference(&old_keys),
         );
 
-        let handle = self.handle();
-
         //println!("Update index {} --{:?} ++{:?}", &handle.path, &old_keys, &new_keys);
 
         for key in old_keys {
             access
-                .del_item(&handle.db, key.as_raw(), &Unaligned::new(id))
+                .del_item(&handle.db, key.as_raw(), id.as_slice())
                 .wrap_err()?;
         }
 
         let f = match handle.kind {
             IndexKind::Unique => NOOVERWRITE,
-            IndexKind::Index => NODUPDATA,
+            IndexKind::Index | IndexKind::FullText => NODUPDATA,
         };
 
         for key in new_keys {
             access
-                .put(&handle.db, key.as_raw(), &Unaligned::new(id), f)
+                .put(&handle.db, key.as_raw(), id.as_slice(), f)
                 .wrap_err()?;
         }
 
         Ok(())
     }
 
-    fn extract(&self, doc: &RawDocument) -> HashSet<KeyData> {
-        let mut keys = HashSet::new();
+    pub(crate) fn extract(&self, doc: &RawDocument) -> HashSet<KeyData> {
         let handle = self.handle();
-        let path = handle.path.split('.');
-        extract_field_values(&*doc, handle.key, &path, &mut keys);
-        keys
+        if self.is_compound() {
+            extract_compound_keys(doc, &handle.path, &handle.keys)
+        } else if self.is_text() {
+            extract_text_terms(doc, &handle.path)
+        } else {
+            extract_field_keys(doc, &handle.path, Some(handle.key))
+        }
     }
 
     pub(crate) fn query_set<'a, I: Iterator<Item = &'a KeyData>>(
         &self,
-        txn: &ReadTransaction,
+        txn: &ConstTransaction,
         access: &ConstAccessor,
         keys: I,
     ) -> Result<HashSet<Primary>> {
@@ -200,41 +270,19 @@ impl Index {
             if let Some(key) = key.to_type(handle.key) {
                 let mut cursor = txn.cursor(self.clone()).wrap_err()?;
 
-                match handle.kind {
-                    IndexKind::Unique => match cursor
-                        .seek_k_both::<[u8], Unaligned<Primary>>(&access, key.as_raw())
-                        .to_opt()
+                match cursor.seek_k::<[u8], [u8]>(&access, key.as_raw()).to_opt() {
+                    Ok(Some(id)) => {
+                        out.insert(handle.primary.decode(id)?);
+                    }
+                    Ok(None) => continue,
+                    Err(e) => return Err(e).wrap_err(),
+                }
+
+                if handle.kind != IndexKind::Unique {
+                    while let Some((_key, id)) =
+                        cursor.next_dup::<[u8], [u8]>(&access).to_opt().wrap_err()?
                     {
-                        Ok(Some((_key, id))) => {
-                            out.insert(id.get());
-                        }
-                        Err(e) => return Err(e).wrap_err(),
-                        _ => (),
-                    },
-                    IndexKind::Index => {
-                        match cursor
-                            .seek_k::<[u8], Unaligned<Primary>>(&access, key.as_raw())
-                            .to_opt()
-                        {
-                            Ok(Some(..)) => (),
-                            Ok(None) => continue,
-                            Err(e) => return Err(e).wrap_err(),
-                        }
-
-                        for res in CursorIter::new(
-                            MaybeOwned::Owned(cursor),
-                            &access,
-                            |c, a| c.get_multiple::<[Unaligned<Primary>]>(&a),
-                            Cursor::next_multiple::<[Unaligned<Primary>]>,
-                        )
-                        .wrap_err()?
-                        {
-                            if let Some(ids) = res.to_opt().wrap_err()? {
-                                for id in ids {
-                                    out.insert(id.get());
-                                }
-                            }
-                        }
+                        out.insert(handle.primary.decode(id)?);
                     }
                 }
             }
@@ -243,9 +291,40 @@ impl Index {
         Ok(out)
     }
 
+    /// Count the entries of index which has specified keys
+    pub(crate) fn count_keys<'a, I: Iterator<Item = &'a KeyData>>(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        keys: I,
+    ) -> Result<usize> {
+        let mut count = 0;
+        let handle = self.handle();
+
+        for key in keys {
+            if let Some(key) = key.to_type(handle.key) {
+                let mut cursor = txn.cursor(self.clone()).wrap_err()?;
+
+                match cursor.seek_k::<[u8], [u8]>(&access, key.as_raw()).to_opt() {
+                    Ok(Some(_id)) => {
+                        count += if handle.kind != IndexKind::Unique {
+                            cursor.count().wrap_err()?
+                        } else {
+                            1
+                        };
+                    }
+                    Ok(None) => continue,
+                    Err(e) => return Err(e).wrap_err(),
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
     pub(crate) fn query_range(
         &self,
-        txn: &ReadTransaction,
+        txn: &ConstTransaction,
         access: &ConstAccessor,
         beg: Option<(&KeyData, bool)>,
         end: Option<(&KeyData, bool)>,
@@ -257,109 +336,384 @@ impl Index {
         let end = end.and_then(|(key, inc)| key.to_type(handle.key).map(|key| (key, inc)));
         let cursor = txn.cursor(self.clone()).wrap_err()?;
 
-        match handle.kind {
-            IndexKind::Unique => {
-                for item in CursorIter::new(
-                    MaybeOwned::Owned(cursor),
-                    access,
-                    |c, a| match beg {
-                        Some((beg_key, beg_inc)) => {
-                            let p = c.seek_range_k(a, beg_key.as_raw())?;
-                            if beg_inc {
-                                Ok(p)
-                            } else {
-                                c.next(a)
-                            }
-                        }
-                        _ => c.first(a),
-                    },
-                    Cursor::next::<[u8], Unaligned<Primary>>,
-                )
-                .wrap_err()?
-                {
-                    match (item, &end) {
-                        (Ok((key, id)), Some((end_key, end_inc))) => {
-                            let key = KeyData::from_raw(end_key.get_type(), key)?;
-                            #[allow(clippy::op_ref)]
-                            {
-                                if &key < end_key || *end_inc && &key <= end_key {
-                                    out.insert(id.get());
-                                } else {
-                                    break;
-                                }
-                            }
-                        }
-                        (Ok((_, id)), _) => {
-                            out.insert(id.get());
-                        }
-                        (Err(e), _) => return Err(e).wrap_err(),
-                    }
+        for item in CursorIter::new(
+            MaybeOwned::Owned(cursor),
+            access,
+            |c, a| match &beg {
+                Some((beg_key, _)) => c.seek_range_k(a, beg_key.as_raw()),
+                _ => c.first(a),
+            },
+            Cursor::next::<[u8], [u8]>,
+        )
+        .wrap_err()?
+        {
+            let (key, id) = item.wrap_err()?;
+            let key = KeyData::from_raw(handle.key, key)?;
+
+            if let Some((beg_key, false)) = &beg {
+                if key == **beg_key {
+                    // skip the excluded start key
+                    continue;
                 }
             }
-            IndexKind::Index => {
-                for item in CursorIter::new(
-                    MaybeOwned::Owned(cursor),
-                    access,
-                    |c, a| {
-                        let key = match beg {
-                            Some((beg_key, beg_inc)) => {
-                                let p = c.seek_range_k::<[u8], [u8]>(a, beg_key.as_raw())?.0;
-                                if beg_inc {
-                                    p
-                                } else {
-                                    c.next::<[u8], [u8]>(a)?.0
-                                }
-                            }
-                            _ => c.first::<[u8], [u8]>(a)?.0,
-                        };
-                        c.get_multiple::<[Unaligned<Primary>]>(a)
-                            .map(|val| (key, val))
-                    },
-                    |c, a| {
-                        if let Some(ids) = c.next_multiple(a).to_opt()? {
-                            c.get_current::<[u8], [u8]>(a).map(|(key, _val)| (key, ids))
-                        } else {
-                            let key = c.next::<[u8], Unaligned<Primary>>(a)?.0;
-                            c.get_multiple(a).map(|ids| (key, ids))
-                        }
-                    },
-                )
-                .wrap_err()?
-                {
-                    match (item, &end) {
-                        (Ok((key, ids)), Some((end_key, end_inc))) => {
-                            let key = KeyData::from_raw(end_key.get_type(), key)?;
-                            #[allow(clippy::op_ref)]
-                            {
-                                if &key < end_key || *end_inc && &key <= end_key {
-                                    for id in ids {
-                                        out.insert(id.get());
-                                    }
-                                } else {
-                                    break;
-                                }
-                            }
-                        }
-                        (Ok((_, ids)), _) => {
-                            for id in ids {
-                                out.insert(id.get());
-                            }
-                        }
-                        (Err(e), _) => return Err(e).wrap_err(),
+
+            if let Some((end_key, end_inc)) = &end {
+                if !(key < **end_key || *end_inc && key <= **end_key) {
+                    break;
+                }
+            }
+
+            out.insert(handle.primary.decode(id)?);
+        }
+
+        Ok(out)
+    }
+
+    /// Query full-text index
+    ///
+    /// The documents which contains all terms or any of terms will be selected.
+    ///
+    pub(crate) fn query_text(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        query: &TextQuery,
+    ) -> Result<HashSet<Primary>> {
+        let mut out: Option<HashSet<Primary>> = None;
+
+        for term in query.terms() {
+            let ids = self.query_set(txn, access, once(&KeyData::String(term)))?;
+            out = Some(match out {
+                Some(out) if query.any => out.union(&ids).cloned().collect(),
+                Some(out) => out.intersection(&ids).cloned().collect(),
+                None => ids,
+            });
+        }
+
+        Ok(out.unwrap_or_default())
+    }
+
+    /// Score the documents which contains the terms of full-text index
+    ///
+    /// The score of document is the sum of inverse document frequencies of the terms which it contains, so the rare terms weights more.
+    ///
+    pub(crate) fn score_text(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        terms: &[String],
+        documents: usize,
+    ) -> Result<HashMap<Primary, f64>> {
+        let mut scores = HashMap::new();
+
+        for term in terms {
+            let ids = self.query_set(txn, access, once(&KeyData::String(term.clone())))?;
+            let idf = (1.0 + documents as f64 / usize::max(ids.len(), 1) as f64).ln();
+            for id in ids {
+                *scores.entry(id).or_insert(0.0) += idf;
+            }
+        }
+
+        Ok(scores)
+    }
+
+    /// Query index keys which starts with prefix
+    ///
+    /// Only string and binary keys can be queried by prefix.
+    ///
+    pub(crate) fn query_prefix(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        prefix: &KeyData,
+    ) -> Result<HashSet<Primary>> {
+        let handle = self.handle();
+
+        let prefix = match prefix.to_type(handle.key).as_deref() {
+            Some(KeyData::String(val)) => val.as_bytes().to_vec(),
+            Some(KeyData::Binary(val)) => val.clone(),
+            Some(_) => {
+                return Err("Prefix comparison is supported for string and binary keys only".into())
+            }
+            None => return Ok(HashSet::new()),
+        };
+
+        self.query_prefix_keys(txn, access, &prefix, |_| Ok(true))
+    }
+
+    /// Query index keys which starts with literal prefix and matches pattern
+    ///
+    /// Only string keys can be matched.
+    ///
+    pub(crate) fn query_pattern<F: Fn(&str) -> bool>(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        prefix: &str,
+        matches: F,
+    ) -> Result<HashSet<Primary>> {
+        if self.handle().key != KeyType::String {
+            return Err("Pattern matching is supported for string keys only".into());
+        }
+
+        self.query_prefix_keys(txn, access, prefix.as_bytes(), |key| {
+            // the empty strings stored as zero byte
+            let key = if key == b"\0" {
+                ""
+            } else {
+                from_utf8(key).wrap_err()?
+            };
+            Ok(matches(key))
+        })
+    }
+
+    fn query_prefix_keys<F: FnMut(&[u8]) -> Result<bool>>(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        prefix: &[u8],
+        mut select: F,
+    ) -> Result<HashSet<Primary>> {
+        let mut out = HashSet::new();
+        let handle = self.handle();
+        let cursor = txn.cursor(self.clone()).wrap_err()?;
+
+        for item in CursorIter::new(
+            MaybeOwned::Owned(cursor),
+            access,
+            |c, a| {
+                if prefix.is_empty() {
+                    c.first(a)
+                } else {
+                    c.seek_range_k(a, prefix)
+                }
+            },
+            Cursor::next::<[u8], [u8]>,
+        )
+        .wrap_err()?
+        {
+            let (key, id): (&[u8], &[u8]) = item.wrap_err()?;
+
+            if !key.starts_with(prefix) {
+                // the keys behind prefix range
+                break;
+            }
+
+            if select(key)? {
+                out.insert(handle.primary.decode(id)?);
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Query geo index keys within bounds which satisfies the check
+    ///
+    /// The index is scanned by the ranges of keys which covers the bounds, so only the points near the bounds will be checked.
+    ///
+    pub(crate) fn query_geo<F: Fn(&GeoPoint) -> bool>(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        bounds: &[GeoBounds],
+        matches: F,
+    ) -> Result<HashSet<Primary>> {
+        let mut out = HashSet::new();
+        let handle = self.handle();
+
+        if handle.key != KeyType::Geo {
+            return Err("Geo comparison is supported for geo keys only".into());
+        }
+
+        for (beg, end) in bounds.iter().flat_map(GeoBounds::ranges) {
+            let beg = beg.to_be_bytes();
+            let end = end.to_be_bytes();
+            let cursor = txn.cursor(self.clone()).wrap_err()?;
+
+            for item in CursorIter::new(
+                MaybeOwned::Owned(cursor),
+                access,
+                |c, a| c.seek_range_k(a, &beg[..]),
+                Cursor::next::<[u8], [u8]>,
+            )
+            .wrap_err()?
+            {
+                let (key, id): (&[u8], &[u8]) = item.wrap_err()?;
+
+                if key > &end[..] {
+                    // the keys behind range
+                    break;
+                }
+
+                if matches(&GeoPoint::from_raw(key)?) {
+                    out.insert(handle.primary.decode(id)?);
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Query compound index
+    ///
+    /// The keys of first fields must be equal to prefix keys, and optionally the key of next field must be in range.
+    ///
+    pub(crate) fn query_compound(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        prefix: &[KeyData],
+        beg: Option<(&KeyData, bool)>,
+        end: Option<(&KeyData, bool)>,
+    ) -> Result<HashSet<Primary>> {
+        let mut out = HashSet::new();
+        let handle = self.handle();
+
+        let mut start = Vec::new();
+        for (key, typ) in prefix.iter().zip(&handle.keys) {
+            if let Some(key) = key.to_type(*typ) {
+                encode_compound_key(&key, &mut start);
+            } else {
+                // nothing can be equal
+                return Ok(out);
+            }
+        }
+
+        let bound = |bound: Option<(&KeyData, bool)>| {
+            bound.and_then(|(key, inc)| {
+                let key = key.to_type(*handle.keys.get(prefix.len())?)?;
+                let mut raw = start.clone();
+                encode_compound_key(&key, &mut raw);
+                Some((raw, inc))
+            })
+        };
+        let beg = bound(beg);
+        let end = bound(end);
+        let cursor = txn.cursor(self.clone()).wrap_err()?;
+
+        for item in CursorIter::new(
+            MaybeOwned::Owned(cursor),
+            access,
+            |c, a| match &beg {
+                Some((beg_key, _)) => c.seek_range_k(a, beg_key.as_slice()),
+                _ if start.is_empty() => c.first(a),
+                _ => c.seek_range_k(a, start.as_slice()),
+            },
+            Cursor::next::<[u8], [u8]>,
+        )
+        .wrap_err()?
+        {
+            let (key, id) = item.wrap_err()?;
+
+            if !key.starts_with(&start) {
+                break;
+            }
+
+            if let Some((beg_key, false)) = &beg {
+                if key.starts_with(beg_key) {
+                    // skip the excluded start keys
+                    continue;
+                }
+            }
+
+            if let Some((end_key, end_inc)) = &end {
+                if if *end_inc {
+                    key > end_key.as_slice() && !key.starts_with(end_key)
+                } else {
+                    key >= end_key.as_slice()
+                } {
+                    break;
+                }
+            }
+
+            out.insert(handle.primary.decode(id)?);
+        }
+
+        Ok(out)
+    }
+
+    /// Query all entries of index in the order of keys
+    pub(crate) fn query_entries(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+    ) -> Result<Vec<(KeyData, Primary)>> {
+        let mut out = Vec::new();
+        let handle = self.handle();
+        let cursor = txn.cursor(self.clone()).wrap_err()?;
+
+        for item in CursorIter::new(
+            MaybeOwned::Owned(cursor),
+            access,
+            |c, a| c.first(a),
+            Cursor::next::<[u8], [u8]>,
+        )
+        .wrap_err()?
+        {
+            let (key, id) = item.wrap_err()?;
+            out.push((
+                KeyData::from_raw(handle.key, key)?,
+                handle.primary.decode(id)?,
+            ));
+        }
+
+        Ok(out)
+    }
+
+    /// Count the entries of index by keys
+    ///
+    /// The numbers of duplicates of keys are used when no selection specified, otherwise only the entries of selected documents will be counted. The keys are returned in index order.
+    ///
+    pub(crate) fn count_entries(
+        &self,
+        txn: &ConstTransaction,
+        access: &ConstAccessor,
+        sel: Option<&Selection>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        let mut out: Vec<(KeyData, usize)> = Vec::new();
+        let handle = self.handle();
+        let mut cursor = txn.cursor(self.clone()).wrap_err()?;
+
+        if let Some(sel) = sel {
+            let mut item = cursor.first::<[u8], [u8]>(access).to_opt().wrap_err()?;
+
+            while let Some((key, id)) = item {
+                if sel.has(&handle.primary.decode(id)?) {
+                    let key = KeyData::from_raw(handle.key, key)?;
+                    match out.last_mut() {
+                        Some((last, count)) if *last == key => *count += 1,
+                        _ => out.push((key, 1)),
                     }
                 }
+                item = cursor.next::<[u8], [u8]>(access).to_opt().wrap_err()?;
+            }
+        } else {
+            let mut item = cursor.first::<[u8], [u8]>(access).to_opt().wrap_err()?;
+
+            while let Some((key, _id)) = item {
+                let count = if handle.kind != IndexKind::Unique {
+                    cursor.count().wrap_err()?
+                } else {
+                    1
+                };
+                out.push((KeyData::from_raw(handle.key, key)?, count));
+                item = cursor
+                    .next_nodup::<[u8], [u8]>(access)
+                    .to_opt()
+                    .wrap_err()?;
             }
         }
 
         Ok(out)
     }
 
-    pub(crate) fn query_iter(
+    pub(crate) fn query_iter<'txn>(
         &self,
-        txn: Arc<ReadTransaction<'static>>,
+        txn: &'txn ConstTransaction<'static>,
         order: OrderKind,
-    ) -> Result<IndexIterator> {
-        IndexIterator::new(txn, self.clone(), order)
+        after: Option<&(KeyData, Primary)>,
+    ) -> Result<IndexIterator<'txn>> {
+        IndexIterator::new(txn, self.clone(), order, after)
     }
 
     pub(crate) fn purge(&self, access: &mut WriteAccessor) -> Result<()> {
@@ -369,9 +723,14 @@ impl Index {
 
     pub(crate) fn to_delete(&self, access: &mut WriteAccessor) -> Result<()> {
         self.purge(access)?;
+        self.set_deleted();
+        Ok(())
+    }
+
+    /// Delete the database of index when the last handle is dropped
+    pub(crate) fn set_deleted(&self) {
         let handle = self.handle();
         handle.delete.store(true, AtomicOrdering::SeqCst);
-        Ok(())
     }
 }
 
@@ -421,11 +780,106 @@ impl<'a> Into<Supercow<'a, Database<'a>>> for Index {
     }
 }
 
-fn extract_field_values<'a, 'i: 'a, I: Iterator<Item = &'i str> + Clone>(
+/// Extract the keys of document field
+///
+/// When the type is specified the keys will be converted into it, otherwise the keys of any types will be extracted as is.
+///
+pub(crate) fn extract_field_keys(
+    doc: &Value,
+    path: &str,
+    typ: Option<KeyType>,
+) -> HashSet<KeyData> {
+    let mut keys = HashSet::new();
+    let path = path.split('.');
+    extract_field_values(doc, typ, &path, &mut keys);
+    keys
+}
+
+/// Extract the values of document field
+///
+/// Unlike the keys the duplicated values are kept in the order of its appearance in document.
+///
+pub(crate) fn extract_field_list(doc: &Value, path: &str) -> Vec<KeyData> {
+    let mut vals = Vec::new();
+    let path = path.split('.');
+    extract_field_values(doc, None, &path, &mut vals);
+    vals
+}
+
+/// Extract the keys of compound index
+///
+/// The keys of compound index are the all combinations of the keys of its fields.
+///
+fn extract_compound_keys(doc: &Value, path: &str, typs: &[KeyType]) -> HashSet<KeyData> {
+    let mut raws = vec![Vec::new()];
+    for (path, typ) in path.split(',').zip(typs) {
+        let keys = extract_field_keys(doc, path, Some(*typ));
+        raws = raws
+            .iter()
+            .flat_map(|raw| {
+                keys.iter().map(move |key| {
+                    let mut raw = raw.clone();
+                    encode_compound_key(key, &mut raw);
+                    raw
+                })
+            })
+            .collect();
+    }
+    raws.into_iter().map(KeyData::Binary).collect()
+}
+
+/// Extract the terms of text field
+fn extract_text_terms(doc: &Value, path: &str) -> HashSet<KeyData> {
+    extract_field_keys(doc, path, Some(KeyType::String))
+        .iter()
+        .flat_map(|key| match key {
+            KeyData::String(text) => text_terms(text).map(KeyData::String).collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Encode the key of field of compound index
+///
+/// The encoded keys are compared bytewise in the same order as the keys itself. The strings and binaries are terminated, so the keys of next fields never mix with it.
+///
+fn encode_compound_key(key: &KeyData, raw: &mut Vec<u8>) {
+    use self::KeyData::*;
+    match key {
+        Int(val) | Timestamp(val) => raw.extend_from_slice(&(*val as u64 ^ 1 << 63).to_be_bytes()),
+        Float(val) => {
+            let bits = val.0.to_bits();
+            let bits = if bits >> 63 == 0 {
+                bits ^ 1 << 63
+            } else {
+                !bits
+            };
+            raw.extend_from_slice(&bits.to_be_bytes());
+        }
+        String(val) => encode_compound_bytes(val.as_bytes(), raw),
+        Binary(val) => encode_compound_bytes(val, raw),
+        Bool(val) => raw.push(*val as u8),
+        Geo(val) => raw.extend_from_slice(val.as_raw()),
+    }
+}
+
+fn encode_compound_bytes(val: &[u8], raw: &mut Vec<u8>) {
+    for byte in val {
+        raw.push(*byte);
+        if *byte == 0 {
+            // escape zero byte
+            raw.push(0xff);
+        }
+    }
+    // terminator
+    raw.extend_from_slice(&[0, 0]);
+}
+
+fn extract_field_values<'a, 'i: 'a, I: Iterator<Item = &'i str> + Clone, K: Extend<KeyData>>(
     doc: &'a Value,
-    typ: KeyType,
+    typ: Option<KeyType>,
     path: &'a I,
-    keys: &mut HashSet<KeyData>,
+    keys: &mut K,
 ) {
     let mut sub_path = path.clone();
     if let Some(name) = sub_path.next() {
@@ -444,12 +898,16 @@ fn extract_field_values<'a, 'i: 'a, I: Iterator<Item = &'i str> + Clone>(
             }
             _ => (),
         }
+    } else if typ == Some(KeyType::Geo) {
+        extract_field_points(doc, keys);
+    } else if typ == Some(KeyType::Timestamp) {
+        extract_field_timestamps(doc, keys);
     } else {
         extract_field_primitives(doc, typ, keys);
     }
 }
 
-fn extract_field_primitives(doc: &Value, typ: KeyType, keys: &mut HashSet<KeyData>) {
+fn extract_field_primitives<K: Extend<KeyData>>(doc: &Value, typ: Option<KeyType>, keys: &mut K) {
     use serde_cbor::Value::*;
     match (typ, doc) {
         (_, Array(val)) => val
@@ -458,58 +916,160 @@ fn extract_field_primitives(doc: &Value, typ: KeyType, keys: &mut HashSet<KeyDat
         (_, Map(val)) => val
             .iter()
             .for_each(|(key, _doc)| extract_field_primitives(key, typ, keys)),
-        (typ, val) => {
+        (Some(typ), val) => {
             if let Some(val) = KeyData::from_val(&val) {
                 if let Some(val) = val.to_type(typ) {
-                    keys.insert(val.into_owned());
+                    keys.extend(once(val.into_owned()));
                 }
             }
         }
+        (None, val) => {
+            if let Some(val) = KeyData::from_val(&val) {
+                keys.extend(once(val));
+            }
+        }
+    }
+}
+
+/// Extract the geo points of field
+///
+/// The points are represented as `{"lat": .., "lon": ..}` objects or `[lon, lat]` arrays, also the arrays of points are supported.
+///
+fn extract_field_points<K: Extend<KeyData>>(doc: &Value, keys: &mut K) {
+    if let Some(point) = GeoPoint::from_val(doc) {
+        keys.extend(once(KeyData::Geo(point)));
+    } else if let Value::Array(val) = doc {
+        val.iter().for_each(|doc| extract_field_points(doc, keys));
     }
 }
 
-pub(crate) struct IndexIterator {
-    txn: Arc<ReadTransaction<'static>>,
-    cur: Cursor<'static, 'static>,
+/// Extract the timestamps of field
+///
+/// The timestamps are represented as the numbers of seconds since epoch, RFC 3339 strings, CBOR datetimes or serialized `SystemTime` objects, also the arrays of timestamps are supported.
+///
+fn extract_field_timestamps<K: Extend<KeyData>>(doc: &Value, keys: &mut K) {
+    if let Some(time) = timestamp::from_val(doc) {
+        keys.extend(once(KeyData::Timestamp(time)));
+    } else if let Value::Array(val) = doc {
+        val.iter()
+            .for_each(|doc| extract_field_timestamps(doc, keys));
+    }
+}
+
+pub(crate) struct IndexIterator<'txn> {
+    txn: &'txn ConstTransaction<'static>,
+    cur: Cursor<'txn, 'txn>,
+    primary: PrimaryType,
     order: OrderKind,
     init: bool,
+    after: Option<(Vec<u8>, Option<Vec<u8>>)>,
 }
 
-impl IndexIterator {
-    pub fn new(txn: Arc<ReadTransaction<'static>>, coll: Index, order: OrderKind) -> Result<Self> {
+impl<'txn> IndexIterator<'txn> {
+    /// Create iterator across index
+    ///
+    /// When the key and primary key is specified the iteration starts after the corresponding entry.
+    ///
+    pub fn new(
+        txn: &'txn ConstTransaction<'static>,
+        coll: Index,
+        order: OrderKind,
+        after: Option<&(KeyData, Primary)>,
+    ) -> Result<Self> {
+        let handle = coll.handle();
+        let primary = handle.primary;
+        let after = if let Some((key, id)) = after {
+            let key = key.as_raw().to_vec();
+            Some(match handle.kind {
+                // unique index has single entry with key
+                IndexKind::Unique => (key, None),
+                IndexKind::Index | IndexKind::FullText => (key, Some(primary.encode(id)?)),
+            })
+        } else {
+            None
+        };
         let cur = txn.cursor(coll)?;
 
         Ok(Self {
             txn,
             cur,
+            primary,
             order,
             init: false,
+            after,
         })
     }
 }
 
-impl Iterator for IndexIterator {
+impl<'txn> Iterator for IndexIterator<'txn> {
     type Item = Result<Primary>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let access = self.txn.access();
         match if self.init {
             match self.order {
-                OrderKind::Asc => self.cur.next::<[u8], Unaligned<Primary>>(&access),
-                OrderKind::Desc => self.cur.prev::<[u8], Unaligned<Primary>>(&access),
+                OrderKind::Asc => self.cur.next::<[u8], [u8]>(&access),
+                OrderKind::Desc => self.cur.prev::<[u8], [u8]>(&access),
             }
         } else {
             self.init = true;
-            match self.order {
-                OrderKind::Asc => self.cur.first::<[u8], Unaligned<Primary>>(&access),
-                OrderKind::Desc => self.cur.last::<[u8], Unaligned<Primary>>(&access),
+            if let Some((key, id)) = self.after.take() {
+                seek_after(&mut self.cur, &access, &key, id.as_deref(), self.order)
+            } else {
+                match self.order {
+                    OrderKind::Asc => self.cur.first::<[u8], [u8]>(&access),
+                    OrderKind::Desc => self.cur.last::<[u8], [u8]>(&access),
+                }
             }
         }
         .to_opt()
         {
-            Ok(Some((_key, id))) => Some(Ok(id.get())),
+            Ok(Some((_key, id))) => Some(self.primary.decode(id)),
             Ok(None) => None,
             Err(e) => Some(Err(e).wrap_err()),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{encode_compound_key, KeyData};
+
+    fn encode(keys: &[KeyData]) -> Vec<u8> {
+        let mut raw = Vec::new();
+        for key in keys {
+            encode_compound_key(key, &mut raw);
+        }
+        raw
+    }
+
+    #[test]
+    fn compound_key_order() {
+        let sorted = [
+            vec![KeyData::from(-10), KeyData::from("")],
+            vec![KeyData::from(-10), KeyData::from("a")],
+            vec![KeyData::from(-1), KeyData::from("a")],
+            vec![KeyData::from(0), KeyData::from("a")],
+            vec![KeyData::from(0), KeyData::from("a\u{0}")],
+            vec![KeyData::from(0), KeyData::from("a\u{0}b")],
+            vec![KeyData::from(0), KeyData::from("a\u{1}")],
+            vec![KeyData::from(0), KeyData::from("ab")],
+            vec![KeyData::from(1), KeyData::from("")],
+        ];
+
+        for pair in sorted.windows(2) {
+            assert!(encode(&pair[0]) < encode(&pair[1]), "{:?}", pair);
+        }
+
+        let sorted = [-1e10, -1.5, -0.5, 0.0, 0.5, 1.5, 1e10];
+
+        for pair in sorted.windows(2) {
+            assert!(
+                encode(&[KeyData::from(pair[0]), KeyData::from(true)])
+                    < encode(&[KeyData::from(pair[1]), KeyData::from(false)]),
+                "{:?}",
+                pair
+            );
+        }
+    }
+}
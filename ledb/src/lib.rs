@@ -5,12 +5,28 @@
 ## Features
 
 * Processing documents which implements `Serialize` and `Deserialize` traits from [serde](https://serde.rs/).
-* Identifying documents using auto-incrementing integer primary keys.
-* Indexing any fields of documents using unique or duplicated keys.
-* Searching and ordering documents using indexed fields or primary key.
-* Selecting documents using complex filters with fields comparing and logical operations.
+* Identifying documents using auto-incrementing integer primary keys, or string, UUID and ULID primary keys.
+* Indexing any fields of documents using unique or duplicated keys, also several fields at once using compound indexes.
+* Searching and ordering documents using indexed fields or primary key, also by several fields at once.
+* Planning queries using the estimated selectivity of indexes, which can be explained.
+* Paging found documents using skipping and limiting, or starting after the last document of previous page.
+* Projecting found documents to keep or remove the specified fields before deserializing.
+* Embedding the referenced documents of other collections into found documents using lookups.
+* Counting documents using the numbers of entries of collections and indexes without loading them.
+* Aggregating documents grouped by fields using accumulators like count, sum, average, minimum and maximum.
+* Getting the distinct keys of indexed fields with the numbers of documents, which is useful for faceted search.
+* Selecting documents using complex filters with fields comparing and logical operations, also on the fields without indexes.
+* Searching the words of text fields using full-text indexes with ordering by relevance.
+* Searching geographic points within radius, box or polygon using geo indexes with ordering by distance.
+* Indexing dates and times given as epoch seconds, RFC 3339 strings or CBOR datetimes using timestamp keys.
 * Updating documents using rich set of modifiers.
-* Storing documents into independent storages so called collections.
+* Storing documents into independent storages so called collections, which can be renamed and copied.
+* Grouping multiple operations across collections into atomic transactions.
+* Reading documents from consistent snapshots of storage.
+* Growing the storage automatically when it becomes full.
+* Making consistent backups of live storage with optional compaction.
+* Exporting and importing whole storage using portable archives.
+* Describing collections and indexes using the metadata catalog of storage.
 * Flexible `query!` macro which helps write clear and readable queries.
 * Using [LMDB](https://en.wikipedia.org/wiki/Lightning_Memory-Mapped_Database) as backend for document storage and indexing engine.
 
@@ -148,12 +164,23 @@ query!(
 )
 ```
 
+The compound index over several fields can be used to lookup documents using the equality conditions on the first fields of index and optionally the range condition on the next field, also to order documents by that fields:
+
+```ignore
+query!(
+    index for some_collection
+        (some_field String, other_field Int), // compound index
+        (some_field String, other_field Int, next_field Float) unique,
+)
+```
+
 ### Index kinds
 
 | Internal Type | JSON Type  | Description                  |
 | ------------- | ---------  | -----------                  |
 | Index         | "index"    | The values can be duplicated |
 | Unique        | "unique"   | Each value is unique         |
+| FullText      | "fulltext" | The words of text is indexed |
 
 Unique index guarantee that each value can be stored once, any duplicates disalowed.
 
@@ -164,35 +191,54 @@ The operation will fail in two cases:
 
 Unique fields is pretty fit for sorting.
 
-*TODO: Full-text index kind for searching*
+Full-text index splits the strings of field to the words using Unicode word boundaries and stores the lowercased words, so the documents can be searched by words using text search (`$text`) and ordered by relevance. Full-text index requires string keys and cannot be compound.
+
+```ignore
+query!(
+    index for some_collection
+        content String fulltext,
+)
+```
 
 ### Key types
 
-| Internal Type | JSON Type  | Description                   |
-| ------------- | ---------  | -----------                   |
-| Int           | "int"      | 64-bit signed integers        |
-| Float         | "float"    | 64-bit floating point numbers |
-| Bool          | "bool"     | boolean values                |
-| String        | "string"   | UTF-8 strings                 |
-| Binary        | "binary"   | raw binary data               |
+| Internal Type | JSON Type   | Description                   |
+| ------------- | ---------   | -----------                   |
+| Int           | "int"       | 64-bit signed integers        |
+| Float         | "float"     | 64-bit floating point numbers |
+| Bool          | "bool"      | boolean values                |
+| String        | "string"    | UTF-8 strings                 |
+| Binary        | "binary"    | raw binary data               |
+| Geo           | "geo"       | geographic points             |
+| Timestamp     | "timestamp" | dates and times               |
+
+The geo keys are extracted from the points represented as `[lon, lat]` arrays or `{"lat": .., "lon": ..}` objects, also from the arrays of points. The points are stored as Z-order codes of quantized coordinates, so the points which are near usually have near keys and the areas can be searched by a few ranges of keys.
+
+The timestamp keys are extracted from the numbers of seconds since epoch, the RFC 3339 strings like `"2009-02-13T23:31:30Z"` (the dates without time and the times without offset are treated as UTC), the CBOR datetimes (tags 0 and 1) and the serialized `SystemTime` objects, so the documents with dates in different forms are ordered together. The timestamps are stored as the numbers of microseconds since epoch. The values of comparisons are converted same way when the timestamp index is used, and the `KeyData::Timestamp` values (like `SystemTime` in `query!` filters) compare the fields of any forms as timestamps too.
 
 ## Filters
 
 ### Comparison operations
 
-| Internal Repr          | JSON Repr                     | Query (where)     | Description           |
-| -------------          | ---------------               | -------------     | -----------           |
-| Eq(value)              | {"$eq": value}                | field == val      | General Equality      |
-| In(Vec<value>)         | {"$in": [...values]}          | field of [...val] | One of                |
-| Lt(value)              | {"$lt": value}                | field < val       | Less than             |
-| Le(value)              | {"$le": value}                | field <= val      | Less than or equal    |
-| Gt(value)              | {"$gt": value}                | field > val       | Greater than          |
-| Ge(value)              | {"$ge": value}                | field >= val      | Greater than or equal |
-| Bw(a, true, b, true)   | {"$bw": [a, true, b, true]}   | field in a..b     | Between including a b |
-| Bw(a, false, b, false) | {"$bw": [a, false, b, false]} | field \<in> a..b  | Between excluding a b |
-| Bw(a, true, b, false)  | {"$bw": [a, true, b, false]}  | field in> a..b    | Between incl a excl b |
-| Bw(a, false, b, true)  | {"$bw": [a, false, b, true]}  | field <in a..b    | Between excl a incl b |
-| Has                    | "$has"                        | field ?           | Has value (not null)  |
+| Internal Repr          | JSON Repr                                     | Query (where)            | Description               |
+| -------------          | ---------------                               | -------------            | -----------               |
+| Eq(value)              | {"$eq": value}                                | field == val             | General Equality          |
+| In(Vec<value>)         | {"$in": [...values]}                          | field of [...val]        | One of                    |
+| Lt(value)              | {"$lt": value}                                | field < val              | Less than                 |
+| Le(value)              | {"$le": value}                                | field <= val             | Less than or equal        |
+| Gt(value)              | {"$gt": value}                                | field > val              | Greater than              |
+| Ge(value)              | {"$ge": value}                                | field >= val             | Greater than or equal     |
+| Bw(a, true, b, true)   | {"$bw": [a, true, b, true]}                   | field in a..b            | Between including a b     |
+| Bw(a, false, b, false) | {"$bw": [a, false, b, false]}                 | field \<in> a..b         | Between excluding a b     |
+| Bw(a, true, b, false)  | {"$bw": [a, true, b, false]}                  | field in> a..b           | Between incl a excl b     |
+| Bw(a, false, b, true)  | {"$bw": [a, false, b, true]}                  | field <in a..b           | Between excl a incl b     |
+| Prefix(value)          | {"$prefix": value}                            | field ^= val             | Starts with               |
+| Regex(regex)           | {"$regex": "regex"}                           | field ~= "regex"         | Matches regexp            |
+| Like(pattern)          | {"$like": "pattern"}                          | field like "pat"         | Matches like pattern      |
+| Text(query)            | {"$text": {"text": "words"}}                  | field ~ "words"          | Contains the words        |
+| Near(near)             | {"$near": {"point": point, "radius": meters}} | field near point, meters | Within the radius         |
+| Within(shape)          | {"$within": {"$box": [sw, ne]}}               | field within shape       | Within the box or polygon |
+| Has                    | "$has"                                        | field ?                  | Has value (not null)      |
 
 **NOTE: To be able to use particular field of document in filters you need create index for it first.**
 
@@ -208,6 +254,27 @@ query!(@filter field in 123..456)   // [123 ... 456]
 query!(@filter field <in> 123..456) // (123 ... 456)
 query!(@filter field <in 123..456)  // (123 ... 456]
 query!(@filter field in> 123..456)  // [123 ... 456)
+query!(@filter field ^= "abc")      // "abc..."
+query!(@filter field ~= "^a.*c$")   // "abc", "ac", ...
+query!(@filter field like "a%c")    // "abc", "ac", ...
+query!(@filter field like "a_c")    // "abc", "a-c", ...
+```
+
+The like pattern uses `%` for any sequence of chars and `_` for any single char, which can be escaped using `\`.
+The literal prefix of like pattern or regexp which is anchored to start (`^`) helps narrow the range of index keys to check.
+
+The text search finds the documents which contains all of words of text, or any of words when `any` is set. The stop words like "the" or "and" can be skipped by setting `skip_stop_words`:
+
+```ignore
+query!(@filter content ~ "quick fox")
+query!(@filter content ~ TextQuery::new("the quick fox").any().skip_stop_words())
+```
+
+The geo comparisons uses the points represented as `{"lat": .., "lon": ..}` objects. The radius is measured in meters along the great circle. The box which west longitude is greater than east one crosses the antimeridian, and the polygon is represented as `{"$polygon": [...points]}`:
+
+```ignore
+query!(@filter place near GeoPoint::new(55.75, 37.62), 1000.0)
+query!(@filter place within GeoShape::Box(GeoPoint::new(55.5, 37.3), GeoPoint::new(56.0, 38.0)))
 ```
 
 ### Logical operations
@@ -235,12 +302,14 @@ query!(@filter field <= 123 || field > 456)
 
 ## Results ordering
 
-| Internal Repr      | JSON Repr          | Query (where)       | Description                        |
-| -------------      | ---------------    | -------------       | -----------                        |
-| Primary(Asc)       | "$asc"             | >, asc (default)    | Ascending ordering by primary key  |
-| Primary(Desc)      | "$desc"            | <, desc             | Descending ordering by primary key |
-| Field(field, Asc)  | {"field": "$asc"}  | field >, field asc  | Ascending ordering by field        |
-| Field(field, Desc) | {"field": "$desc"} | field <, field desc | Descending ordering by field       |
+| Internal Repr          | JSON Repr                       | Query (where)             | Description                          |
+| -------------          | ---------------                 | -------------             | -----------                          |
+| Primary(Asc)           | "$asc"                          | >, asc (default)          | Ascending ordering by primary key    |
+| Primary(Desc)          | "$desc"                         | <, desc                   | Descending ordering by primary key   |
+| Field(field, Asc)      | {"field": "$asc"}               | field >, field asc        | Ascending ordering by field          |
+| Field(field, Desc)     | {"field": "$desc"}              | field <, field desc       | Descending ordering by field         |
+| Relevance(field)       | {"$relevance": "field"}         | relevance field           | Ordering by relevance of text search |
+| Distance(field, point) | {"$distance": {"field": point}} | distance field from point | Ordering by distance to point        |
 
 Examples:
 
@@ -260,8 +329,60 @@ query!(@order by field asc)
 // descending ordering by other.field
 query!(@order by other.field <)
 query!(@order by other.field desc)
+
+// ordering by relevance of text search on field
+query!(@order relevance content)
+
+// ordering by distance from point to place
+query!(@order distance place from GeoPoint::new(55.75, 37.62))
+```
+
+The relevance of document is the sum of weights of the searched words which it contains, the rare words weights more. The text search on field which has full-text index is required to order by relevance.
+
+The distance of document is the distance to the nearest point of field, so the field must have geo index. The documents which hasn't points will be omitted.
+
+## Projection
+
+The found documents can be projected to keep the specified fields only or to remove the specified fields. The paths of fields are same as used by indexes, so the nested fields and the `*` pattern are supported, also the arrays are projected element-wise. The projection is applied before deserializing, so the primary key is always kept.
+
+| Internal Repr       | JSON Repr                    | Query (project)  | Description                  |
+| -------------       | ---------------              | ---------------  | -----------                  |
+| Include(fields)     | {"$include": ["a", "b.c"]}   | a, b.c           | Keep the specified fields    |
+| Exclude(fields)     | {"$exclude": ["a", "b.*.c"]} | -a, -b.*.c       | Remove the specified fields  |
+
+Examples:
+
+```ignore
+// keep the title and the names of authors only
+query!(find in collection where tag == "rust" project title, authors.name)
+
+// remove the contents of all sections
+query!(find in collection order desc project -sections.*.content)
+
+// the projection of the found documents
+collection.find::<Value>(None, Order::default())?.project(Projection::include(vec!["title"]))
+```
+
+## Lookups
+
+The documents of other collections which referenced by the found documents can be embedded into it. The references is the values of local field, which can be the primary keys of referenced documents or the keys of indexed foreign field. The referenced documents are read using the same transaction as the found document and will be embedded as an array under the specified path.
+
+```ignore
+// embed the author of each post under `author` field
+let posts = collection
+    .find::<Value>(None, Order::default())?
+    .lookup(Lookup::new("users", "author_id", "author"))?;
+
+// embed the tags which `name` field is referenced by `tags` field of post
+let posts = collection
+    .find::<Value>(None, Order::default())?
+    .lookup(Lookup::new("tags", "tags", "meta.tags").foreign("name"))?;
 ```
 
+The same lookups in JSON representation is `{"from": "users", "local": "author_id", "path": "author"}` and `{"from": "tags", "local": "tags", "foreign": "name", "path": "meta.tags"}`.
+
+The lookups applies before projection, so the embedded documents can be projected too.
+
 ## Modifiers
 
 | Internal Repr              | JSON Repr                       | Query (where)         | Description                |
@@ -333,6 +454,46 @@ query!(@modify obj ~= { a: true, b: "abc", c: 123 })
 query!(@modify obj ~= extra)
 ```
 
+## Aggregation
+
+| Internal Repr | JSON Repr           | Description                              |
+| ------------- | ---------------     | -----------                              |
+| Count         | "$count"            | The number of documents in group         |
+| Sum(field)    | {"$sum": "field"}   | The sum of numeric values of field       |
+| Avg(field)    | {"$avg": "field"}   | The average of numeric values of field   |
+| Min(field)    | {"$min": "field"}   | The minimum value of field               |
+| Max(field)    | {"$max": "field"}   | The maximum value of field               |
+| First(field)  | {"$first": "field"} | The value of field of the first document |
+| Last(field)   | {"$last": "field"}  | The value of field of the last document  |
+
+The documents selected by filter will be grouped by the values of group fields, and each group will be accumulated into the single document with the keys of group and the accumulated values. The documents in groups are taken in the order of primary keys. The document which has several values of group field belongs to several groups, and the document which hasn't values belongs to the group with null key.
+
+Example:
+
+```ignore
+let groups = collection.aggregate(
+    query!(@filter price > 10),
+    vec!["status".into()],
+    Accumulators::new()
+        .with("count", Accumulator::Count)
+        .with("total", Accumulator::Sum("price".into())),
+)?;
+```
+
+The same accumulators in JSON representation is `{"count": "$count", "total": {"$sum": "price"}}`.
+
+## Distinct keys and facets
+
+The distinct keys of indexed field can be got with the numbers of documents which has it. The keys are walked using index, so the documents will not be loaded.
+
+```ignore
+// all tags in the order of keys
+let tags = collection.distinct("tag", None)?;
+
+// ten most frequent tags of the selected documents
+let tags = collection.facets("tag", query!(@filter price > 10), Some(10))?;
+```
+
 */
 
 pub use ledb_types as types;
@@ -344,59 +505,95 @@ pub use ledb_derive::Document;
 #[macro_use]
 mod test;
 
+mod aggregate;
+mod archive;
+mod catalog;
 mod collection;
 mod document;
 mod enumerate;
 mod error;
 mod filter;
 mod float;
+mod gate;
+mod geo;
 mod index;
+mod lookup;
 mod modify;
+mod plan;
 mod pool;
+mod primary;
+mod projection;
 mod selection;
+mod snapshot;
 mod storage;
+mod text;
+mod timestamp;
+mod transaction;
 mod value;
 
 #[macro_use]
 mod macros;
 
-pub use ledb_types::{Document, Identifier, IndexKind, KeyField, KeyFields, KeyType, Primary};
+pub use ledb_types::{
+    Document, Identifier, IndexKind, KeyField, KeyFields, KeyType, Primary, PrimaryType,
+};
 
 #[cfg(feature = "ledb-derive")]
 #[doc(hidden)]
 pub use ledb_derive::*;
 
-pub use collection::{Collection, DocumentsIterator};
+pub use aggregate::{Accumulator, Accumulators};
+pub use catalog::{CollectionDescription, IndexDescription, StorageDescription};
+pub use collection::{Collection, DocumentsIterator, TxnDocumentsIterator};
 pub use document::{to_value, RawDocument, Value};
 pub use error::{Error, Result, ResultWrap};
-pub use filter::{Comp, Cond, Filter, Order, OrderKind};
+pub use filter::{Comp, Cond, Filter, LikePattern, Order, OrderKind, Page, Position, TextQuery};
+pub use geo::{GeoNear, GeoPoint, GeoShape};
+pub use lookup::Lookup;
 pub use macros::*;
 pub use modify::{Action, Modify, WrappedRegex};
-pub use storage::{Info, Options, Stats, Storage};
+pub use plan::{Explain, FilterPlan, FilterStep, OrderPlan};
+pub use projection::Projection;
+pub use snapshot::{Snapshot, SnapshotCollection};
+pub use storage::{Info, Options, Stats, Storage, Usage};
+pub use transaction::{Transaction, TransactionCollection};
 pub use value::KeyData;
 
-use collection::CollectionDef;
+use aggregate::Aggregation;
+use catalog::{load_catalog, now, CollectionMeta, IndexMeta, StorageMeta};
+use collection::{seek_after, CollectionDef};
 use enumerate::{Enumerable, Serial, SerialGenerator};
-use index::{Index, IndexDef};
+use gate::{TxnEnv, TxnGate};
+use index::{extract_field_keys, extract_field_list, Index, IndexDef};
+use lookup::reference_id;
+use plan::SORT_COST;
 use pool::Pool;
+use primary::PrimaryKey;
 use selection::Selection;
-use storage::{DatabaseDef, StorageData};
+use storage::{load_databases, DatabaseDef, Definitions, StorageData};
 
 #[cfg(test)]
 mod tests {
     use serde::{Deserialize, Serialize};
     use serde_json::json;
+    use std::{
+        collections::BTreeMap,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
 
     use super::{
-        test::test_db, Collection, Document, Identifier, IndexKind, KeyFields, KeyType, Primary,
-        Result, Value,
+        test::{test_db, test_db_with, test_path},
+        to_value, Accumulator, Accumulators, Collection, Comp, DatabaseDef, Document, Error,
+        FilterStep, GeoNear, GeoPoint, GeoShape, Identifier, IndexKind, KeyData, KeyField,
+        KeyFields, KeyType, Lookup, Options, Order, OrderKind, OrderPlan, Page, Primary,
+        PrimaryType, Result, ResultWrap, Storage, TextQuery, Value,
     };
 
     fn get_id(val: Value) -> Option<Primary> {
         if let Value::Map(map) = val {
             map.get(&Value::Text("$".into())).and_then(|val| {
                 if let Value::Integer(id) = val {
-                    Some(*id as u32)
+                    Some(Primary::Int(*id as u64))
                 } else {
                     None
                 }
@@ -408,7 +605,7 @@ mod tests {
 
     macro_rules! assert_found {
         ($res:expr $(,$exp:expr)*) => {
-            let ids: Vec<Primary> = vec![$($exp),*];
+            let ids: Vec<Primary> = vec![$(Primary::from($exp)),*];
             assert_eq!($res.unwrap().map(|doc: Result<Value>| get_id(doc.unwrap()).unwrap()).collect::<Vec<_>>(), ids)
         }
     }
@@ -495,6 +692,144 @@ mod tests {
         assert_eq!(&c.get::<Doc>(5).unwrap().unwrap().s, "321");
     }
 
+    #[test]
+    fn primary_sequence() {
+        let s = test_db("primary_sequence").unwrap();
+        let c = s.collection("test").unwrap();
+
+        assert_eq!(c.sequence().unwrap(), 0);
+
+        fill_data(&c).unwrap();
+
+        assert_eq!(c.sequence().unwrap(), 7);
+        assert!(c.delete(7).unwrap());
+        assert!(c.delete(6).unwrap());
+        assert_eq!(c.last_id().unwrap(), 5);
+        assert_eq!(c.sequence().unwrap(), 7);
+        assert_eq!(query!(insert into c { "s": "new" }).unwrap(), 8);
+
+        c.put(json!({ "$": 20, "s": "put" })).unwrap();
+        assert_eq!(query!(insert into c { "s": "next" }).unwrap(), 21);
+
+        c.reset_sequence(0).unwrap();
+        assert_eq!(c.sequence().unwrap(), 21);
+        c.reset_sequence(100).unwrap();
+        assert_eq!(query!(insert into c { "s": "after" }).unwrap(), 101);
+
+        let ids = (0..8)
+            .map(|_| {
+                let c = c.clone();
+                std::thread::spawn(move || {
+                    (0..10)
+                        .map(|_| query!(insert into c { "s": "" }).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect::<std::collections::HashSet<_>>();
+
+        assert_eq!(ids.len(), 80);
+        assert_eq!(c.sequence().unwrap(), 181);
+    }
+
+    #[test]
+    fn primary_u64() {
+        let s = test_db("primary_u64").unwrap();
+        let c = s.collection_with_key("test", PrimaryType::U64).unwrap();
+
+        assert_eq!(c.primary_type(), PrimaryType::U64);
+
+        mk_index(&c).unwrap();
+        c.reset_sequence(u64::from(u32::MAX)).unwrap();
+
+        let id = c.insert(json!({ "s": "big", "b": true, "i": [] })).unwrap();
+        assert_eq!(id, 1 << 32);
+        assert_eq!(&c.get::<Doc>(1u64 << 32).unwrap().unwrap().s, "big");
+        assert_eq!(c.get::<Doc>("4294967296").unwrap().unwrap().k, Some(id));
+
+        fill_data(&c).unwrap();
+
+        assert_found!(
+            query!(find in c where b == true),
+            4294967296,
+            4294967299,
+            4294967300,
+            4294967302
+        );
+        assert_eq!(c.last_id().unwrap(), 4294967303);
+
+        assert!(s.collection_with_key("test", PrimaryType::U32).is_err());
+        assert_eq!(
+            s.collection("test").unwrap().primary_type(),
+            PrimaryType::U64
+        );
+    }
+
+    #[test]
+    fn primary_uuid() {
+        let s = test_db("primary_uuid").unwrap();
+        let c = s.collection_with_key("test", PrimaryType::Uuid).unwrap();
+
+        mk_index(&c).unwrap();
+
+        let id = c.insert(json!({ "s": "abc", "b": true, "i": [] })).unwrap();
+        assert_eq!(id.as_str().unwrap().len(), 36);
+
+        let id2 = c
+            .insert(json!({ "$": "6BA7B810-9DAD-11D1-80B4-00C04FD430C8", "s": "def", "b": true, "i": [] }))
+            .unwrap();
+        assert_eq!(id2, "6ba7b810-9dad-11d1-80b4-00c04fd430c8");
+
+        assert!(c
+            .insert(json!({ "$": "6ba7b8109dad11d180b400c04fd430c8", "s": "ghi" }))
+            .is_err());
+        assert!(c.insert(json!({ "$": 1, "s": "ghi" })).is_err());
+
+        assert_eq!(&c.get::<Doc>(id.clone()).unwrap().unwrap().s, "abc");
+        assert_eq!(&c.get::<Doc>(&id2).unwrap().unwrap().s, "def");
+
+        let ids = c
+            .find_ids(query!(@filter b == true))
+            .unwrap()
+            .into_iter()
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(ids, [id.clone(), id2.clone()].iter().cloned().collect());
+
+        assert!(c.delete(&id2).unwrap());
+        assert!(!c.has(&id2).unwrap());
+        assert_eq!(
+            query!(find Doc in c where b == true)
+                .unwrap()
+                .map(|doc| doc.unwrap().k.unwrap())
+                .collect::<Vec<_>>(),
+            vec![id]
+        );
+    }
+
+    #[test]
+    fn primary_string() {
+        let s = test_db("primary_string").unwrap();
+        let c = s.collection_with_key("test", PrimaryType::String).unwrap();
+
+        c.insert(json!({ "$": "b", "s": "second", "b": false, "i": [] }))
+            .unwrap();
+        c.insert(json!({ "$": "a", "s": "first", "b": false, "i": [] }))
+            .unwrap();
+        c.put(json!({ "$": "c", "s": "third", "b": false, "i": [] }))
+            .unwrap();
+
+        assert_eq!(
+            query!(find Doc in c order desc)
+                .unwrap()
+                .map(|doc| doc.unwrap().s)
+                .collect::<Vec<_>>(),
+            vec!["third", "second", "first"]
+        );
+        assert_eq!(c.last_id().unwrap(), "c");
+    }
+
     #[test]
     fn index_before_insert() {
         let s = test_db("index_before_insert").unwrap();
@@ -760,117 +1095,1936 @@ mod tests {
     }
 
     #[test]
-    fn remove_eq_str() {
-        let s = test_db("remove_eq_str").unwrap();
+    fn find_without_index() {
+        let s = test_db("find_without_index").unwrap();
         let c = s.collection("test").unwrap();
 
-        mk_index(&c).unwrap();
+        c.create_index("s", IndexKind::Unique, KeyType::String)
+            .unwrap();
         fill_data(&c).unwrap();
 
-        assert_found!(query!(find in c where s == "def"), 2);
-        assert_eq!(query!(remove from c where s == "def").unwrap(), 1);
-        assert_found!(query!(find in c where s == "def"));
-        assert_found!(query!(find in c where s == "abc"), 1);
+        assert_found!(query!(find in c where b == true), 3, 4, 6);
+        assert_found!(query!(find in c where b != true), 1, 2, 5, 7);
+        assert_found!(query!(find in c where i > 3 order desc), 6, 4, 3);
+        assert_found!(query!(find in c where i == 2.0), 2, 3, 5);
+        assert_found!(query!(find in c where n.i in 1..2), 2, 3, 5);
+        assert_found!(query!(find in c where n.a?), 2, 4, 5, 6);
+        assert_found!(
+            query!(find in c where s of ["def", "xyz"] && n.a == "t4"),
+            4
+        );
+        assert_found!(query!(find in c where n.a == "t4" || s == "abc"), 1, 4, 5);
+        assert_found!(query!(find in c where s > "a" && !(n.a == "t2")), 1);
+        assert_found!(query!(find in c where s < "a" && !(n.a == "t2")), 3, 5, 7);
     }
 
     #[test]
-    fn remove_ge_int() {
-        let s = test_db("remove_ge_int").unwrap();
+    fn find_projection() {
+        let s = test_db("find_projection").unwrap();
         let c = s.collection("test").unwrap();
 
         mk_index(&c).unwrap();
         fill_data(&c).unwrap();
 
-        assert_found!(query!(find in c where i >= 4), 3, 4, 6);
-        assert_eq!(query!(remove from c where i >= 4).unwrap(), 3);
-        assert_found!(query!(find in c where i >= 4));
-        assert_found!(query!(find in c where i >= 2), 2, 5);
+        let docs: Vec<Value> = query!(find in c where s == "def" project s, n.a)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            docs,
+            vec![to_value(json!({ "$": 2, "s": "def", "n": { "a": ["t1", "t2"] } })).unwrap()]
+        );
+
+        let docs: Vec<Value> = query!(find in c where b == true order desc project -s, -n.*)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            docs,
+            vec![
+                to_value(json!({ "$": 6, "b": true, "i": [3, 5], "n": {} })).unwrap(),
+                to_value(json!({ "$": 4, "b": true, "i": [1, 5, 4], "n": {} })).unwrap(),
+                to_value(json!({ "$": 3, "b": true, "i": [2, 3, 4], "n": {} })).unwrap(),
+            ]
+        );
+
+        // the missing fields of typed documents
+        let docs: Vec<Doc> = query!(find Doc in c where n.i == 2 project -n)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            docs,
+            vec![
+                Doc {
+                    k: Some(Primary::Int(3)),
+                    s: "123".into(),
+                    b: true,
+                    i: vec![2, 3, 4],
+                    ..Doc::default()
+                },
+                Doc {
+                    k: Some(Primary::Int(5)),
+                    s: "321".into(),
+                    b: false,
+                    i: vec![2],
+                    ..Doc::default()
+                },
+            ]
+        );
     }
 
     #[test]
-    fn update_set_eq_str() {
-        let s = test_db("update_set_eq_str").unwrap();
-        let c = s.collection("test").unwrap();
+    fn find_lookup() {
+        let s = test_db("find_lookup").unwrap();
+        let users = s.collection("users").unwrap();
+        let tags = s.collection("tags").unwrap();
+        let posts = s.collection("posts").unwrap();
+
+        query!(index for tags name str unique).unwrap();
+
+        users.insert(json!({ "name": "alice" })).unwrap();
+        users.insert(json!({ "name": "bob" })).unwrap();
+        tags.insert(json!({ "name": "rust", "title": "Rust" }))
+            .unwrap();
+        tags.insert(json!({ "name": "db", "title": "Databases" }))
+            .unwrap();
+        posts
+            .insert(json!({ "title": "First", "author": 2, "tags": ["db", "rust", "db"] }))
+            .unwrap();
+        posts
+            .insert(json!({ "title": "Second", "author": 3, "meta": { "tags": ["none"] } }))
+            .unwrap();
+
+        let docs: Vec<Value> = posts
+            .find(None, Order::default())
+            .unwrap()
+            .lookup(Lookup::new("users", "author", "meta.author"))
+            .unwrap()
+            .lookup(Lookup::new("tags", "tags", "tags").foreign("name"))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            docs,
+            vec![
+                to_value(json!({ "$": 1, "title": "First", "author": 2,
+                                 "meta": { "author": [{ "$": 2, "name": "bob" }] },
+                                 "tags": [{ "$": 1, "name": "rust", "title": "Rust" },
+                                          { "$": 2, "name": "db", "title": "Databases" }] }))
+                .unwrap(),
+                // the missing references
+                to_value(json!({ "$": 2, "title": "Second", "author": 3,
+                                 "meta": { "author": [], "tags": ["none"] },
+                                 "tags": [] }))
+                .unwrap(),
+            ]
+        );
+
+        // the projection of referenced documents
+        {
+            let snapshot = s.snapshot().unwrap();
+            let docs: Vec<Value> = snapshot
+                .collection("posts")
+                .unwrap()
+                .find(query!(@filter title == "First"), Order::default())
+                .unwrap()
+                .project(query!(@project title, author.name))
+                .lookup(Lookup::new("users", "author", "author"))
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap();
+            assert_eq!(
+                docs,
+                vec![
+                    to_value(json!({ "$": 1, "title": "First", "author": [{ "name": "bob" }] }))
+                        .unwrap()
+                ]
+            );
+        }
+
+        // the missing collection or index
+        assert!(posts
+            .find::<Value>(None, Order::default())
+            .unwrap()
+            .lookup(Lookup::new("authors", "author", "author"))
+            .is_err());
+        assert!(posts
+            .find::<Value>(None, Order::default())
+            .unwrap()
+            .lookup(Lookup::new("users", "author", "author").foreign("name"))
+            .is_err());
+    }
+
+    #[test]
+    fn scan_matches_index() {
+        let s = test_db("scan_matches_index").unwrap();
+        let c = s.collection("indexed").unwrap();
+        let d = s.collection("scanned").unwrap();
 
         mk_index(&c).unwrap();
         fill_data(&c).unwrap();
-
-        assert_found!(query!(find in c where s == "def"), 2);
-        assert_eq!(
-            query!(update in c modify s = "klm" where s == "def").unwrap(),
-            1
-        );
-        assert_found!(query!(find in c where s == "def"));
-        assert_found!(query!(find in c where s == "abc"), 1);
-        assert_found!(query!(find in c where s == "klm"), 2);
+        fill_data(&d).unwrap();
+
+        for filter in [
+            query!(@filter s == "def"),
+            query!(@filter s >= "123" && s < "abc"),
+            query!(@filter b == false),
+            query!(@filter i of [1, 5]),
+            query!(@filter i in 2..3),
+            query!(@filter i <= 3 && b == true),
+            query!(@filter n.i < 2 || n.a == "t1"),
+            query!(@filter n.i > 1 && i < 4),
+            query!(@filter n.i?),
+        ] {
+            assert_eq!(
+                c.find_ids(filter.clone()).unwrap(),
+                d.find_ids(filter).unwrap()
+            );
+        }
     }
 
-    // derive
-    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
-    struct RootDoc {
-        #[document(primary)]
-        id: Option<Primary>,
+    fn fill_plan(c: &Collection) -> Result<()> {
+        query!(index for c n int unique, m int, a int)?;
+        for i in 0..200 {
+            c.insert(json!({ "n": i, "m": i % 10, "a": [i % 7, i % 5] }))?;
+        }
+        Ok(())
+    }
 
-        #[document(unique)]
-        title: String,
+    #[test]
+    fn explain_filter() {
+        let s = test_db("explain_filter").unwrap();
+        let c = s.collection("test").unwrap();
 
-        #[document(nested)]
-        meta: MetaData,
+        fill_plan(&c).unwrap();
+
+        let e = c.explain(None, Order::default()).unwrap();
+        assert_eq!(e.filter, None);
+        assert_eq!(e.order, OrderPlan::Primary(OrderKind::Asc));
+        assert_eq!((e.estimated, e.actual), (200, 200));
+
+        // the most selective condition goes first
+        let e = c
+            .explain(query!(@filter m == 3 && n < 50), Order::default())
+            .unwrap();
+        let plan = e.filter.unwrap();
+        assert_eq!((plan.actual, e.actual), (Some(5), 5));
+        assert_eq!(e.order, OrderPlan::SortPrimary(OrderKind::Asc));
+        if let FilterStep::And(plans) = plan.step {
+            assert_eq!(
+                plans[0].step,
+                FilterStep::Index("m".into(), Comp::Eq(KeyData::Int(3)))
+            );
+            assert_eq!((plans[0].estimated, plans[0].actual), (20, Some(20)));
+            assert_eq!(
+                plans[1].step,
+                FilterStep::Index("n".into(), Comp::Lt(KeyData::Int(50)))
+            );
+        } else {
+            panic!("Expected $and plan");
+        }
 
-        #[document(nested)]
-        links: Vec<LinkData>,
+        // checking few documents is cheaper than index lookup
+        let e = c
+            .explain(query!(@filter n == 5 && a > 2 && x == 1), Order::default())
+            .unwrap();
+        assert_eq!(e.actual, 0);
+        if let FilterStep::And(plans) = e.filter.unwrap().step {
+            assert_eq!(
+                plans[0].step,
+                FilterStep::Index("n".into(), Comp::Eq(KeyData::Int(5)))
+            );
+            assert_eq!(plans[0].actual, Some(1));
+            assert_eq!(
+                plans[1].step,
+                FilterStep::Scan(query!(@filter a > 2).unwrap())
+            );
+            assert_eq!(
+                plans[2].step,
+                FilterStep::Scan(query!(@filter x == 1).unwrap())
+            );
+        } else {
+            panic!("Expected $and plan");
+        }
+        assert_found!(query!(find in c where n == 5 && a > 2), 6);
     }
 
-    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
-    #[document(nested)]
-    struct MetaData {
-        #[document(index)]
-        keywords: Vec<String>,
+    #[test]
+    fn explain_order() {
+        let s = test_db("explain_order").unwrap();
+        let c = s.collection("test").unwrap();
 
-        description: String,
+        fill_plan(&c).unwrap();
+
+        let e = c.explain(None, query!(@order by a desc)).unwrap();
+        assert_eq!(e.order, OrderPlan::Index("a".into(), OrderKind::Desc));
+
+        // few documents will be sorted
+        let e = c
+            .explain(query!(@filter n < 20), query!(@order by a desc))
+            .unwrap();
+        assert_eq!(e.order, OrderPlan::SortField("a".into(), OrderKind::Desc));
+        let sorted = c
+            .find::<Value>(query!(@filter n < 20), query!(@order by a desc))
+            .unwrap()
+            .map(|doc| get_id(doc.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(sorted.len(), 35);
+
+        // the inverted selection uses index
+        let e = c
+            .explain(query!(@filter !(n >= 20)), query!(@order by a desc))
+            .unwrap();
+        assert_eq!(e.order, OrderPlan::Index("a".into(), OrderKind::Desc));
+        let scanned = c
+            .find::<Value>(query!(@filter !(n >= 20)), query!(@order by a desc))
+            .unwrap()
+            .map(|doc| get_id(doc.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(sorted, scanned);
+
+        let e = c
+            .explain(query!(@filter m == 1), query!(@order by n asc))
+            .unwrap();
+        assert_eq!(e.order, OrderPlan::SortField("n".into(), OrderKind::Asc));
+        assert_found!(
+            query!(find in c where m == 1 && n < 50 order by n desc),
+            42,
+            32,
+            22,
+            12,
+            2
+        );
     }
 
-    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
-    #[document(nested)]
-    struct LinkData {
-        #[document(index)]
-        title: Vec<String>,
+    #[test]
+    fn find_page() {
+        let s = test_db("find_page").unwrap();
+        let c = s.collection("test").unwrap();
 
-        url: String,
+        fill_plan(&c).unwrap();
 
-        #[document(nested)]
-        meta: MetaData,
+        assert_found!(
+            c.find_page(None, Order::default(), Page::new(10, Some(5))),
+            11,
+            12,
+            13,
+            14,
+            15
+        );
+        assert_found!(
+            c.find_page(
+                None,
+                query!(@order desc),
+                Page::new(0, Some(3)).after(None, 190)
+            ),
+            189,
+            188,
+            187
+        );
+        assert_found!(c.find_page(None, query!(@order desc), Page::default().after(None, 1)));
+        assert_found!(
+            c.find_page(
+                query!(@filter m == 3),
+                Order::default(),
+                Page::new(0, Some(2)).after(None, 14)
+            ),
+            24,
+            34
+        );
+        assert_found!(
+            c.find_page(
+                query!(@filter a == 0),
+                query!(@order by n asc),
+                Page::new(2, Some(3))
+            ),
+            8,
+            11,
+            15
+        );
     }
 
     #[test]
-    fn derive_primary() {
-        assert_eq!(MetaData::primary_field().as_ref(), "$");
-        assert_eq!(LinkData::primary_field().as_ref(), "$");
-        assert_eq!(RootDoc::primary_field().as_ref(), "id");
+    fn find_page_after_key() {
+        let s = test_db("find_page_after_key").unwrap();
+        let c = s.collection("test").unwrap();
+
+        fill_plan(&c).unwrap();
+
+        let after = |key: i64, id: u64| Page::new(0, Some(3)).after(Some(KeyData::Int(key)), id);
+
+        // index with duplicates
+        assert_found!(
+            c.find_page(None, query!(@order by m asc), after(3, 14)),
+            24,
+            34,
+            44
+        );
+        assert_found!(
+            c.find_page(None, query!(@order by m desc), after(3, 14)),
+            4,
+            193,
+            183
+        );
+        assert_found!(
+            c.find_page(None, query!(@order by m asc), after(3, 194)),
+            5,
+            15,
+            25
+        );
+        assert_found!(
+            c.find_page(None, query!(@order by m asc), after(3, 1000)),
+            5,
+            15,
+            25
+        );
+        assert_found!(
+            c.find_page(None, query!(@order by m desc), after(3, 1)),
+            193,
+            183,
+            173
+        );
+        assert_found!(c.find_page(None, query!(@order by m asc), after(20, 1)));
+        assert_found!(
+            c.find_page(None, query!(@order by m desc), after(20, 1)),
+            200,
+            190,
+            180
+        );
+
+        // unique index
+        assert_found!(
+            c.find_page(None, query!(@order by n desc), after(100, 101)),
+            100,
+            99,
+            98
+        );
+
+        // sorting selected documents
+        assert_eq!(
+            c.explain(query!(@filter m == 1), query!(@order by n asc))
+                .unwrap()
+                .order,
+            OrderPlan::SortField("n".into(), OrderKind::Asc)
+        );
+        assert_found!(
+            c.find_page(
+                query!(@filter m == 1),
+                query!(@order by n asc),
+                after(21, 22)
+            ),
+            32,
+            42,
+            52
+        );
+        assert_found!(
+            c.find_page(
+                query!(@filter m == 1),
+                query!(@order by n desc),
+                after(21, 22)
+            ),
+            12,
+            2
+        );
+
+        assert!(c
+            .find_page::<Value>(
+                None,
+                query!(@order by m asc),
+                Page::default().after(None, 14)
+            )
+            .is_err());
     }
 
     #[test]
-    fn derive_key_fields() {
+    fn order_fields() {
+        let s = test_db("order_fields").unwrap();
+        let c = s.collection("test").unwrap();
+
+        fill_plan(&c).unwrap();
+
+        let e = c
+            .explain(query!(@filter n < 30), query!(@order by m desc, n asc))
+            .unwrap();
         assert_eq!(
-            MetaData::key_fields(),
-            KeyFields::new().with_field(("keywords", KeyType::String, IndexKind::Index))
+            e.order,
+            OrderPlan::SortFields(vec![
+                ("m".into(), OrderKind::Desc),
+                ("n".into(), OrderKind::Asc)
+            ])
         );
-
         assert_eq!(
-            LinkData::key_fields(),
-            KeyFields::new()
-                .with_field(("title", KeyType::String, IndexKind::Index))
-                .with_field(("meta.keywords", KeyType::String, IndexKind::Index))
+            c.explain(None, Order::fields(vec![("n", OrderKind::Asc)]))
+                .unwrap()
+                .order,
+            OrderPlan::Index("n".into(), OrderKind::Asc)
+        );
+
+        assert_found!(
+            c.find_page(
+                query!(@filter n < 30),
+                query!(@order by m desc, n asc),
+                Page::new(0, Some(6))
+            ),
+            10,
+            20,
+            30,
+            9,
+            19,
+            29
+        );
+        assert_found!(
+            c.find_page(
+                query!(@filter n < 30),
+                query!(@order by m asc, n desc),
+                Page::new(0, Some(6))
+            ),
+            21,
+            11,
+            1,
+            22,
+            12,
+            2
+        );
+        assert_found!(
+            c.find_page(
+                query!(@filter n < 30),
+                query!(@order by m desc, n asc),
+                Page::new(0, Some(3)).after(None, 20)
+            ),
+            30,
+            9,
+            19
+        );
+
+        // the first key in order of field which has several keys
+        assert_found!(
+            query!(find in c where n < 10 order by a asc, n desc),
+            8,
+            6,
+            1,
+            9,
+            7,
+            2,
+            10,
+            3,
+            4,
+            5
         );
 
+        // the documents without keys goes first in ascending order
+        c.insert(json!({ "n": 200 })).unwrap();
+        assert_found!(
+            query!(find in c where n > 195 order by m asc, n asc),
+            201,
+            197,
+            198,
+            199,
+            200
+        );
+        assert_found!(
+            query!(find in c where n > 195 order by m desc, n asc),
+            200,
+            199,
+            198,
+            197,
+            201
+        );
+
+        assert!(c
+            .find::<Value>(None, query!(@order by m asc, x asc))
+            .is_err());
+    }
+
+    #[test]
+    fn compound_index() {
+        let s = test_db("compound_index").unwrap();
+        let c = s.collection("test").unwrap();
+
+        fill_plan(&c).unwrap();
+        query!(index for c (m int, n int) unique, (a int, m int)).unwrap();
+
+        assert!(c.has_index("m,n").unwrap());
+        assert!(c.get_indexes().unwrap().contains(
+            &KeyField::compound(vec![("m", KeyType::Int), ("n", KeyType::Int)])
+                .with_kind(IndexKind::Unique)
+        ));
+
+        // the equality on first field and the range on next field
+        let e = c
+            .explain(query!(@filter m == 3 && n < 50), Order::default())
+            .unwrap();
         assert_eq!(
-            RootDoc::key_fields(),
-            KeyFields::new()
-                .with_field(("title", KeyType::String, IndexKind::Unique))
-                .with_field(("meta.keywords", KeyType::String, IndexKind::Index))
-                .with_field(("links.title", KeyType::String, IndexKind::Index))
-                .with_field(("links.meta.keywords", KeyType::String, IndexKind::Index))
+            e.filter.unwrap().step,
+            FilterStep::Compound(
+                "m,n".into(),
+                vec![KeyData::Int(3)],
+                Some(Comp::Lt(KeyData::Int(50)))
+            )
         );
+        assert_found!(query!(find in c where m == 3 && n < 50), 4, 14, 24, 34, 44);
+        assert_found!(query!(find in c where m == 3 && n in 13..33), 14, 24, 34);
+        assert_found!(query!(find in c where n > 183 && m == 3 && a == 3), 194);
+
+        // the all combinations of keys of field which has several keys
+        assert_eq!(c.count(query!(@filter a == 4 && m == 9)).unwrap(), 20);
+        assert_eq!(c.count(query!(@filter a == 4 && m == 8)).unwrap(), 3);
+
+        // ordering by the first fields of index
+        let e = c.explain(None, query!(@order by m desc, n desc)).unwrap();
+        assert_eq!(e.order, OrderPlan::Index("m,n".into(), OrderKind::Desc));
+        assert_found!(
+            c.find_page(None, query!(@order by m asc, n asc), Page::new(0, Some(3))),
+            1,
+            11,
+            21
+        );
+        assert_found!(
+            c.find_page(
+                None,
+                query!(@order by m asc, n asc),
+                Page::new(0, Some(3)).after(None, 21)
+            ),
+            31,
+            41,
+            51
+        );
+        assert_found!(
+            c.find_page(
+                query!(@filter n >= 100),
+                query!(@order by m desc, n desc),
+                Page::new(0, Some(3))
+            ),
+            200,
+            190,
+            180
+        );
+
+        // the different ordering of fields and few documents will be sorted
+        let e = c.explain(None, query!(@order by m desc, n asc)).unwrap();
+        assert_eq!(
+            e.order,
+            OrderPlan::SortFields(vec![
+                ("m".into(), OrderKind::Desc),
+                ("n".into(), OrderKind::Asc)
+            ])
+        );
+        let e = c
+            .explain(query!(@filter n < 20), query!(@order by m asc, n asc))
+            .unwrap();
+        assert_eq!(
+            e.order,
+            OrderPlan::SortFields(vec![
+                ("m".into(), OrderKind::Asc),
+                ("n".into(), OrderKind::Asc)
+            ])
+        );
+
+        // the keys of compound unique index cannot be duplicated
+        let d = s.collection("unique").unwrap();
+        query!(index for d (x str, y int) unique).unwrap();
+        d.insert(json!({ "x": "a", "y": 1 })).unwrap();
+        d.insert(json!({ "x": "a", "y": -1 })).unwrap();
+        d.insert(json!({ "x": "a\u{0}", "y": 1 })).unwrap();
+        assert!(d.insert(json!({ "x": "a", "y": 1 })).is_err());
+        assert_found!(query!(find in d where x == "a" && y > -2), 1, 2);
+        assert_found!(query!(find in d where x == "a" order by x asc, y asc), 2, 1);
+
+        // the number of key types must be same as the number of fields
+        assert!(d
+            .ensure_index("x,y", IndexKind::Index, KeyType::String)
+            .is_err());
+
+        // the compound index should be persistent
+        let path = test_path("compound_index_backup");
+        s.backup(&path, false).unwrap();
+        let b = Storage::new(&path, Options::default()).unwrap();
+        let e = b.collection("test").unwrap();
+        assert_eq!(e.get_indexes().unwrap(), c.get_indexes().unwrap());
+        assert_found!(query!(find in e where m == 3 && n < 30), 4, 14, 24);
+    }
+
+    #[test]
+    fn prefix_compare() {
+        let s = test_db("prefix_compare").unwrap();
+        let c = s.collection("test").unwrap();
+
+        for path in &["a", "a/b", "a/b/c", "a/bc", "ab", "b/a", ""] {
+            query!(insert into c { "path": path, "name": path }).unwrap();
+        }
+        query!(index for c path str).unwrap();
+
+        // the bounded range of index keys
+        let e = c
+            .explain(query!(@filter path ^= "a/b"), Order::default())
+            .unwrap();
+        assert_eq!(
+            e.filter.unwrap().step,
+            FilterStep::Index("path".into(), Comp::Prefix(KeyData::from("a/b")))
+        );
+        assert_found!(query!(find in c where path ^= "a/b"), 2, 3, 4);
+        assert_found!(query!(find in c where path ^= "a/b/"), 3);
+        assert_found!(query!(find in c where path ^= "a"), 1, 2, 3, 4, 5);
+        assert_found!(query!(find in c where path ^= "c"));
+        assert_eq!(c.count(query!(@filter path ^= "")).unwrap(), 7);
+
+        // the scanning of field without index
+        assert_found!(query!(find in c where name ^= "a/b"), 2, 3, 4);
+        assert_found!(query!(find in c where name ^= "b"), 6);
+
+        // the prefix of non-string keys
+        query!(index for c id int).unwrap();
+        assert!(c.count(query!(@filter id ^= "1")).is_err());
+    }
+
+    #[test]
+    fn pattern_match() {
+        let s = test_db("pattern_match").unwrap();
+        let c = s.collection("test").unwrap();
+
+        for name in &[
+            "foobar", "foo-bar", "FooBar", "foobaz", "barfoo", "fobar", "",
+        ] {
+            query!(insert into c { "name": name, "title": name }).unwrap();
+        }
+        query!(index for c name str).unwrap();
+
+        // the keys of string index
+        assert_found!(query!(find in c where name ~= "^foo.*bar$"), 1, 2);
+        assert_found!(query!(find in c where name ~= "(?i)^foo.*bar$"), 1, 2, 3);
+        assert_found!(query!(find in c where name ~= "foo"), 1, 2, 4, 5);
+        assert_found!(query!(find in c where name ~= "^$"), 7);
+        assert_found!(query!(find in c where name like "foo%"), 1, 2, 4);
+        assert_found!(query!(find in c where name like "fo_bar"), 1);
+        assert_found!(query!(find in c where name like "%bar"), 1, 2, 6);
+        assert_found!(query!(find in c where name like "%o%" && name ~= "z$"), 4);
+
+        // the scanning of field without index
+        assert_found!(query!(find in c where title ~= "(?i)^foo.*bar$"), 1, 2, 3);
+        assert_found!(query!(find in c where title like "%bar"), 1, 2, 6);
+
+        // the patterns of non-string keys
+        query!(index for c id int).unwrap();
+        assert!(c.count(query!(@filter id like "1%")).is_err());
+    }
+
+    #[test]
+    fn fulltext_search() {
+        let s = test_db("fulltext_search").unwrap();
+        let c = s.collection("test").unwrap();
+
+        for text in &[
+            "The quick brown fox jumps over the lazy dog",
+            "A quick brown dog",
+            "Lazy cats sleep all day",
+            "The fox and the hound",
+        ] {
+            query!(insert into c { "content": text, "body": text }).unwrap();
+        }
+        query!(index for c content str fulltext).unwrap();
+
+        // the words of full-text index
+        let e = c
+            .explain(query!(@filter content ~ "quick brown"), Order::default())
+            .unwrap();
+        assert_eq!(
+            e.filter.unwrap().step,
+            FilterStep::Index("content".into(), Comp::Text(TextQuery::new("quick brown")))
+        );
+        assert_found!(query!(find in c where content ~ "quick brown"), 1, 2);
+        assert_found!(query!(find in c where content ~ "FOX,"), 1, 4);
+        assert_found!(query!(find in c where content ~ "fox cats"));
+        assert_found!(
+            query!(find in c where content ~ TextQuery::new("cats hound").any()),
+            3,
+            4
+        );
+        assert_found!(query!(find in c where content ~ "the cats"));
+        assert_found!(
+            query!(find in c where content ~ TextQuery::new("the cats").skip_stop_words()),
+            3
+        );
+        assert_eq!(c.count(query!(@filter content ~ "lazy")).unwrap(), 2);
+
+        // the values of field with full-text index can be compared by scanning only
+        assert_found!(query!(find in c where content == "A quick brown dog"), 2);
+
+        // the scanning of field without index
+        assert_found!(query!(find in c where body ~ "quick brown"), 1, 2);
+        assert_found!(
+            query!(find in c where body ~ TextQuery::new("cats hound").any()),
+            3,
+            4
+        );
+
+        // the ordering by relevance
+        let e = c
+            .explain(
+                query!(@filter content ~ TextQuery::new("lazy hound").any()),
+                query!(@order relevance content),
+            )
+            .unwrap();
+        assert_eq!(e.order, OrderPlan::Relevance("content".into()));
+        assert_found!(
+            query!(find in c where content ~ TextQuery::new("lazy hound").any() order relevance content),
+            4,
+            1,
+            3
+        );
+        assert_found!(
+            c.find_page(
+                query!(@filter content ~ TextQuery::new("lazy hound").any()),
+                query!(@order relevance content),
+                Page::new(0, None).after(None, 4)
+            ),
+            1,
+            3
+        );
+        assert!(c.explain(None, query!(@order relevance content)).is_err());
+        assert!(c
+            .explain(query!(@filter body ~ "fox"), query!(@order relevance body))
+            .is_err());
+
+        // the words cannot be ordered
+        assert!(c.explain(None, query!(@order by content)).is_err());
+
+        // the full-text index of non-string keys
+        assert!(c
+            .ensure_index("size", IndexKind::FullText, KeyType::Int)
+            .is_err());
+    }
+
+    #[test]
+    fn geo_search() {
+        let s = test_db("geo_search").unwrap();
+        let c = s.collection("test").unwrap();
+
+        for loc in &[
+            json!({ "lat": 55.7539, "lon": 37.6208 }),
+            json!([37.6186, 55.7601]),
+            json!([37.5307, 55.7033]),
+            json!([-0.1278, 51.5074]),
+            json!([179.9, -16.5]),
+            json!({ "lat": -16.6, "lon": -179.9 }),
+            json!([[37.62, 55.75], [30.31, 59.94]]),
+            json!(null),
+        ] {
+            c.insert(json!({ "loc": loc, "pos": loc })).unwrap();
+        }
+        query!(index for c loc geo).unwrap();
+
+        let center = GeoPoint::new(55.7539, 37.6208);
+
+        // the points within radius
+        let e = c
+            .explain(query!(@filter loc near center, 1000.0), Order::default())
+            .unwrap();
+        assert_eq!(
+            e.filter.unwrap().step,
+            FilterStep::Index("loc".into(), Comp::Near(GeoNear::new(center, 1000.0)))
+        );
+        assert_found!(query!(find in c where loc near center, 1000.0), 1, 2, 7);
+        assert_found!(query!(find in c where loc near center, 10.0), 1);
+
+        // the points within box
+        let moscow = GeoShape::Box(GeoPoint::new(55.0, 37.0), GeoPoint::new(56.0, 38.0));
+        assert_found!(
+            query!(find in c where loc within moscow.clone()),
+            1,
+            2,
+            3,
+            7
+        );
+        let pacific = GeoShape::Box(GeoPoint::new(-17.0, 179.0), GeoPoint::new(-16.0, -179.0));
+        assert_found!(query!(find in c where loc within pacific.clone()), 5, 6);
+
+        // the points within polygon
+        let london = GeoShape::Polygon(vec![
+            GeoPoint::new(51.0, -1.0),
+            GeoPoint::new(52.0, 0.0),
+            GeoPoint::new(51.0, 1.0),
+        ]);
+        assert_found!(query!(find in c where loc within london.clone()), 4);
+
+        // the scanning of field without index
+        assert_found!(query!(find in c where pos near center, 1000.0), 1, 2, 7);
+        assert_found!(query!(find in c where pos within moscow), 1, 2, 3, 7);
+        assert_found!(query!(find in c where pos within pacific), 5, 6);
+        assert_found!(query!(find in c where pos within london), 4);
+
+        // the ordering by distance
+        let e = c
+            .explain(
+                query!(@filter loc near center, 10000.0),
+                query!(@order distance loc from center),
+            )
+            .unwrap();
+        assert_eq!(e.order, OrderPlan::Distance("loc".into()));
+        assert_found!(
+            query!(find in c where loc near center, 10000.0 order distance loc from center),
+            1,
+            7,
+            2,
+            3
+        );
+        assert_found!(
+            c.find_page(
+                None,
+                query!(@order distance loc from center),
+                Page::new(0, Some(4))
+            ),
+            1,
+            7,
+            2,
+            3
+        );
+        assert_found!(
+            c.find_page(
+                None,
+                query!(@order distance loc from center),
+                Page::new(0, Some(2)).after(None, 7)
+            ),
+            2,
+            3
+        );
+        assert!(c
+            .explain(None, query!(@order distance pos from center))
+            .is_err());
+
+        // the keys of geo index
+        assert_eq!(c.distinct("loc", None).unwrap().len(), 8);
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
+    struct VisitDoc {
+        #[document(primary)]
+        id: Option<Primary>,
+
+        #[document(index)]
+        at: SystemTime,
+    }
+
+    #[test]
+    fn timestamp_search() {
+        let s = test_db("timestamp_search").unwrap();
+        let c = s.collection("test").unwrap();
+
+        let tag = |time: &str| Value::Tag(0, Box::new(Value::Text(time.into())));
+        for at in &[
+            to_value(json!(1234567890)).unwrap(),
+            to_value(json!("2009-02-14T01:31:31+02:00")).unwrap(),
+            to_value(json!("2009-02-13")).unwrap(),
+            to_value(json!(1234567890.5)).unwrap(),
+            to_value(UNIX_EPOCH + Duration::from_secs(1234567900)).unwrap(),
+            tag("2009-02-14T00:00:00Z"),
+            to_value(json!("not a date")).unwrap(),
+            Value::Null,
+        ] {
+            let mut doc = BTreeMap::new();
+            doc.insert(Value::Text("at".into()), at.clone());
+            doc.insert(Value::Text("pos".into()), at.clone());
+            c.insert(Value::Map(doc)).unwrap();
+        }
+        query!(index for c at timestamp).unwrap();
+
+        // the comparisons with the keys of any forms
+        let e = c
+            .explain(
+                query!(@filter at > "2009-02-13T23:31:30Z"),
+                Order::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            e.filter.unwrap().step,
+            FilterStep::Index("at".into(), Comp::Gt("2009-02-13T23:31:30Z".into()))
+        );
+        assert_found!(
+            query!(find in c where at > "2009-02-13T23:31:30Z"),
+            2,
+            4,
+            5,
+            6
+        );
+        assert_found!(
+            query!(find in c where at in "2009-02-13".."2009-02-13T23:31:30.5Z"),
+            1,
+            3,
+            4
+        );
+        assert_found!(query!(find in c where at == 1234567890), 1);
+        assert_found!(query!(find in c where at >= 1234567899.5), 5, 6);
+        let time = UNIX_EPOCH + Duration::from_secs(1234567890);
+        assert_found!(query!(find in c where at < time), 3);
+
+        // the scanning of field without index
+        assert_found!(query!(find in c where pos >= time), 1, 2, 4, 5, 6);
+        assert_found!(query!(find in c where pos < time), 3);
+
+        // the ordering by timestamp
+        assert_found!(query!(find in c order by at), 3, 1, 4, 2, 5, 6);
+
+        // the keys of timestamp index
+        let keys = c.distinct("at", None).unwrap();
+        assert_eq!(keys.len(), 6);
+        assert_eq!(keys[0].0, KeyData::Timestamp(1234483200000000));
+        assert_eq!(
+            serde_json::to_value(&keys[0].0).unwrap(),
+            json!("2009-02-13T00:00:00Z")
+        );
+
+        // the timestamp fields of documents
+        assert_eq!(
+            VisitDoc::key_fields(),
+            KeyFields::new().with_field(("at", KeyType::Timestamp, IndexKind::Index))
+        );
+        let c = s.collection("visits").unwrap();
+        c.index::<VisitDoc>().unwrap();
+        for secs in &[30, 10, 20] {
+            c.insert(VisitDoc {
+                id: None,
+                at: UNIX_EPOCH + Duration::from_secs(*secs),
+            })
+            .unwrap();
+        }
+        let time = UNIX_EPOCH + Duration::from_secs(15);
+        assert_found!(query!(find in c where at > time order by at), 3, 1);
+        assert_found!(query!(find in c where at < "1970-01-01T00:00:15Z"), 2);
+    }
+
+    #[test]
+    fn aggregate_documents() {
+        let s = test_db("aggregate_documents").unwrap();
+        let c = s.collection("test").unwrap();
+
+        let fields = Accumulators::new()
+            .with("count", Accumulator::Count)
+            .with("sum", Accumulator::Sum("price".into()))
+            .with("avg", Accumulator::Avg("price".into()))
+            .with("min", Accumulator::Min("price".into()))
+            .with("max", Accumulator::Max("price".into()))
+            .with("first", Accumulator::First("price".into()))
+            .with("last", Accumulator::Last("price".into()));
+
+        // the single group of empty collection
+        assert_eq!(
+            Value::Array(c.aggregate(None, vec![], fields.clone()).unwrap()),
+            to_value(json!([{ "count": 0, "sum": 0, "avg": null, "min": null, "max": null, "first": null, "last": null }])).unwrap()
+        );
+
+        c.insert(json!({ "status": "paid", "price": 10, "tags": ["a", "b"] }))
+            .unwrap();
+        c.insert(json!({ "status": "paid", "price": 2.5 })).unwrap();
+        c.insert(json!({ "status": "new", "price": 4, "tags": ["b"] }))
+            .unwrap();
+        c.insert(json!({ "price": 1 })).unwrap();
+        c.insert(json!({ "status": "new", "price": 6 })).unwrap();
+
+        assert_eq!(
+            Value::Array(c.aggregate(None, vec![], fields.clone()).unwrap()),
+            to_value(json!([{ "count": 5, "sum": 23.5, "avg": 4.7, "min": 1, "max": 10, "first": 10, "last": 6 }])).unwrap()
+        );
+
+        let by_status = to_value(json!([
+            { "status": null, "count": 1, "sum": 1, "avg": 1.0, "min": 1, "max": 1, "first": 1, "last": 1 },
+            { "status": "new", "count": 2, "sum": 10, "avg": 5.0, "min": 4, "max": 6, "first": 4, "last": 6 },
+            { "status": "paid", "count": 2, "sum": 12.5, "avg": 6.25, "min": 2.5, "max": 10, "first": 10, "last": 2.5 },
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            Value::Array(
+                c.aggregate(None, vec!["status".into()], fields.clone())
+                    .unwrap()
+            ),
+            by_status
+        );
+
+        // the documents with several values belongs to several groups
+        assert_eq!(
+            Value::Array(
+                c.aggregate(
+                    None,
+                    vec!["tags".into()],
+                    Accumulators::new().with("count", Accumulator::Count)
+                )
+                .unwrap()
+            ),
+            to_value(json!([
+                { "tags": null, "count": 3 },
+                { "tags": "a", "count": 1 },
+                { "tags": "b", "count": 2 },
+            ]))
+            .unwrap()
+        );
+
+        assert_eq!(
+            Value::Array(
+                c.aggregate(
+                    query!(@filter price > 3),
+                    vec!["status".into(), "tags".into()],
+                    Accumulators::new().with("total", Accumulator::Sum("price".into()))
+                )
+                .unwrap()
+            ),
+            to_value(json!([
+                { "status": "new", "tags": null, "total": 6 },
+                { "status": "new", "tags": "b", "total": 4 },
+                { "status": "paid", "tags": "a", "total": 10 },
+                { "status": "paid", "tags": "b", "total": 10 },
+            ]))
+            .unwrap()
+        );
+
+        // the grouping using index
+        query!(index for c status str, tags str).unwrap();
+
+        assert_eq!(
+            Value::Array(
+                c.aggregate(None, vec!["status".into()], fields.clone())
+                    .unwrap()
+            ),
+            by_status
+        );
+
+        assert_eq!(
+            Value::Array(
+                c.aggregate(
+                    query!(@filter price < 5),
+                    vec!["tags".into()],
+                    Accumulators::new().with("count", Accumulator::Count)
+                )
+                .unwrap()
+            ),
+            to_value(json!([
+                { "tags": null, "count": 2 },
+                { "tags": "b", "count": 1 },
+            ]))
+            .unwrap()
+        );
+
+        // the selected documents are grouped by the keys of its own
+        assert_eq!(
+            Value::Array(
+                c.aggregate(
+                    query!(@filter status == "paid"),
+                    vec!["tags".into()],
+                    Accumulators::new()
+                        .with("count", Accumulator::Count)
+                        .with("first", Accumulator::First("price".into()))
+                )
+                .unwrap()
+            ),
+            to_value(json!([
+                { "tags": null, "count": 1, "first": 2.5 },
+                { "tags": "a", "count": 1, "first": 10 },
+                { "tags": "b", "count": 1, "first": 10 },
+            ]))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn distinct_facets() {
+        let s = test_db("distinct_facets").unwrap();
+        let c = s.collection("test").unwrap();
+
+        fill_plan(&c).unwrap();
+
+        assert_eq!(
+            c.distinct("m", None).unwrap(),
+            (0..10).map(|m| (KeyData::Int(m), 20)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            c.distinct("m", query!(@filter n < 15)).unwrap(),
+            (0..10)
+                .map(|m| (KeyData::Int(m), if m < 5 { 2 } else { 1 }))
+                .collect::<Vec<_>>()
+        );
+        // the inverted selection
+        assert_eq!(
+            c.distinct("m", query!(@filter !(m == 3))).unwrap(),
+            (0..10)
+                .filter(|m| *m != 3)
+                .map(|m| (KeyData::Int(m), 20))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            c.distinct("n", query!(@filter n < 3)).unwrap(),
+            vec![
+                (KeyData::Int(0), 1),
+                (KeyData::Int(1), 1),
+                (KeyData::Int(2), 1)
+            ]
+        );
+
+        // the documents with several keys counted for each key
+        assert_eq!(
+            c.facets("a", None, Some(5)).unwrap(),
+            vec![
+                (KeyData::Int(0), 63),
+                (KeyData::Int(1), 63),
+                (KeyData::Int(2), 63),
+                (KeyData::Int(3), 63),
+                (KeyData::Int(4), 62)
+            ]
+        );
+        assert_eq!(
+            c.facets("a", query!(@filter m == 0), None).unwrap(),
+            vec![
+                (KeyData::Int(0), 20),
+                (KeyData::Int(1), 3),
+                (KeyData::Int(2), 3),
+                (KeyData::Int(3), 3),
+                (KeyData::Int(5), 3),
+                (KeyData::Int(6), 3),
+                (KeyData::Int(4), 2),
+            ]
+        );
+
+        // the fields without index
+        assert!(c.distinct("b", None).is_err());
+        assert!(c.facets("b", None, None).is_err());
+    }
+
+    #[test]
+    fn count_documents() {
+        let s = test_db("count_documents").unwrap();
+        let c = s.collection("test").unwrap();
+
+        assert_eq!(c.count(None).unwrap(), 0);
+
+        fill_plan(&c).unwrap();
+
+        assert_eq!(c.count(None).unwrap(), 200);
+        assert_eq!(c.count(query!(@filter m == 3)).unwrap(), 20);
+        assert_eq!(c.count(query!(@filter m == 30)).unwrap(), 0);
+        assert_eq!(c.count(query!(@filter n == 30)).unwrap(), 1);
+        assert_eq!(c.count(query!(@filter !(m == 3))).unwrap(), 180);
+        // the document which has same key twice counted once
+        assert_eq!(c.count(query!(@filter a == 0)).unwrap(), 63);
+        assert_eq!(c.count(query!(@filter a of [0])).unwrap(), 63);
+        // the integer index cannot count the timestamps
+        let time = UNIX_EPOCH + Duration::from_millis(3500);
+        assert_eq!(c.count(query!(@filter m == time)).unwrap(), 0);
+
+        for filter in [
+            query!(@filter a of [0, 1]),
+            query!(@filter n > 150),
+            query!(@filter m == 3 && n < 100),
+            query!(@filter m == 3 || a == 4),
+            query!(@filter !(a of [2, 3])),
+            query!(@filter x == 1),
+            query!(@filter !(x == 1)),
+        ] {
+            assert_eq!(
+                c.count(filter.clone()).unwrap(),
+                c.find_ids(filter).unwrap().len()
+            );
+        }
+
+        let s = s.snapshot().unwrap();
+        let c = s.collection("test").unwrap();
+        assert_eq!(c.count(query!(@filter m == 3)).unwrap(), 20);
+    }
+
+    #[test]
+    fn remove_eq_str() {
+        let s = test_db("remove_eq_str").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        assert_found!(query!(find in c where s == "def"), 2);
+        assert_eq!(query!(remove from c where s == "def").unwrap(), 1);
+        assert_found!(query!(find in c where s == "def"));
+        assert_found!(query!(find in c where s == "abc"), 1);
+    }
+
+    #[test]
+    fn remove_ge_int() {
+        let s = test_db("remove_ge_int").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        assert_found!(query!(find in c where i >= 4), 3, 4, 6);
+        assert_eq!(query!(remove from c where i >= 4).unwrap(), 3);
+        assert_found!(query!(find in c where i >= 4));
+        assert_found!(query!(find in c where i >= 2), 2, 5);
+    }
+
+    #[test]
+    fn update_set_eq_str() {
+        let s = test_db("update_set_eq_str").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        assert_found!(query!(find in c where s == "def"), 2);
+        assert_eq!(
+            query!(update in c modify s = "klm" where s == "def").unwrap(),
+            1
+        );
+        assert_found!(query!(find in c where s == "def"));
+        assert_found!(query!(find in c where s == "abc"), 1);
+        assert_found!(query!(find in c where s == "klm"), 2);
+    }
+
+    #[test]
+    fn update_remove_interleaved() {
+        let s = test_db("update_remove_interleaved").unwrap();
+        let c = s.collection("test").unwrap();
+
+        query!(index for c state str, n int).unwrap();
+
+        for _ in 0..100 {
+            query!(insert into c { "state": "free", "n": 0 }).unwrap();
+        }
+
+        let run = |f: fn(&Collection) -> usize| {
+            (0..8)
+                .map(|_| {
+                    let c = c.clone();
+                    std::thread::spawn(move || f(&c))
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|t| t.join().unwrap())
+                .sum::<usize>()
+        };
+
+        assert_eq!(
+            run(
+                |c| query!(update in c modify state = "taken", n += 1 where state == "free")
+                    .unwrap()
+            ),
+            100
+        );
+        assert_eq!(c.find_ids(query!(@filter n == 1)).unwrap().len(), 100);
+        assert_eq!(
+            run(|c| query!(remove from c where state == "taken").unwrap()),
+            100
+        );
+        assert_eq!(c.last_id().unwrap(), 0);
+    }
+
+    #[test]
+    fn transaction_commit() {
+        let s = test_db("transaction_commit").unwrap();
+        let c = s.collection("test").unwrap();
+        let a = s.collection("audit").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        let id = s
+            .transaction(|txn| {
+                let c = txn.collection("test")?;
+                let a = txn.collection("audit")?;
+
+                c.update(query!(@filter s == "def"), query!(@modify s = "klm"))?;
+                assert_eq!(c.find_ids(query!(@filter s == "klm"))?.len(), 1);
+                assert_eq!(c.remove(query!(@filter s == "abc"))?, 1);
+                assert!(!c.has(1)?);
+                c.insert(json!({ "s": "new" }))?;
+
+                a.insert(json!({ "op": "change" }))
+            })
+            .unwrap();
+
+        assert_eq!(id, 1);
+        assert_found!(query!(find in c where s == "klm"), 2);
+        assert_found!(query!(find in c where s == "abc"));
+        assert_found!(query!(find in c where s == "new"), 8);
+        assert_eq!(a.last_id().unwrap(), 1);
+    }
+
+    #[test]
+    fn transaction_abort() {
+        let s = test_db("transaction_abort").unwrap();
+        let c = s.collection("test").unwrap();
+        let a = s.collection("audit").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        let res: Result<()> = s.transaction(|txn| {
+            let c = txn.collection("test")?;
+            let a = txn.collection("audit")?;
+
+            c.update(query!(@filter s == "def"), query!(@modify s = "klm"))?;
+            assert_found!(c.find(query!(@filter s == "klm"), query!(@order)), 2);
+            a.insert(json!({ "op": "change" }))?;
+            // violates unique index
+            c.insert(json!({ "s": "abc" }))?;
+
+            Ok(())
+        });
+
+        assert!(res.is_err());
+        assert_found!(query!(find in c where s == "def"), 2);
+        assert_found!(query!(find in c where s == "klm"));
+        assert_eq!(c.last_id().unwrap(), 7);
+        assert_eq!(a.last_id().unwrap(), 0);
+
+        assert!(s
+            .transaction(|txn| txn.collection("missing").map(|_| ()))
+            .is_err());
+    }
+
+    #[test]
+    fn snapshot_consistent() {
+        let s = test_db("snapshot_consistent").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        let snap = s.snapshot().unwrap();
+        let sc = snap.collection("test").unwrap();
+
+        let docs = sc.find::<Value>(None, query!(@order)).unwrap();
+
+        {
+            let c = c.clone();
+            std::thread::spawn(move || {
+                query!(remove from c where s == "abc").unwrap();
+                query!(update in c modify s = "klm" where s == "def").unwrap();
+                query!(insert into c { "s": "new" }).unwrap();
+            })
+            .join()
+            .unwrap();
+        }
+
+        assert_eq!(docs.len(), 7);
+        assert_eq!(docs.count(), 7);
+        assert!(sc.has(1).unwrap());
+        assert_eq!(&sc.get::<Doc>(2).unwrap().unwrap().s, "def");
+        assert_eq!(sc.find_ids(query!(@filter s == "klm")).unwrap().len(), 0);
+        assert_found!(sc.find(query!(@filter s == "def"), query!(@order)), 2);
+        assert_eq!(sc.last_id().unwrap(), 7);
+
+        drop(sc);
+        drop(snap);
+
+        assert!(!c.has(1).unwrap());
+        assert_found!(query!(find in c where s == "klm"), 2);
+        assert_eq!(c.last_id().unwrap(), 8);
+    }
+
+    fn fill_bulk(c: &Collection, n: usize) -> Result<()> {
+        let text = "lorem ipsum ".repeat(100);
+        for i in 0..n {
+            c.insert(json!({ "n": i, "text": text }))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn map_grow_when_full() {
+        let s = test_db_with(
+            "map_grow_when_full",
+            serde_json::from_value(json!({
+                "map_size": 256 << 10,
+                "map_grow_factor": 1.5,
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        let c = s.collection("test").unwrap();
+
+        c.create_index("n", IndexKind::Unique, KeyType::Int)
+            .unwrap();
+
+        let usage = s.get_usage().unwrap();
+        assert_eq!(usage.map_size, 256 << 10);
+        assert_eq!(usage.map_size_limit, None);
+
+        fill_bulk(&c, 1000).unwrap();
+
+        // the transaction fails when the map is full, but it can be retried after growth
+        let mut calls = 0;
+        loop {
+            calls += 1;
+            let res = s.transaction(|txn| {
+                let c = txn.collection("test")?;
+                for i in 1000..2000 {
+                    c.insert(json!({ "n": i, "text": "lorem ipsum ".repeat(100) }))?;
+                }
+                Ok(())
+            });
+            match res {
+                Ok(()) => break,
+                Err(Error::DbError(_)) if calls < 10 => (),
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+        assert!(calls > 1);
+
+        let usage = s.get_usage().unwrap();
+        assert!(usage.map_size > 2 << 20);
+        assert!(usage.used_size <= usage.map_size);
+        assert_eq!(c.find_ids(None).unwrap().len(), 2000);
+        assert_eq!(c.find_ids(query!(@filter n >= 1990)).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn map_grow_limit() {
+        let s = test_db_with(
+            "map_grow_limit",
+            serde_json::from_value(json!({
+                "map_size": 256 << 10,
+                "map_grow_factor": 2.0,
+                "map_size_limit": 1 << 20,
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        let c = s.collection("test").unwrap();
+
+        match fill_bulk(&c, 1000) {
+            Err(Error::DbError(_)) => (),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let usage = s.get_usage().unwrap();
+        assert_eq!(usage.map_size, 1 << 20);
+        assert_eq!(usage.map_size_limit, Some(1 << 20));
+        assert!(c.sequence().unwrap() > 200);
+    }
+
+    #[test]
+    fn map_full_without_grow() {
+        let s = test_db_with(
+            "map_full_without_grow",
+            serde_json::from_value(json!({ "map_size": 256 << 10 })).unwrap(),
+        )
+        .unwrap();
+        let c = s.collection("test").unwrap();
+
+        assert!(fill_bulk(&c, 1000).is_err());
+
+        let usage = s.get_usage().unwrap();
+        assert_eq!(usage.map_size, 256 << 10);
+        assert_eq!(usage.map_size_limit, Some(256 << 10));
+    }
+
+    #[test]
+    fn backup_copy() {
+        let s = test_db("backup_copy").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        let paths = [
+            (test_path("backup_copy_plain"), false),
+            (test_path("backup_copy_compact"), true),
+        ];
+
+        for (path, compact) in &paths {
+            s.backup(path, *compact).unwrap();
+
+            let b = Storage::new(path, Options::default()).unwrap();
+            let c = b.collection("test").unwrap();
+
+            assert_eq!(
+                c.get_indexes().unwrap(),
+                s.collection("test").unwrap().get_indexes().unwrap()
+            );
+            assert_found!(query!(find in c where s == "def"), 2);
+            assert_eq!(c.last_id().unwrap(), 7);
+        }
+
+        // the target already contains storage
+        assert!(s.backup(&paths[0].0, false).is_err());
+    }
+
+    #[test]
+    fn backup_compact_stream() {
+        let s = test_db("backup_compact_stream").unwrap();
+        let c = s.collection("test").unwrap();
+
+        c.create_index("n", IndexKind::Unique, KeyType::Int)
+            .unwrap();
+        fill_bulk(&c, 500).unwrap();
+        assert_eq!(c.remove(query!(@filter n >= 10)).unwrap(), 490);
+
+        let plain = test_path("backup_compact_stream_plain");
+        let compact = test_path("backup_compact_stream_compact");
+        s.backup(&plain, false).unwrap();
+        s.backup(&compact, true).unwrap();
+
+        let data_size = |path: &std::path::Path| path.join("data.mdb").metadata().unwrap().len();
+        assert!(data_size(&compact) * 4 < data_size(&plain));
+
+        let mut data = Vec::new();
+        let size = s.backup_to(&mut data, true).unwrap();
+        assert_eq!(size, data.len() as u64);
+        assert_eq!(size, data_size(&compact));
+
+        let path = test_path("backup_compact_stream_data");
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("data.mdb"), &data).unwrap();
+
+        let b = Storage::new(&path, Options::default()).unwrap();
+        let c = b.collection("test").unwrap();
+        assert_eq!(c.find_ids(None).unwrap().len(), 10);
+        assert_eq!(c.sequence().unwrap(), 500);
+    }
+
+    #[test]
+    fn export_import() {
+        let s = test_db("export_import").unwrap();
+        let c = s.collection("test").unwrap();
+        let e = s.collection_with_key("event", PrimaryType::Uuid).unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+        c.delete(7).unwrap();
+        c.reset_sequence(10).unwrap();
+
+        let id = e.insert(json!({ "kind": "start" })).unwrap();
+
+        let mut data = Vec::new();
+        assert_eq!(s.export(&mut data).unwrap(), 7);
+
+        let t = test_db("export_import_target").unwrap();
+        assert_eq!(t.import(data.as_slice()).unwrap(), 7);
+
+        let mut names = t.get_collections().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["event", "test"]);
+
+        let tc = t.collection("test").unwrap();
+        assert_eq!(tc.get_indexes().unwrap(), c.get_indexes().unwrap());
+        assert_eq!(tc.sequence().unwrap(), 10);
+        assert_found!(query!(find in tc where s == "def"), 2);
+        assert_eq!(tc.get::<Doc>(3).unwrap(), c.get::<Doc>(3).unwrap());
+        assert_eq!(tc.insert(json!({ "s": "new" })).unwrap(), 11);
+
+        let te = t.collection("event").unwrap();
+        assert_eq!(te.primary_type(), PrimaryType::Uuid);
+        assert!(te.has(&id).unwrap());
+        assert_eq!(te.get::<Value>(&id).unwrap(), e.get::<Value>(&id).unwrap());
+
+        // the collections already exists
+        assert!(t.import(data.as_slice()).is_err());
+
+        // the archive is truncated
+        let u = test_db("export_import_truncated").unwrap();
+        assert!(u.import(&data[..data.len() - 4]).is_err());
+        assert!(u.get_collections().unwrap().is_empty());
+        assert_eq!(u.import(data.as_slice()).unwrap(), 7);
+    }
+
+    #[test]
+    fn rename_collection() {
+        let s = test_db("rename_collection").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+        c.reset_sequence(10).unwrap();
+
+        let indexes = c.get_indexes().unwrap();
+        drop(c);
+
+        s.rename_collection("test", "renamed").unwrap();
+
+        assert!(!s.has_collection("test").unwrap());
+        assert_eq!(s.get_collections().unwrap(), vec!["renamed"]);
+
+        let c = s.collection("renamed").unwrap();
+        assert_eq!(c.get_indexes().unwrap(), indexes);
+        assert_eq!(c.sequence().unwrap(), 10);
+        assert_found!(query!(find in c where s == "def"), 2);
+        assert_found!(query!(find in c where n.i >= 2), 3, 4, 5);
+
+        assert!(s.rename_collection("test", "other").is_err());
+        s.collection("other").unwrap();
+        assert!(s.rename_collection("renamed", "other").is_err());
+        assert!(s.has_collection("renamed").unwrap());
+    }
+
+    #[test]
+    fn copy_collection() {
+        let s = test_db("copy_collection").unwrap();
+        let c = s.collection_with_key("test", PrimaryType::U64).unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        s.copy_collection("test", "copy").unwrap();
+
+        let d = s.collection("copy").unwrap();
+        assert_eq!(d.primary_type(), PrimaryType::U64);
+        assert_eq!(d.get_indexes().unwrap(), c.get_indexes().unwrap());
+        assert_eq!(d.sequence().unwrap(), 7);
+
+        assert_eq!(d.remove(query!(@filter s == "def")).unwrap(), 1);
+        assert_found!(query!(find in d where s == "def"));
+        assert_found!(query!(find in c where s == "def"), 2);
+        assert_eq!(d.insert(json!({ "s": "new" })).unwrap(), 8);
+        assert_found!(query!(find in c where s == "new"));
+
+        assert!(s.copy_collection("test", "copy").is_err());
+        assert!(s.copy_collection("missing", "other").is_err());
+        assert!(!s.has_collection("other").unwrap());
+    }
+
+    #[test]
+    fn describe_storage() {
+        let s = test_db("describe_storage").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        let d = c.describe().unwrap();
+        assert_eq!(d.name, "test");
+        assert_eq!(d.primary, PrimaryType::U32);
+        assert_eq!(d.sequence, 7);
+        assert_eq!(d.documents, 7);
+        assert!(d.created.is_some());
+        assert_eq!(d.indexes.len(), 6);
+
+        let i = d.indexes.iter().find(|i| i.path == "s").unwrap();
+        assert_eq!((i.kind, i.key), (IndexKind::Unique, KeyType::String));
+        assert_eq!(i.entries, 7);
+        assert!(i.created.is_some());
+
+        c.drop_index("s").unwrap();
+        s.collection("other").unwrap();
+
+        let d = s.describe().unwrap();
+        assert_eq!(d.version, 1);
+        assert!(d.created.is_some());
+        assert_eq!(d.collections.len(), 2);
+        assert_eq!(d.collections[0].indexes.len(), 5);
+        assert_eq!(d.collections[1].name, "other");
+        assert_eq!(d.collections[1].documents, 0);
+
+        s.drop_collection("other").unwrap();
+        assert_eq!(s.describe().unwrap().collections.len(), 1);
+    }
+
+    #[test]
+    fn catalog_migration() {
+        let s = test_db("catalog_migration").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        // turn the storage into legacy format which keeps definitions in database names only
+        s.write(|txn| txn.access().clear_db(s.catalog().unwrap()).wrap_err())
+            .unwrap();
+
+        let path = test_path("catalog_migration_legacy");
+        s.backup(&path, false).unwrap();
+
+        let m = Storage::new(&path, Options::default()).unwrap();
+        let d = m.describe().unwrap();
+        assert_eq!(d.version, 1);
+        assert_eq!(d.created, None);
+        assert_eq!(d.collections.len(), 1);
+
+        let c = m.collection("test").unwrap();
+        assert_eq!(
+            c.get_indexes().unwrap(),
+            s.collection("test").unwrap().get_indexes().unwrap()
+        );
+        assert_eq!(c.sequence().unwrap(), 7);
+        assert_found!(query!(find in c where s == "def"), 2);
+        assert_eq!(c.describe().unwrap().created, None);
+    }
+
+    #[test]
+    fn catalog_read_only() {
+        let s = test_db("catalog_read_only").unwrap();
+        let c = s.collection("test").unwrap();
+
+        mk_index(&c).unwrap();
+        fill_data(&c).unwrap();
+
+        let path = test_path("catalog_read_only_legacy");
+        s.backup(&path, false).unwrap();
+
+        // turn the storage into legacy format which has no catalog at all
+        {
+            let mut bld = lmdb::EnvBuilder::new().unwrap();
+            bld.set_maxdbs(128).unwrap();
+            let env =
+                unsafe { bld.open(path.to_str().unwrap(), lmdb::open::Flags::empty(), 0o600) }
+                    .unwrap();
+            let name = ron::ser::to_string(&DatabaseDef::Catalog).unwrap();
+            lmdb::Database::open(&env, Some(&name), &lmdb::DatabaseOptions::defaults())
+                .unwrap()
+                .delete()
+                .unwrap();
+        }
+
+        let m = Storage::new(
+            &path,
+            serde_json::from_value(json!({ "read_only": true })).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(m.get_collections().unwrap(), vec!["test"]);
+
+        let c = m.collection("test").unwrap();
+        assert_eq!(
+            c.get_indexes().unwrap(),
+            s.collection("test").unwrap().get_indexes().unwrap()
+        );
+        assert_found!(query!(find in c where s == "def"), 2);
+        assert_eq!(m.describe().unwrap().collections[0].documents, 7);
+
+        // the storage is not modified
+        assert!(c.insert(json!({ "s": "new" })).is_err());
+    }
+
+    // derive
+    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
+    struct RootDoc {
+        #[document(primary)]
+        id: Option<Primary>,
+
+        #[document(unique)]
+        title: String,
+
+        #[document(nested)]
+        meta: MetaData,
+
+        #[document(nested)]
+        links: Vec<LinkData>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
+    #[document(nested)]
+    struct MetaData {
+        #[document(index)]
+        keywords: Vec<String>,
+
+        description: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
+    #[document(nested)]
+    struct LinkData {
+        #[document(index)]
+        title: Vec<String>,
+
+        url: String,
+
+        #[document(nested)]
+        meta: MetaData,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
+    struct EventDoc {
+        #[document(primary ulid)]
+        id: Option<String>,
+
+        #[document(index)]
+        kind: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Document)]
+    #[document(index(kind, time))]
+    struct LogDoc {
+        #[document(primary)]
+        id: Option<Primary>,
+
+        kind: String,
+
+        #[serde(rename = "t")]
+        time: i64,
+    }
+
+    #[test]
+    fn derive_primary_type() {
+        assert_eq!(RootDoc::primary_type(), PrimaryType::U32);
+        assert_eq!(EventDoc::primary_type(), PrimaryType::Ulid);
+
+        let s = test_db("derive_primary_type").unwrap();
+        let c = s
+            .collection_with_key("test", EventDoc::primary_type())
+            .unwrap();
+        c.index::<EventDoc>().unwrap();
+
+        let id = c
+            .insert(EventDoc {
+                id: None,
+                kind: "start".into(),
+            })
+            .unwrap();
+        assert_eq!(id.as_str().unwrap().len(), 26);
+
+        let doc = c.get::<EventDoc>(&id).unwrap().unwrap();
+        assert_eq!(doc.id.as_ref(), id.as_str().map(String::from).as_ref());
+        assert_eq!(&doc.kind, "start");
+    }
+
+    #[test]
+    fn derive_primary() {
+        assert_eq!(MetaData::primary_field().as_ref(), "$");
+        assert_eq!(LinkData::primary_field().as_ref(), "$");
+        assert_eq!(RootDoc::primary_field().as_ref(), "id");
+    }
+
+    #[test]
+    fn derive_key_fields() {
+        assert_eq!(
+            MetaData::key_fields(),
+            KeyFields::new().with_field(("keywords", KeyType::String, IndexKind::Index))
+        );
+
+        assert_eq!(
+            LinkData::key_fields(),
+            KeyFields::new()
+                .with_field(("title", KeyType::String, IndexKind::Index))
+                .with_field(("meta.keywords", KeyType::String, IndexKind::Index))
+        );
+
+        assert_eq!(
+            RootDoc::key_fields(),
+            KeyFields::new()
+                .with_field(("title", KeyType::String, IndexKind::Unique))
+                .with_field(("meta.keywords", KeyType::String, IndexKind::Index))
+                .with_field(("links.title", KeyType::String, IndexKind::Index))
+                .with_field(("links.meta.keywords", KeyType::String, IndexKind::Index))
+        );
+    }
+
+    #[test]
+    fn derive_compound_index() {
+        assert_eq!(
+            LogDoc::key_fields(),
+            KeyFields::new().with_field(KeyField::compound(vec![
+                ("kind", KeyType::String),
+                ("t", KeyType::Int)
+            ]))
+        );
+
+        let s = test_db("derive_compound_index").unwrap();
+        let c = s.collection("test").unwrap();
+        c.index::<LogDoc>().unwrap();
+
+        for (kind, time) in &[("start", 3), ("stop", 2), ("start", 1)] {
+            c.insert(LogDoc {
+                id: None,
+                kind: kind.to_string(),
+                time: *time,
+            })
+            .unwrap();
+        }
+        assert_found!(query!(find in c where kind == "start" && t < 3), 3);
+        assert_found!(query!(find in c order by kind asc, t asc), 3, 1, 2);
     }
 
     #[test]
@@ -0,0 +1,136 @@
+use std::{collections::BTreeMap, result::Result as StdResult};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{extract_field_list, Identifier, KeyData, Primary, Value};
+
+/// Lookup of referenced documents
+///
+/// The documents of other collection which referenced by the values of local field will be embedded into the found documents. The references is the primary keys of documents, or the keys of indexed field of them when the foreign field is specified.
+///
+/// The found documents will be embedded as an array, which is empty when no documents referenced.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lookup {
+    /// The collection of referenced documents
+    #[serde(deserialize_with = "identifier")]
+    pub from: Identifier,
+    /// The field which contains the references
+    #[serde(deserialize_with = "identifier")]
+    pub local: Identifier,
+    /// The indexed field of referenced documents (primary key when missing)
+    #[serde(default, deserialize_with = "opt_identifier")]
+    pub foreign: Option<Identifier>,
+    /// The field to embed the referenced documents into
+    #[serde(deserialize_with = "identifier")]
+    pub path: Identifier,
+}
+
+fn identifier<'de, D: Deserializer<'de>>(deserializer: D) -> StdResult<Identifier, D::Error> {
+    String::deserialize(deserializer).map(Identifier::from)
+}
+
+fn opt_identifier<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> StdResult<Option<Identifier>, D::Error> {
+    Option::<String>::deserialize(deserializer).map(|field| field.map(Identifier::from))
+}
+
+impl Lookup {
+    /// Create lookup of documents by primary keys
+    pub fn new<C, L, P>(from: C, local: L, path: P) -> Self
+    where
+        C: Into<Identifier>,
+        L: Into<Identifier>,
+        P: Into<Identifier>,
+    {
+        Self {
+            from: from.into(),
+            local: local.into(),
+            foreign: None,
+            path: path.into(),
+        }
+    }
+
+    /// Lookup documents by the keys of indexed field instead of primary keys
+    pub fn foreign<F: Into<Identifier>>(mut self, field: F) -> Self {
+        self.foreign = Some(field.into());
+        self
+    }
+
+    /// Get the references of document
+    pub(crate) fn references(&self, doc: &Value) -> Vec<KeyData> {
+        let mut keys = Vec::new();
+        for key in extract_field_list(doc, &self.local) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// Embed the referenced documents into document
+    pub(crate) fn embed(&self, doc: Value, docs: Vec<Value>) -> Value {
+        let path = self.path.split('.').collect::<Vec<_>>();
+        embed_field(doc, &path, Value::Array(docs))
+    }
+}
+
+/// Convert the key of reference to primary key
+pub(crate) fn reference_id(key: KeyData) -> Option<Primary> {
+    match key {
+        KeyData::Int(id) if id > 0 => Some(Primary::Int(id as u64)),
+        KeyData::String(id) => Some(Primary::String(id)),
+        _ => None,
+    }
+}
+
+/// Put the value into the field of document
+///
+/// The missing objects on path will be created.
+///
+fn embed_field(doc: Value, path: &[&str], val: Value) -> Value {
+    if path.is_empty() {
+        return val;
+    }
+
+    let mut map = if let Value::Map(map) = doc {
+        map
+    } else {
+        BTreeMap::new()
+    };
+
+    let name = Value::Text(path[0].into());
+    let doc = map.remove(&name).unwrap_or(Value::Null);
+    map.insert(name, embed_field(doc, &path[1..], val));
+
+    Value::Map(map)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lookup;
+    use serde_json::{from_str, json, to_string, Value};
+
+    #[test]
+    fn parse_lookup() {
+        test_parse!(
+            Lookup,
+            json!({ "from": "users", "local": "author_id", "path": "author" }),
+            Lookup::new("users", "author_id", "author")
+        );
+        test_parse!(
+            Lookup,
+            json!({ "from": "tags", "local": "tags", "foreign": "name", "path": "meta.tags" }),
+            Lookup::new("tags", "tags", "meta.tags").foreign("name")
+        );
+    }
+
+    #[test]
+    fn build_lookup() {
+        test_build!(
+            Lookup::new("users", "author_id", "author").foreign("login"),
+            json!({ "from": "users", "local": "author_id", "foreign": "login", "path": "author" })
+        );
+    }
+}
@@ -24,6 +24,13 @@
 ///         ).is_ok()
 ///     );
 ///
+///     // ensure compound index over several fields
+///     assert!(
+///         query!(index for my_collection
+///             (field str, other_field int) unique,
+///         ).is_ok()
+///     );
+///
 ///     // find query
 ///     assert!(
 ///         query!(find in my_collection where field == "abc").is_ok()
@@ -34,11 +41,36 @@
 ///         query!(find in my_collection where field == "abc" order by other.field).is_ok()
 ///     );
 ///
+///     // find query with ordering by several fields
+///     assert!(
+///         query!(find in my_collection where field == "abc" order by other.field desc, field asc).is_ok()
+///     );
+///
+///     // find query using full-text search with ordering by relevance
+///     assert!(
+///         query!(find in my_collection where text ~ "quick fox" order relevance text).is_ok()
+///     );
+///
+///     // find query of geo points within radius with ordering by distance
+///     assert!(
+///         query!(find in my_collection where place near GeoPoint::new(55.75, 37.62), 1000.0 order distance place from GeoPoint::new(55.75, 37.62)).is_ok()
+///     );
+///
 ///     // find query with result document type with descending ordering by primary key
 ///     assert!(
 ///         query!(find MyDoc in my_collection where field == "abc" order ^).is_ok()
 ///     );
 ///
+///     // find query with projection of the specified fields only
+///     assert!(
+///         query!(find in my_collection where field == "abc" order desc project field, other.field).is_ok()
+///     );
+///
+///     // find query with projection which removes fields
+///     assert!(
+///         query!(find in my_collection project -field, -other.*.subfield).is_ok()
+///     );
+///
 ///     // update query
 ///     assert!(
 ///         query!(update in my_collection modify field = "def" where [field == "abc"]).is_ok()
@@ -66,6 +98,7 @@ macro_rules! _query_native {
     (@index $coll:expr, [ $($indexes:tt),+ ]) => ( $coll.set_indexes(&[$($indexes),+]) );
     (@index $coll:expr, $($indexes:tt)+) => ( $coll.set_indexes($($indexes)+) );
     (@find $type:tt, $coll:expr, $filter:expr, $order:expr) => ( $coll.find::<$type>($filter, $order) );
+    (@find $type:tt, $coll:expr, $filter:expr, $order:expr, $project:expr) => ( $coll.find::<$type>($filter, $order).map(|docs| docs.project($project)) );
     (@insert $coll:expr, $doc:expr) => ( $coll.insert(&$doc) );
     (@update $coll:expr, $filter:expr, $modify:expr) => ( $coll.update($filter, $modify) );
     (@remove $coll:expr, $filter:expr) => ( $coll.remove($filter) );
@@ -108,9 +141,45 @@ macro_rules! _query_impl {
     (@index $args:tt, $($tokens:tt)+) => (
         _query_impl!(@index_list $args, [], $($tokens)+)
     );
-    // index field tuple
+    // index field
     (@index_field [ $($field:tt)+ ], $type:ident, $kind:ident) => (
-        (_query_impl!(@field $($field)+), _query_impl!(@key_type $type), _query_impl!(@index_kind $kind))
+        $crate::KeyField::from((_query_impl!(@field $($field)+), _query_impl!(@key_type $type), _query_impl!(@index_kind $kind)))
+    );
+    // compound index start
+    (@index_list $args:tt, $index:tt, ( $($fields:tt)+ ) $($tokens:tt)*) => (
+        _query_impl!(@index_list_compound $args, $index, ( $($fields)+ ), $($tokens)*)
+    );
+    // compound index kind parse
+    (@index_list_compound $args:tt, [ $($index:tt)* ], $fields:tt, index $($tokens:tt)*) => (
+        _query_impl!(@index_list_next $args, [ $($index)* {_query_impl!(@index_compound $fields, index)} ], $($tokens)*)
+    );
+    (@index_list_compound $args:tt, [ $($index:tt)* ], $fields:tt, unique $($tokens:tt)*) => (
+        _query_impl!(@index_list_next $args, [ $($index)* {_query_impl!(@index_compound $fields, unique)} ], $($tokens)*)
+    );
+    (@index_list_compound $args:tt, [ $($index:tt)* ], $fields:tt, $($tokens:tt)*) => (
+        _query_impl!(@index_list_next $args, [ $($index)* {_query_impl!(@index_compound $fields, index)} ], $($tokens)*)
+    );
+    // compound index
+    (@index_compound ( $($fields:tt)+ ), $kind:ident) => (
+        _query_impl!(@index_compound_field [], [], $($fields)+).with_kind(_query_impl!(@index_kind $kind))
+    );
+    // compound index field parse
+    (@index_compound_field $out:tt, [], $field:ident $($tokens:tt)*) => (
+        _query_impl!(@index_compound_field $out, [ $field ], $($tokens)*)
+    );
+    (@index_compound_field $out:tt, [ $($path:tt)+ ], . * $($tokens:tt)*) => (
+        _query_impl!(@index_compound_field $out, [ $($path)+ . * ], $($tokens)*)
+    );
+    (@index_compound_field $out:tt, [ $($path:tt)+ ], . $field:ident $($tokens:tt)*) => (
+        _query_impl!(@index_compound_field $out, [ $($path)+ . $field ], $($tokens)*)
+    );
+    // compound index field out
+    (@index_compound_field [ $($out:tt)* ], [ $($path:tt)+ ], $type:ident $(, $($tokens:tt)*)?) => (
+        _query_impl!(@index_compound_field [ $($out)* (_query_impl!(@field $($path)+), _query_impl!(@key_type $type)) ], [], $($($tokens)*)?)
+    );
+    // end compound index fields
+    (@index_compound_field [ $($out:tt)+ ], [], ) => (
+        $crate::KeyField::compound(_query_impl!(@vec $($out),+))
     );
     // index field start
     (@index_list $args:tt, [ $($index:tt)* ], $field:ident $($tokens:tt)*) => (
@@ -137,6 +206,9 @@ macro_rules! _query_impl {
     (@index_list_kind $args:tt, $index:tt, $path:tt, $type:ident, unique $($tokens:tt)*) => (
         _query_impl!(@index_list_out $args, $index, $path, $type, unique, $($tokens)*)
     );
+    (@index_list_kind $args:tt, $index:tt, $path:tt, $type:ident, fulltext $($tokens:tt)*) => (
+        _query_impl!(@index_list_out $args, $index, $path, $type, fulltext, $($tokens)*)
+    );
     (@index_list_kind $args:tt, $index:tt, $path:tt, $type:ident, $($tokens:tt)*) => (
         _query_impl!(@index_list_out $args, $index, $path, $type, index, $($tokens)*)
     );
@@ -163,6 +235,7 @@ macro_rules! _query_impl {
     // index kinds
     (@index_kind index) => ( $crate::IndexKind::Index );
     (@index_kind unique) => ( $crate::IndexKind::Unique );
+    (@index_kind fulltext) => ( $crate::IndexKind::FullText );
     // key types
     (@key_type integer) => ( $crate::KeyType::Int );
     (@key_type int) => ( $crate::KeyType::Int );
@@ -175,53 +248,49 @@ macro_rules! _query_impl {
     (@key_type binary) => ( $crate::KeyType::Binary );
     (@key_type bin) => ( $crate::KeyType::Binary );
     (@key_type bytes) => ( $crate::KeyType::Binary );
+    (@key_type geo) => ( $crate::KeyType::Geo );
+    (@key_type timestamp) => ( $crate::KeyType::Timestamp );
 
     // find query
     (@find $args:tt,) => (
-        _query_impl!(@find_impl $args, [], [])
+        _query_impl!(@find_impl $args, [], [], [])
     );
-    (@find $args:tt, order $($order:tt)+) => (
-        _query_impl!(@find_impl $args, [], [ $($order)+ ])
+    (@find $args:tt, order $($tokens:tt)+) => (
+        _query_impl!(@find_order $args, [], [], $($tokens)+)
+    );
+    (@find $args:tt, project $($project:tt)+) => (
+        _query_impl!(@find_impl $args, [], [], [ $($project)+ ])
     );
     (@find $args:tt, where $($tokens:tt)+) => (
         _query_impl!(@find_filter $args, [], $($tokens)+)
     );
-    (@find_filter $args:tt, $filter:tt, order by $($field:ident).+ >) => (
-        _query_impl!(@find_impl $args, $filter, [ by $($field).+ > ])
-    );
-    (@find_filter $args:tt, $filter:tt, order by $($field:ident).+ <) => (
-        _query_impl!(@find_impl $args, $filter, [ by $($field).+ < ])
-    );
-    (@find_filter $args:tt, $filter:tt, order by $($field:ident).+ asc) => (
-        _query_impl!(@find_impl $args, $filter, [ by $($field).+ asc ])
-    );
-    (@find_filter $args:tt, $filter:tt, order by $($field:ident).+ desc) => (
-        _query_impl!(@find_impl $args, $filter, [ by $($field).+ desc ])
-    );
-    (@find_filter $args:tt, $filter:tt, order by $($field:ident).+) => (
-        _query_impl!(@find_impl $args, $filter, [ by $($field).+ ])
-    );
-    (@find_filter $args:tt, $filter:tt, order >) => (
-        _query_impl!(@find_impl $args, $filter, [ > ])
-    );
-    (@find_filter $args:tt, $filter:tt, order <) => (
-        _query_impl!(@find_impl $args, $filter, [ < ])
-    );
-    (@find_filter $args:tt, $filter:tt, order asc) => (
-        _query_impl!(@find_impl $args, $filter, [ > ])
+    (@find_filter $args:tt, $filter:tt, order $($tokens:tt)+) => (
+        _query_impl!(@find_order $args, $filter, [], $($tokens)+)
     );
-    (@find_filter $args:tt, $filter:tt, order desc) => (
-        _query_impl!(@find_impl $args, $filter, [ < ])
+    (@find_filter $args:tt, $filter:tt, project $($project:tt)+) => (
+        _query_impl!(@find_impl $args, $filter, [], [ $($project)+ ])
     );
     (@find_filter $args:tt, $filter:tt, ) => (
-        _query_impl!(@find_impl $args, $filter, [])
+        _query_impl!(@find_impl $args, $filter, [], [])
     );
     (@find_filter $args:tt, [ $($filter:tt)* ], $token:tt $($tokens:tt)*) => (
         _query_impl!(@find_filter $args, [ $($filter)* $token ], $($tokens)*)
     );
-    (@find_impl ($out:ident, $type:tt, $coll:expr), [ $($filter:tt)* ], [ $($order:tt)* ]) => (
+    (@find_order $args:tt, $filter:tt, $order:tt, project $($project:tt)+) => (
+        _query_impl!(@find_impl $args, $filter, $order, [ $($project)+ ])
+    );
+    (@find_order $args:tt, $filter:tt, $order:tt, ) => (
+        _query_impl!(@find_impl $args, $filter, $order, [])
+    );
+    (@find_order $args:tt, $filter:tt, [ $($order:tt)* ], $token:tt $($tokens:tt)*) => (
+        _query_impl!(@find_order $args, $filter, [ $($order)* $token ], $($tokens)*)
+    );
+    (@find_impl ($out:ident, $type:tt, $coll:expr), [ $($filter:tt)* ], [ $($order:tt)* ], []) => (
         _query_impl!(@call $out, @find $type, $coll, _query_impl!(@filter $($filter)*), _query_impl!(@order $($order)*))
     );
+    (@find_impl ($out:ident, $type:tt, $coll:expr), [ $($filter:tt)* ], [ $($order:tt)* ], [ $($project:tt)+ ]) => (
+        _query_impl!(@call $out, @find $type, $coll, _query_impl!(@filter $($filter)*), _query_impl!(@order $($order)*), _query_impl!(@project $($project)+))
+    );
 
     // insert query
     (@insert $args:tt, { $($json:tt)* }) => ( // json
@@ -281,17 +350,45 @@ macro_rules! _query_impl {
     (@order_kind desc) => ( $crate::OrderKind::Desc );
     (@order_kind ) => ( $crate::OrderKind::default() );
 
-    (@order by $field:ident $($tokens:tt)*) => ( _query_impl!(@order_field [ $field ] $($tokens)*) );
+    (@order by $field:ident $($tokens:tt)*) => ( _query_impl!(@order_field [] [ $field ] $($tokens)*) );
+    (@order relevance $($field:tt)+) => ( $crate::Order::relevance(_query_impl!(@field $($field)+)) );
+    (@order distance $field:ident $($tokens:tt)+) => ( _query_impl!(@order_distance [ $field ] $($tokens)+) );
     (@order $order:tt) => ( $crate::Order::primary(_query_impl!(@order_kind $order)) );
     (@order ) => ( $crate::Order::primary(_query_impl!(@order_kind )) );
 
-    (@order_field [ $($path:tt)+ ] . * $($tokens:tt)*) => ( _query_impl!(@order_field [ $($path)+ . * ] $($tokens)*) );
-    (@order_field [ $($path:tt)+ ] . $field:ident $($tokens:tt)*) => ( _query_impl!(@order_field [ $($path)+ . $field ] $($tokens)*) );
-    (@order_field [ $($path:tt)+ ] $($tokens:tt)*) => ( _query_impl!(@order_field_impl [ $($path)+ ], $($tokens)*) );
+    (@order_field $fields:tt [ $($path:tt)+ ] . * $($tokens:tt)*) => ( _query_impl!(@order_field $fields [ $($path)+ . * ] $($tokens)*) );
+    (@order_field $fields:tt [ $($path:tt)+ ] . $field:ident $($tokens:tt)*) => ( _query_impl!(@order_field $fields [ $($path)+ . $field ] $($tokens)*) );
+    (@order_field [ $($fields:tt)* ] [ $($path:tt)+ ] , $field:ident $($tokens:tt)*) => ( _query_impl!(@order_field [ $($fields)* ([ $($path)+ ]) ] [ $field ] $($tokens)*) );
+    (@order_field [ $($fields:tt)* ] [ $($path:tt)+ ] $order:tt , $field:ident $($tokens:tt)*) => ( _query_impl!(@order_field [ $($fields)* ([ $($path)+ ] $order) ] [ $field ] $($tokens)*) );
+    (@order_field [] [ $($path:tt)+ ] $($tokens:tt)*) => ( _query_impl!(@order_field_impl [ $($path)+ ], $($tokens)*) );
+    (@order_field [ $($fields:tt)+ ] [ $($path:tt)+ ] $($tokens:tt)*) => ( _query_impl!(@order_fields_impl [ $($fields)+ ([ $($path)+ ] $($tokens)*) ]) );
+
+    (@order_distance [ $($path:tt)+ ] . * $($tokens:tt)+) => ( _query_impl!(@order_distance [ $($path)+ . * ] $($tokens)+) );
+    (@order_distance [ $($path:tt)+ ] . $field:ident $($tokens:tt)+) => ( _query_impl!(@order_distance [ $($path)+ . $field ] $($tokens)+) );
+    (@order_distance [ $($path:tt)+ ] from $point:expr) => (
+        $crate::Order::distance(_query_impl!(@field $($path)+), $point)
+    );
 
     (@order_field_impl [ $($field:tt)+ ], $($order:tt)*) => (
         $crate::Order::field(_query_impl!(@field $($field)+), _query_impl!(@order_kind $($order)*))
     );
+    (@order_fields_impl [ $(([ $($field:tt)+ ] $($order:tt)*))+ ]) => (
+        $crate::Order::fields(_query_impl!(@vec $((_query_impl!(@field $($field)+), _query_impl!(@order_kind $($order)*))),+))
+    );
+
+    //
+    // Projection util
+    //
+    (@project - $($tokens:tt)+) => ( $crate::Projection::exclude(_query_impl!(@project_fields [] [] $($tokens)+)) );
+    (@project $($tokens:tt)+) => ( $crate::Projection::include(_query_impl!(@project_fields [] [] $($tokens)+)) );
+
+    (@project_fields $fields:tt [] $field:ident $($tokens:tt)*) => ( _query_impl!(@project_fields $fields [ $field ] $($tokens)*) );
+    (@project_fields $fields:tt [ $($path:tt)+ ] . * $($tokens:tt)*) => ( _query_impl!(@project_fields $fields [ $($path)+ . * ] $($tokens)*) );
+    (@project_fields $fields:tt [ $($path:tt)+ ] . $field:ident $($tokens:tt)*) => ( _query_impl!(@project_fields $fields [ $($path)+ . $field ] $($tokens)*) );
+    (@project_fields [ $($fields:tt)* ] [ $($path:tt)+ ] , - $($tokens:tt)+) => ( _query_impl!(@project_fields [ $($fields)* [ $($path)+ ] ] [] $($tokens)+) );
+    (@project_fields [ $($fields:tt)* ] [ $($path:tt)+ ] , $($tokens:tt)*) => ( _query_impl!(@project_fields [ $($fields)* [ $($path)+ ] ] [] $($tokens)*) );
+    (@project_fields [ $($fields:tt)* ] [ $($path:tt)+ ]) => ( _query_impl!(@project_fields [ $($fields)* [ $($path)+ ] ] []) );
+    (@project_fields [ $([ $($field:tt)+ ])+ ] []) => ( _query_impl!(@vec $(_query_impl!(@field $($field)+)),+) );
 
     //
     // Filter util
@@ -474,6 +571,36 @@ macro_rules! _query_impl {
         _query_impl!(@filter_comp_impl $field, Bw, $crate::KeyData::from($range.start), true, $crate::KeyData::from($range.end), false)
     );
 
+    // starts with (string or binary prefix)
+    (@filter_comp $field:tt ^= $value:expr) => (
+        _query_impl!(@filter_comp_impl $field, Prefix, $crate::KeyData::from($value))
+    );
+
+    // matches regular expression
+    (@filter_comp $field:tt ~= $value:expr) => (
+        _query_impl!(@filter_comp_impl $field, Regex, $crate::WrappedRegex($value.parse().unwrap()))
+    );
+
+    // matches like pattern
+    (@filter_comp $field:tt like $value:expr) => (
+        _query_impl!(@filter_comp_impl $field, Like, $crate::LikePattern::from($value))
+    );
+
+    // contains words (full-text search)
+    (@filter_comp $field:tt ~ $value:expr) => (
+        _query_impl!(@filter_comp_impl $field, Text, $crate::TextQuery::from($value))
+    );
+
+    // within radius around point (geo)
+    (@filter_comp $field:tt near $point:expr, $radius:expr) => (
+        _query_impl!(@filter_comp_impl $field, Near, $crate::GeoNear::new($point, $radius))
+    );
+
+    // within box or polygon (geo)
+    (@filter_comp $field:tt within $shape:expr) => (
+        _query_impl!(@filter_comp_impl $field, Within, $shape)
+    );
+
     // has value (field exists or not null)
     (@filter_comp $field:tt ?) => (
         _query_impl!(@filter_comp_impl $field, Has)
@@ -750,6 +877,58 @@ mod test {
             );
         }
 
+        #[test]
+        fn comp_prefix() {
+            assert_eq!(
+                query!(@filter f ^= "abc"),
+                json_val!({ "f": { "$prefix": "abc" } })
+            );
+            assert_eq!(
+                query!(@filter f.s ^= "a/b/"),
+                json_val!({ "f.s": { "$prefix": "a/b/" } })
+            );
+        }
+
+        #[test]
+        fn comp_regex() {
+            assert_eq!(
+                query!(@filter f ~= "^foo.*bar$"),
+                json_val!({ "f": { "$regex": "^foo.*bar$" } })
+            );
+        }
+
+        #[test]
+        fn comp_like() {
+            assert_eq!(
+                query!(@filter f like "a%b"),
+                json_val!({ "f": { "$like": "a%b" } })
+            );
+        }
+
+        #[test]
+        fn comp_text() {
+            assert_eq!(
+                query!(@filter f ~ "quick fox"),
+                json_val!({ "f": { "$text": { "text": "quick fox", "any": false, "skip_stop_words": false } } })
+            );
+            assert_eq!(
+                query!(@filter f ~ crate::TextQuery::new("the fox").any().skip_stop_words()),
+                json_val!({ "f": { "$text": { "text": "the fox", "any": true, "skip_stop_words": true } } })
+            );
+        }
+
+        #[test]
+        fn comp_geo() {
+            assert_eq!(
+                query!(@filter p near crate::GeoPoint::new(55.75, 37.62), 500.0),
+                json_val!({ "p": { "$near": { "point": { "lat": 55.75, "lon": 37.62 }, "radius": 500.0 } } })
+            );
+            assert_eq!(
+                query!(@filter p within crate::GeoShape::Box(crate::GeoPoint::new(55.0, 37.0), crate::GeoPoint::new(56.0, 38.0))),
+                json_val!({ "p": { "$within": { "$box": [{ "lat": 55.0, "lon": 37.0 }, { "lat": 56.0, "lon": 38.0 }] } } })
+            );
+        }
+
         #[test]
         fn comp_has() {
             assert_eq!(query!(@filter f?), json_val!({ "f": "$has" }));
@@ -908,6 +1087,58 @@ mod test {
                 json_val!({ "field.*.subfield": "$desc" })
             );
         }
+
+        #[test]
+        fn fields() {
+            assert_eq!(
+                query!(@order by a desc, b asc),
+                json_val!([{ "a": "$desc" }, { "b": "$asc" }])
+            );
+            assert_eq!(
+                query!(@order by a.b, c.d <, e),
+                json_val!([{ "a.b": "$asc" }, { "c.d": "$desc" }, { "e": "$asc" }])
+            );
+            assert_eq!(
+                query!(@order by field.*.subfield desc, other),
+                json_val!([{ "field.*.subfield": "$desc" }, { "other": "$asc" }])
+            );
+        }
+
+        #[test]
+        fn relevance() {
+            assert_eq!(query!(@order relevance text), json_val!({ "$relevance": "text" }));
+            assert_eq!(query!(@order relevance a.b), json_val!({ "$relevance": "a.b" }));
+        }
+
+        #[test]
+        fn distance() {
+            assert_eq!(
+                query!(@order distance a.p from crate::GeoPoint::new(55.75, 37.62)),
+                json_val!({ "$distance": { "a.p": { "lat": 55.75, "lon": 37.62 } } })
+            );
+        }
+    }
+
+    mod project {
+        use serde_json::from_value;
+
+        #[test]
+        fn include() {
+            assert_eq!(query!(@project field), json_val!({ "$include": ["field"] }));
+            assert_eq!(query!(@project a, b.c), json_val!({ "$include": ["a", "b.c"] }));
+        }
+
+        #[test]
+        fn exclude() {
+            assert_eq!(query!(@project -field), json_val!({ "$exclude": ["field"] }));
+            assert_eq!(query!(@project -a.b, -c), json_val!({ "$exclude": ["a.b", "c"] }));
+        }
+
+        #[test]
+        fn pattern_map() {
+            assert_eq!(query!(@project field.*.subfield), json_val!({ "$include": ["field.*.subfield"] }));
+            assert_eq!(query!(@project -a, -field.*), json_val!({ "$exclude": ["a", "field.*"] }));
+        }
     }
 
     mod modify {
@@ -0,0 +1,572 @@
+use std::iter::once;
+
+use crate::filter::regex_prefix;
+
+use lmdb::ConstTransaction;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Collection, Comp, Cond, Filter, Index, KeyData, OrderKind, Result, ResultWrap, Selection,
+    TextQuery,
+};
+
+/// Selectivity of equality condition on non-indexed field
+const EQ_SELECTIVITY: usize = 10;
+
+/// Selectivity of one-sided range condition
+const RANGE_SELECTIVITY: usize = 3;
+
+/// Selectivity of two-sided range condition
+const BETWEEN_SELECTIVITY: usize = 4;
+
+/// The cost of checking document relative to single index entry
+///
+/// The index lookup for next condition of `$and` will be replaced by checking the documents which already selected when it is cheaper.
+const SCAN_COST: usize = 8;
+
+/// The cost of sorting document by field relative to iterating single index entry
+pub(crate) const SORT_COST: usize = 4;
+
+/// The plan of filter evaluation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterPlan {
+    /// Evaluation step
+    pub step: FilterStep,
+    /// Estimated number of selected documents
+    pub estimated: usize,
+    /// Actual number of selected documents (when the step has been evaluated)
+    #[serde(default)]
+    pub actual: Option<usize>,
+}
+
+/// The step of filter evaluation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterStep {
+    /// Lookup keys using index of field
+    #[serde(rename = "index")]
+    Index(String, Comp),
+    /// Lookup keys using compound index
+    ///
+    /// The keys of the first fields of index are equal to the prefix keys, and optionally the key of next field satisfies the comparison.
+    #[serde(rename = "compound")]
+    Compound(String, Vec<KeyData>, Option<Comp>),
+    /// Check documents using filter
+    ///
+    /// Within `$and` only the documents which selected by indexed conditions will be checked.
+    #[serde(rename = "scan")]
+    Scan(Filter),
+    /// Inverse selection
+    #[serde(rename = "$not")]
+    Not(Box<FilterPlan>),
+    /// Intersect selections starting from the most selective
+    #[serde(rename = "$and")]
+    And(Vec<FilterPlan>),
+    /// Unite selections
+    #[serde(rename = "$or")]
+    Or(Vec<FilterPlan>),
+}
+
+/// The plan of ordering
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderPlan {
+    /// Iterate documents by primary key
+    #[serde(rename = "primary")]
+    Primary(OrderKind),
+    /// Sort selected primary keys
+    #[serde(rename = "sort_primary")]
+    SortPrimary(OrderKind),
+    /// Iterate index of field
+    #[serde(rename = "index")]
+    Index(String, OrderKind),
+    /// Sort selected documents by the keys of field
+    #[serde(rename = "sort_field")]
+    SortField(String, OrderKind),
+    /// Sort selected documents by the keys of several fields
+    #[serde(rename = "sort_fields")]
+    SortFields(Vec<(String, OrderKind)>),
+    /// Sort selected documents by the relevance of text search on field
+    #[serde(rename = "relevance")]
+    Relevance(String),
+    /// Sort selected documents by the distance to the points of field
+    #[serde(rename = "distance")]
+    Distance(String),
+}
+
+/// The explanation of query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Explain {
+    /// The plan of filter evaluation
+    pub filter: Option<FilterPlan>,
+    /// The plan of ordering
+    pub order: OrderPlan,
+    /// Estimated number of found documents
+    pub estimated: usize,
+    /// Actual number of found documents
+    pub actual: usize,
+}
+
+impl Filter {
+    /// Make the plan of filter evaluation
+    pub(crate) fn plan(
+        &self,
+        txn: &ConstTransaction,
+        coll: &Collection,
+        documents: usize,
+    ) -> Result<FilterPlan> {
+        Ok(match self {
+            Filter::Cond(Cond::Not(filter)) => {
+                let plan = filter.plan(txn, coll, documents)?;
+                let estimated = documents.saturating_sub(plan.estimated);
+                FilterPlan::new(FilterStep::Not(Box::new(plan)), estimated)
+            }
+            Filter::Cond(Cond::And(filters)) => {
+                let (compound, filters) = plan_compound(txn, coll, filters, documents)?;
+                if filters.is_empty() {
+                    if let Some(plan) = compound {
+                        return Ok(plan);
+                    }
+                }
+
+                let mut plans = compound
+                    .into_iter()
+                    .map(Ok)
+                    .chain(
+                        filters
+                            .into_iter()
+                            .map(|filter| filter.plan(txn, coll, documents)),
+                    )
+                    .collect::<Result<Vec<_>>>()?;
+                plans.sort_by_key(|plan| (!plan.is_indexed(), plan.estimated));
+
+                let mut bound: Option<usize> = None;
+                for plan in &mut plans {
+                    if !plan.is_indexed() {
+                        *plan = plan.to_scan();
+                        continue;
+                    }
+                    if let Some(bound) = bound {
+                        if bound.saturating_mul(SCAN_COST) < plan.estimated {
+                            // checking already selected documents is cheaper than lookup
+                            *plan = plan.to_scan();
+                            continue;
+                        }
+                    }
+                    bound = Some(usize::min(bound.unwrap_or(documents), plan.estimated));
+                }
+                plans.sort_by_key(|plan| !plan.is_indexed());
+
+                let estimated = if documents > 0 {
+                    plans.iter().fold(documents as f64, |estimated, plan| {
+                        estimated * plan.estimated as f64 / documents as f64
+                    }) as usize
+                } else {
+                    0
+                };
+
+                FilterPlan::new(FilterStep::And(plans), estimated)
+            }
+            Filter::Cond(Cond::Or(filters)) => {
+                let plans = filters
+                    .iter()
+                    .map(|filter| filter.plan(txn, coll, documents))
+                    .collect::<Result<Vec<_>>>()?;
+                let estimated = plans.iter().map(|plan| plan.estimated).sum();
+
+                FilterPlan::new(FilterStep::Or(plans), usize::min(estimated, documents))
+            }
+            Filter::Comp(path, comp) => {
+                // the full-text index can be used for text search only, and the geo comparisons requires geo index
+                let index = coll.get_index(path)?.filter(|index| {
+                    index.is_text() == matches!(comp, Comp::Text(..))
+                        && comp
+                            .key_type()
+                            .map(|key| key == index.key())
+                            .unwrap_or(true)
+                });
+
+                if let Some(index) = index {
+                    let entries = txn.db_stat(&index).wrap_err()?.entries;
+                    let access = txn.access();
+
+                    use self::Comp::*;
+                    let estimated = match comp {
+                        Eq(val) => index.count_keys(txn, &access, once(val))?,
+                        In(vals) => index.count_keys(txn, &access, vals.iter())?,
+                        Gt(..) | Ge(..) | Lt(..) | Le(..) => entries / RANGE_SELECTIVITY,
+                        Bw(..) => entries / BETWEEN_SELECTIVITY,
+                        Prefix(..) | Regex(..) | Like(..) => entries / RANGE_SELECTIVITY,
+                        Text(query) => {
+                            let counts = query
+                                .terms()
+                                .into_iter()
+                                .map(|term| {
+                                    index.count_keys(txn, &access, once(&KeyData::String(term)))
+                                })
+                                .collect::<Result<Vec<_>>>()?;
+                            if query.any {
+                                counts.into_iter().sum()
+                            } else {
+                                counts.into_iter().min().unwrap_or(0)
+                            }
+                        }
+                        Near(..) | Within(..) => entries / BETWEEN_SELECTIVITY,
+                        Has => entries,
+                    };
+
+                    FilterPlan::new(
+                        FilterStep::Index(path.to_string(), comp.clone()),
+                        usize::min(estimated, documents),
+                    )
+                } else {
+                    use self::Comp::*;
+                    let estimated = match comp {
+                        Eq(..) => documents / EQ_SELECTIVITY,
+                        In(vals) => documents.saturating_mul(vals.len()) / EQ_SELECTIVITY,
+                        Gt(..) | Ge(..) | Lt(..) | Le(..) => documents / RANGE_SELECTIVITY,
+                        Bw(..) => documents / BETWEEN_SELECTIVITY,
+                        Prefix(..) | Regex(..) | Like(..) => documents / RANGE_SELECTIVITY,
+                        Text(..) => documents / EQ_SELECTIVITY,
+                        Near(..) | Within(..) => documents / BETWEEN_SELECTIVITY,
+                        Has => documents,
+                    };
+
+                    FilterPlan::new(
+                        FilterStep::Scan(self.clone()),
+                        usize::min(estimated, documents),
+                    )
+                }
+            }
+        })
+    }
+
+    /// Count the documents which satisfies the filter using index statistics only
+    ///
+    /// The number of duplicates of key in index is the number of documents which have that key.
+    ///
+    /// None will be returned when the filter cannot be counted exactly without selecting documents.
+    ///
+    pub(crate) fn count(
+        &self,
+        txn: &ConstTransaction,
+        coll: &Collection,
+        documents: usize,
+    ) -> Result<Option<usize>> {
+        Ok(match self {
+            Filter::Cond(Cond::Not(filter)) => filter
+                .count(txn, coll, documents)?
+                .map(|count| documents.saturating_sub(count)),
+            Filter::Comp(path, comp) => match comp {
+                Comp::Eq(val) => count_keys(txn, coll, path, comp, once(val))?,
+                // the document which has several of keys would be counted several times
+                Comp::In(vals) if vals.len() == 1 => {
+                    count_keys(txn, coll, path, comp, vals.iter())?
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Find the text search on field which selects documents
+    fn text_query(&self, path: &str) -> Option<&TextQuery> {
+        match self {
+            Filter::Comp(field, Comp::Text(query)) if &**field == path => Some(query),
+            Filter::Cond(Cond::And(filters)) | Filter::Cond(Cond::Or(filters)) => {
+                filters.iter().find_map(|filter| filter.text_query(path))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Plan the lookup of several conditions of `$and` using compound index
+///
+/// The equality conditions on the first fields of index, and optionally the range condition on the next field, can be covered by single lookup. The index which covers the most conditions will be used.
+///
+/// The plan of lookup (when any) will be returned with the rest of conditions.
+///
+#[allow(clippy::type_complexity)]
+fn plan_compound<'a>(
+    txn: &ConstTransaction,
+    coll: &Collection,
+    filters: &'a [Filter],
+    documents: usize,
+) -> Result<(Option<FilterPlan>, Vec<&'a Filter>)> {
+    let mut found: Option<(Index, Vec<usize>, Vec<KeyData>, Option<Comp>)> = None;
+
+    for index in coll.get_compound_indexes()? {
+        let mut covered = Vec::new();
+        let mut prefix = Vec::new();
+        let mut range = None;
+
+        for field in index.path().split(',') {
+            if let Some((pos, val)) =
+                filters
+                    .iter()
+                    .enumerate()
+                    .find_map(|(pos, filter)| match filter {
+                        Filter::Comp(path, Comp::Eq(val)) if &**path == field => Some((pos, val)),
+                        _ => None,
+                    })
+            {
+                covered.push(pos);
+                prefix.push(val.clone());
+                continue;
+            }
+
+            if !prefix.is_empty() {
+                if let Some((pos, comp)) =
+                    filters
+                        .iter()
+                        .enumerate()
+                        .find_map(|(pos, filter)| match filter {
+                            Filter::Comp(path, comp) if &**path == field && is_range(comp) => {
+                                Some((pos, comp))
+                            }
+                            _ => None,
+                        })
+                {
+                    covered.push(pos);
+                    range = Some(comp.clone());
+                }
+            }
+            break;
+        }
+
+        if covered.len() > 1
+            && found
+                .as_ref()
+                .map(|(_, found, ..)| found.len() < covered.len())
+                .unwrap_or(true)
+        {
+            found = Some((index, covered, prefix, range));
+        }
+    }
+
+    Ok(if let Some((index, covered, prefix, range)) = found {
+        let entries = txn.db_stat(&index).wrap_err()?.entries;
+        let estimated = prefix
+            .iter()
+            .fold(entries, |estimated, _| estimated / EQ_SELECTIVITY)
+            / match range {
+                Some(Comp::Bw(..)) => BETWEEN_SELECTIVITY,
+                Some(_) => RANGE_SELECTIVITY,
+                None => 1,
+            };
+
+        (
+            Some(FilterPlan::new(
+                FilterStep::Compound(index.path().into(), prefix, range),
+                usize::min(estimated, documents),
+            )),
+            filters
+                .iter()
+                .enumerate()
+                .filter(|(pos, _)| !covered.contains(pos))
+                .map(|(_, filter)| filter)
+                .collect(),
+        )
+    } else {
+        (None, filters.iter().collect())
+    })
+}
+
+fn is_range(comp: &Comp) -> bool {
+    use self::Comp::*;
+    matches!(comp, Gt(..) | Ge(..) | Lt(..) | Le(..) | Bw(..))
+}
+
+/// Count the entries of index of field which has specified keys
+///
+/// The index is used same way as in the plan of comparison.
+///
+fn count_keys<'a>(
+    txn: &ConstTransaction,
+    coll: &Collection,
+    path: &str,
+    comp: &Comp,
+    keys: impl Iterator<Item = &'a KeyData>,
+) -> Result<Option<usize>> {
+    let index = coll.get_index(path)?.filter(|index| {
+        !index.is_text()
+            && comp
+                .key_type()
+                .map(|key| key == index.key())
+                .unwrap_or(true)
+    });
+
+    if let Some(index) = index {
+        let access = txn.access();
+        index.count_keys(txn, &access, keys).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+impl FilterPlan {
+    fn new(step: FilterStep, estimated: usize) -> Self {
+        FilterPlan {
+            step,
+            estimated,
+            actual: None,
+        }
+    }
+
+    /// Find the text search on field which selects documents
+    pub(crate) fn text_query(&self, path: &str) -> Option<&TextQuery> {
+        use self::FilterStep::*;
+        match &self.step {
+            Index(field, Comp::Text(query)) if field == path => Some(query),
+            Scan(filter) => filter.text_query(path),
+            And(plans) | Or(plans) => plans.iter().find_map(|plan| plan.text_query(path)),
+            _ => None,
+        }
+    }
+
+    /// Check that the plan uses indexes only
+    fn is_indexed(&self) -> bool {
+        use self::FilterStep::*;
+        match &self.step {
+            Index(..) | Compound(..) => true,
+            Scan(..) => false,
+            Not(plan) => plan.is_indexed(),
+            And(plans) | Or(plans) => plans.iter().all(FilterPlan::is_indexed),
+        }
+    }
+
+    /// Convert the plan into checking documents
+    fn to_scan(&self) -> Self {
+        FilterPlan::new(FilterStep::Scan(self.to_filter()), self.estimated)
+    }
+
+    fn to_filter(&self) -> Filter {
+        use self::FilterStep::*;
+        match &self.step {
+            Index(path, comp) => Filter::comp(path.clone(), comp.clone()),
+            Compound(path, prefix, range) => {
+                let mut fields = path.split(',');
+                let mut filters = prefix
+                    .iter()
+                    .zip(fields.by_ref())
+                    .map(|(val, field)| Filter::comp(field.to_string(), Comp::Eq(val.clone())))
+                    .collect::<Vec<_>>();
+                if let (Some(comp), Some(field)) = (range, fields.next()) {
+                    filters.push(Filter::comp(field.to_string(), comp.clone()));
+                }
+                Filter::Cond(Cond::And(filters))
+            }
+            Scan(filter) => filter.clone(),
+            Not(plan) => Filter::Cond(Cond::Not(Box::new(plan.to_filter()))),
+            And(plans) => {
+                Filter::Cond(Cond::And(plans.iter().map(FilterPlan::to_filter).collect()))
+            }
+            Or(plans) => Filter::Cond(Cond::Or(plans.iter().map(FilterPlan::to_filter).collect())),
+        }
+    }
+
+    /// Evaluate the plan
+    ///
+    /// The actual numbers of selected documents will be stored to the evaluated steps.
+    pub(crate) fn apply(
+        &mut self,
+        txn: &ConstTransaction,
+        coll: &Collection,
+        documents: usize,
+    ) -> Result<Selection> {
+        use self::FilterStep::*;
+        let sel = match &mut self.step {
+            Index(path, comp) => {
+                let index = coll.req_index(path)?;
+                let access = txn.access();
+                use self::Comp::*;
+                let ids = match comp {
+                    Eq(val) => index.query_set(txn, &access, once(&*val))?,
+                    In(vals) => index.query_set(txn, &access, vals.iter())?,
+                    Gt(val) => index.query_range(txn, &access, Some((val, false)), None)?,
+                    Ge(val) => index.query_range(txn, &access, Some((val, true)), None)?,
+                    Lt(val) => index.query_range(txn, &access, None, Some((val, false)))?,
+                    Le(val) => index.query_range(txn, &access, None, Some((val, true)))?,
+                    Bw(val1, inc1, val2, inc2) => {
+                        index.query_range(txn, &access, Some((val1, *inc1)), Some((val2, *inc2)))?
+                    }
+                    Prefix(val) => index.query_prefix(txn, &access, val)?,
+                    Regex(re) => {
+                        index.query_pattern(txn, &access, &regex_prefix(re.as_str()), |key| {
+                            re.is_match(key)
+                        })?
+                    }
+                    Like(pat) => {
+                        index.query_pattern(txn, &access, pat.prefix(), |key| pat.is_match(key))?
+                    }
+                    Text(query) => index.query_text(txn, &access, query)?,
+                    Near(near) => index
+                        .query_geo(txn, &access, &near.bounds(), |point| near.contains(point))?,
+                    Within(shape) => index
+                        .query_geo(txn, &access, &shape.bounds(), |point| shape.contains(point))?,
+                    Has => index.query_range(txn, &access, None, None)?,
+                };
+                Selection::new(ids, false)
+            }
+            Compound(path, prefix, range) => {
+                let index = coll.req_index(path)?;
+                let access = txn.access();
+                use self::Comp::*;
+                let (beg, end) = match range {
+                    Some(Gt(val)) => (Some((&*val, false)), None),
+                    Some(Ge(val)) => (Some((&*val, true)), None),
+                    Some(Lt(val)) => (None, Some((&*val, false))),
+                    Some(Le(val)) => (None, Some((&*val, true))),
+                    Some(Bw(val1, inc1, val2, inc2)) => {
+                        (Some((&*val1, *inc1)), Some((&*val2, *inc2)))
+                    }
+                    _ => (None, None),
+                };
+                Selection::new(index.query_compound(txn, &access, prefix, beg, end)?, false)
+            }
+            Scan(filter) => Selection::new(
+                coll.scan_txn(txn, &!Selection::default(), |doc| filter.matches(doc))?,
+                false,
+            ),
+            Not(plan) => !plan.apply(txn, coll, documents)?,
+            And(plans) => {
+                let mut res = !Selection::default(); // universe
+                let mut scan = Vec::new();
+                for plan in plans.iter_mut() {
+                    if !res.inv && res.ids.is_empty() {
+                        // nothing to intersect
+                        break;
+                    }
+                    if let Scan(filter) = &plan.step {
+                        scan.push(filter.clone());
+                    } else {
+                        res = res & plan.apply(txn, coll, documents)?;
+                    }
+                }
+                if !scan.is_empty() && (res.inv || !res.ids.is_empty()) {
+                    res = Selection::new(
+                        coll.scan_txn(txn, &res, |doc| {
+                            scan.iter().all(|filter| filter.matches(doc))
+                        })?,
+                        false,
+                    );
+                }
+                res
+            }
+            Or(plans) => {
+                let mut res = Selection::default(); // empty
+                for plan in plans.iter_mut() {
+                    res = res | plan.apply(txn, coll, documents)?;
+                }
+                res
+            }
+        };
+
+        self.actual = Some(if sel.inv {
+            documents.saturating_sub(sel.ids.len())
+        } else {
+            sel.ids.len()
+        });
+
+        Ok(sel)
+    }
+}
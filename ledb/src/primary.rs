@@ -0,0 +1,267 @@
+use std::{
+    convert::TryFrom,
+    str::from_utf8,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use byteorder::{BigEndian, ByteOrder, NativeEndian};
+use getrandom::getrandom;
+
+use super::{Primary, PrimaryType, Result, ResultWrap};
+
+const ULID_CHARS: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The encoding and decoding of primary keys
+///
+/// The integer keys stored in native byte order, the strings as is, the UUIDs and ULIDs as 16-byte binary.
+pub(crate) trait PrimaryKey {
+    /// Convert primary key to canonical form of specified type
+    fn check(self, id: Primary) -> Result<Primary>;
+
+    /// Convert primary key to binary representation
+    fn encode(self, id: &Primary) -> Result<Vec<u8>>;
+
+    /// Restore primary key from binary representation
+    fn decode(self, raw: &[u8]) -> Result<Primary>;
+
+    /// Generate new unique primary key
+    fn generate(self) -> Result<Primary>;
+}
+
+impl PrimaryKey for PrimaryType {
+    fn check(self, id: Primary) -> Result<Primary> {
+        use self::PrimaryType::*;
+        Ok(match (self, id) {
+            (U32, Primary::Int(id)) if id <= u64::from(u32::MAX) => Primary::Int(id),
+            (U64, Primary::Int(id)) => Primary::Int(id),
+            (U32, Primary::String(id)) | (U64, Primary::String(id)) => {
+                return self.check(Primary::Int(parse_int(&id)?));
+            }
+            (String, Primary::Int(id)) => Primary::String(id.to_string()),
+            (String, Primary::String(id)) if !id.is_empty() => Primary::String(id),
+            (Uuid, Primary::String(id)) => Primary::String(format_uuid(parse_uuid(&id)?)),
+            (Ulid, Primary::String(id)) => Primary::String(format_ulid(parse_ulid(&id)?)),
+            (_, id) => {
+                return Err(format!("Invalid primary key '{}' for type {}", id, self)).wrap_err()
+            }
+        })
+    }
+
+    fn encode(self, id: &Primary) -> Result<Vec<u8>> {
+        use self::PrimaryType::*;
+        Ok(match (self, self.check(id.clone())?) {
+            (U32, Primary::Int(id)) => {
+                let mut raw = vec![0; 4];
+                NativeEndian::write_u32(&mut raw, id as u32);
+                raw
+            }
+            (U64, Primary::Int(id)) => {
+                let mut raw = vec![0; 8];
+                NativeEndian::write_u64(&mut raw, id);
+                raw
+            }
+            (String, Primary::String(id)) => id.into_bytes(),
+            (Uuid, Primary::String(id)) => parse_uuid(&id)?.to_be_bytes().to_vec(),
+            (Ulid, Primary::String(id)) => parse_ulid(&id)?.to_be_bytes().to_vec(),
+            _ => unreachable!(),
+        })
+    }
+
+    fn decode(self, raw: &[u8]) -> Result<Primary> {
+        use self::PrimaryType::*;
+        Ok(match (self, raw.len()) {
+            (U32, 4) => Primary::Int(NativeEndian::read_u32(raw).into()),
+            (U64, 8) => Primary::Int(NativeEndian::read_u64(raw)),
+            (String, _) => Primary::String(from_utf8(raw)?.into()),
+            (Uuid, 16) => Primary::String(format_uuid(BigEndian::read_u128(raw))),
+            (Ulid, 16) => Primary::String(format_ulid(BigEndian::read_u128(raw))),
+            _ => return Err(format!("Invalid raw primary key for type {}", self)).wrap_err(),
+        })
+    }
+
+    fn generate(self) -> Result<Primary> {
+        use self::PrimaryType::*;
+        Ok(match self {
+            U32 | U64 => unreachable!(),
+            String | Ulid => {
+                let time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|time| time.as_millis())
+                    .unwrap_or_default();
+                let rand = random_u128()?;
+                Primary::String(format_ulid(
+                    (time & 0xffff_ffff_ffff) << 80 | rand & 0xffff_ffff_ffff_ffff_ffff,
+                ))
+            }
+            Uuid => {
+                let rand = random_u128()?;
+                // set version 4 and RFC 4122 variant bits
+                let rand = rand & !(0xf << 76) | (0x4 << 76);
+                let rand = rand & !(0x3 << 62) | (0x2 << 62);
+                Primary::String(format_uuid(rand))
+            }
+        })
+    }
+}
+
+fn random_u128() -> Result<u128> {
+    let mut raw = [0; 16];
+    getrandom(&mut raw)
+        .map_err(|e| format!("Unable to get random key: {}", e))
+        .wrap_err()?;
+    Ok(u128::from_be_bytes(raw))
+}
+
+fn parse_int(id: &str) -> Result<u64> {
+    id.parse()
+        .map_err(|_| format!("Invalid integer primary key '{}'", id))
+        .wrap_err()
+}
+
+fn parse_uuid(id: &str) -> Result<u128> {
+    // the canonical form with dashes or the bare hexadecimal digits
+    let dashed = match id.len() {
+        36 => true,
+        32 => false,
+        _ => return Err(format!("Invalid UUID primary key '{}'", id)).wrap_err(),
+    };
+
+    let mut val = 0u128;
+
+    for (i, chr) in id.bytes().enumerate() {
+        if dashed && matches!(i, 8 | 13 | 18 | 23) {
+            if chr != b'-' {
+                return Err(format!("Invalid UUID primary key '{}'", id)).wrap_err();
+            }
+            continue;
+        }
+        let digit = char::from(chr)
+            .to_digit(16)
+            .ok_or_else(|| format!("Invalid UUID primary key '{}'", id))
+            .wrap_err()?;
+        val = val << 4 | u128::from(digit);
+    }
+
+    Ok(val)
+}
+
+fn format_uuid(id: u128) -> String {
+    let hex = format!("{:032x}", id);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn parse_ulid(id: &str) -> Result<u128> {
+    if id.len() != 26 {
+        return Err(format!("Invalid ULID primary key '{}'", id)).wrap_err();
+    }
+
+    let mut val = 0u128;
+
+    for (i, chr) in id.bytes().enumerate() {
+        let chr = match chr.to_ascii_uppercase() {
+            b'I' | b'L' => b'1',
+            b'O' => b'0',
+            chr => chr,
+        };
+        let digit = ULID_CHARS
+            .iter()
+            .position(|c| *c == chr)
+            .filter(|digit| i > 0 || *digit < 8)
+            .ok_or_else(|| format!("Invalid ULID primary key '{}'", id))
+            .wrap_err()?;
+        val = val << 5 | u128::try_from(digit).unwrap();
+    }
+
+    Ok(val)
+}
+
+fn format_ulid(id: u128) -> String {
+    (0..26)
+        .map(|i| char::from(ULID_CHARS[(id >> (125 - i * 5)) as usize & 0x1f]))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Primary, PrimaryKey, PrimaryType};
+
+    #[test]
+    fn int_keys() {
+        assert_eq!(PrimaryType::U32.check("123".into()).unwrap(), 123);
+        assert!(PrimaryType::U32.check(Primary::Int(1 << 32)).is_err());
+        assert_eq!(
+            PrimaryType::U64.check(Primary::Int(1 << 32)).unwrap(),
+            1 << 32
+        );
+
+        let raw = PrimaryType::U64.encode(&Primary::Int(1 << 40)).unwrap();
+        assert_eq!(raw.len(), 8);
+        assert_eq!(PrimaryType::U64.decode(&raw).unwrap(), 1 << 40);
+    }
+
+    #[test]
+    fn uuid_keys() {
+        let id = PrimaryType::Uuid
+            .check("6BA7B810-9DAD-11D1-80B4-00C04FD430C8".into())
+            .unwrap();
+        assert_eq!(id, "6ba7b810-9dad-11d1-80b4-00c04fd430c8");
+
+        let raw = PrimaryType::Uuid.encode(&id).unwrap();
+        assert_eq!(raw.len(), 16);
+        assert_eq!(PrimaryType::Uuid.decode(&raw).unwrap(), id);
+
+        assert_eq!(
+            PrimaryType::Uuid
+                .check("6ba7b8109dad11d180b400c04fd430c8".into())
+                .unwrap(),
+            id
+        );
+
+        assert!(PrimaryType::Uuid.check("6ba7b810-9dad".into()).is_err());
+        assert!(PrimaryType::Uuid
+            .check("+ba7b810-9dad-11d1-80b4-00c04fd430c8".into())
+            .is_err());
+        assert!(PrimaryType::Uuid
+            .check("6ba7b8109-dad-11d1-80b4-00c04fd430c8".into())
+            .is_err());
+        assert!(PrimaryType::Uuid
+            .check("6ba7b810-9dad-11d1-80b4-00c04fd430c8-".into())
+            .is_err());
+        assert!(PrimaryType::Uuid
+            .check("--6ba7b8109dad11d180b400c04fd430c8--".into())
+            .is_err());
+        assert!(PrimaryType::Uuid.check(Primary::Int(1)).is_err());
+
+        let id = PrimaryType::Uuid.generate().unwrap();
+        assert_eq!(PrimaryType::Uuid.check(id.clone()).unwrap(), id);
+        assert_eq!(&id.as_str().unwrap()[14..15], "4");
+    }
+
+    #[test]
+    fn ulid_keys() {
+        let id = PrimaryType::Ulid
+            .check("01arz3ndektsv4rrffq69g5fav".into())
+            .unwrap();
+        assert_eq!(id, "01ARZ3NDEKTSV4RRFFQ69G5FAV");
+
+        let raw = PrimaryType::Ulid.encode(&id).unwrap();
+        assert_eq!(raw.len(), 16);
+        assert_eq!(PrimaryType::Ulid.decode(&raw).unwrap(), id);
+
+        assert!(PrimaryType::Ulid
+            .check("81ARZ3NDEKTSV4RRFFQ69G5FAV".into())
+            .is_err());
+
+        let a = PrimaryType::Ulid.generate().unwrap();
+        let b = PrimaryType::Ulid.generate().unwrap();
+        assert_ne!(a, b);
+        assert_eq!(PrimaryType::Ulid.check(a.clone()).unwrap(), a);
+    }
+}
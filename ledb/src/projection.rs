@@ -0,0 +1,218 @@
+use std::{collections::BTreeMap, result::Result as StdResult};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{Identifier, Value};
+
+/// Projection of document fields
+///
+/// The field paths are same as the paths of indexes, so the nested fields separated by dots and the `*` matches any field of object. The arrays are projected element-wise.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Projection {
+    /// Keep the specified fields only
+    #[serde(rename = "$include", deserialize_with = "identifiers")]
+    Include(Vec<Identifier>),
+    /// Remove the specified fields
+    #[serde(rename = "$exclude", deserialize_with = "identifiers")]
+    Exclude(Vec<Identifier>),
+}
+
+fn identifiers<'de, D: Deserializer<'de>>(deserializer: D) -> StdResult<Vec<Identifier>, D::Error> {
+    Vec::<String>::deserialize(deserializer)
+        .map(|fields| fields.into_iter().map(Identifier::from).collect())
+}
+
+impl Projection {
+    /// Create projection which keeps the specified fields only
+    pub fn include<F: Into<Identifier>, I: IntoIterator<Item = F>>(fields: I) -> Self {
+        Projection::Include(fields.into_iter().map(|field| field.into()).collect())
+    }
+
+    /// Create projection which removes the specified fields
+    pub fn exclude<F: Into<Identifier>, I: IntoIterator<Item = F>>(fields: I) -> Self {
+        Projection::Exclude(fields.into_iter().map(|field| field.into()).collect())
+    }
+
+    /// Apply projection to generic data
+    pub fn apply(&self, doc: Value) -> Value {
+        match self {
+            Projection::Include(fields) => {
+                let paths = split_paths(fields);
+                let paths = paths.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                include_fields(doc, &paths).unwrap_or_else(|| Value::Map(BTreeMap::new()))
+            }
+            Projection::Exclude(fields) => {
+                let paths = split_paths(fields);
+                let paths = paths.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                exclude_fields(doc, &paths)
+            }
+        }
+    }
+}
+
+fn split_paths(fields: &[Identifier]) -> Vec<Vec<&str>> {
+    fields
+        .iter()
+        .map(|field| field.split('.').collect())
+        .collect()
+}
+
+/// Get the rest parts of paths which matches the field name
+fn sub_paths<'a>(name: &Value, paths: &[&'a [&'a str]]) -> Vec<&'a [&'a str]> {
+    paths
+        .iter()
+        .filter(|path| match (path.first(), name) {
+            (Some(&"*"), _) => true,
+            (Some(part), Value::Text(name)) => part == name,
+            _ => false,
+        })
+        .map(|path| &path[1..])
+        .collect()
+}
+
+/// Keep the fields which matches paths
+///
+/// Returns nothing when no fields matched.
+///
+fn include_fields(doc: Value, paths: &[&[&str]]) -> Option<Value> {
+    if paths.iter().any(|path| path.is_empty()) {
+        return Some(doc);
+    }
+
+    match doc {
+        Value::Map(val) => {
+            let val = val
+                .into_iter()
+                .filter_map(|(name, doc)| {
+                    let paths = sub_paths(&name, paths);
+                    if paths.is_empty() {
+                        None
+                    } else {
+                        include_fields(doc, &paths).map(|doc| (name, doc))
+                    }
+                })
+                .collect::<BTreeMap<_, _>>();
+            if val.is_empty() {
+                None
+            } else {
+                Some(Value::Map(val))
+            }
+        }
+        Value::Array(val) => {
+            let val = val
+                .into_iter()
+                .filter_map(|doc| include_fields(doc, paths))
+                .collect::<Vec<_>>();
+            if val.is_empty() {
+                None
+            } else {
+                Some(Value::Array(val))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Remove the fields which matches paths
+fn exclude_fields(doc: Value, paths: &[&[&str]]) -> Value {
+    match doc {
+        Value::Map(val) => Value::Map(
+            val.into_iter()
+                .filter_map(|(name, doc)| {
+                    let paths = sub_paths(&name, paths);
+                    if paths.iter().any(|path| path.is_empty()) {
+                        None
+                    } else if paths.is_empty() {
+                        Some((name, doc))
+                    } else {
+                        Some((name, exclude_fields(doc, &paths)))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(val) => Value::Array(
+            val.into_iter()
+                .map(|doc| exclude_fields(doc, paths))
+                .collect(),
+        ),
+        doc => doc,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Projection;
+    use serde_json::{from_str, from_value, json, to_string, Value};
+
+    macro_rules! test_apply {
+        ($projection:expr, $doc:tt, $res:tt) => {
+            assert_eq!($projection.apply(json_val!($doc)), json_val!($res));
+        };
+    }
+
+    #[test]
+    fn parse_projection() {
+        test_parse!(
+            Projection,
+            json!({ "$include": ["title", "meta.author"] }),
+            Projection::include(vec!["title", "meta.author"])
+        );
+        test_parse!(
+            Projection,
+            json!({ "$exclude": ["content"] }),
+            Projection::exclude(vec!["content"])
+        );
+    }
+
+    #[test]
+    fn build_projection() {
+        test_build!(
+            Projection::include(vec!["a.*.b"]),
+            json!({ "$include": ["a.*.b"] })
+        );
+        test_build!(
+            Projection::exclude(vec!["a", "b"]),
+            json!({ "$exclude": ["a", "b"] })
+        );
+    }
+
+    #[test]
+    fn include_fields() {
+        test_apply!(Projection::include(vec!["a", "c.d"]),
+                    { "a": 1, "b": 2, "c": { "d": [3], "e": 4 } },
+                    { "a": 1, "c": { "d": [3] } });
+        test_apply!(Projection::include(vec!["c"]),
+                    { "a": 1, "c": { "d": 3 } },
+                    { "c": { "d": 3 } });
+        test_apply!(Projection::include(vec!["x", "a.x"]),
+                    { "a": { "b": 1 } },
+                    {});
+        // the elements of arrays
+        test_apply!(Projection::include(vec!["a.b"]),
+                    { "a": [{ "b": 1, "c": 2 }, { "c": 3 }, { "b": 4 }] },
+                    { "a": [{ "b": 1 }, { "b": 4 }] });
+        // the wildcards
+        test_apply!(Projection::include(vec!["a.*.b"]),
+                    { "a": { "x": { "b": 1, "c": 2 }, "y": { "b": 3 } }, "d": 4 },
+                    { "a": { "x": { "b": 1 }, "y": { "b": 3 } } });
+    }
+
+    #[test]
+    fn exclude_fields() {
+        test_apply!(Projection::exclude(vec!["a", "c.d"]),
+                    { "a": 1, "b": 2, "c": { "d": [3], "e": 4 } },
+                    { "b": 2, "c": { "e": 4 } });
+        test_apply!(Projection::exclude(vec!["x", "a.x"]),
+                    { "a": { "b": 1 } },
+                    { "a": { "b": 1 } });
+        // the elements of arrays
+        test_apply!(Projection::exclude(vec!["a.b"]),
+                    { "a": [{ "b": 1, "c": 2 }, { "c": 3 }, { "b": 4 }] },
+                    { "a": [{ "c": 2 }, { "c": 3 }, {}] });
+        // the wildcards
+        test_apply!(Projection::exclude(vec!["a.*.b"]),
+                    { "a": { "x": { "b": 1, "c": 2 }, "y": { "b": 3 } }, "d": 4 },
+                    { "a": { "x": { "c": 2 }, "y": {} }, "d": 4 });
+    }
+}
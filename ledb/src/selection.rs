@@ -16,7 +16,6 @@ impl Selection {
         Selection { ids, inv }
     }
 
-    #[allow(clippy::trivially_copy_pass_by_ref)]
     pub fn has(&self, id: &Primary) -> bool {
         self.inv ^ self.ids.contains(id)
     }
@@ -30,9 +29,15 @@ impl Selection {
     }
 }
 
-impl<T: AsRef<[Primary]>> From<T> for Selection {
-    fn from(v: T) -> Self {
-        Selection::new(v.as_ref().iter().cloned().collect(), false)
+impl<'a, I: Clone + Into<Primary>> From<&'a [I]> for Selection {
+    fn from(v: &'a [I]) -> Self {
+        Selection::new(v.iter().cloned().map(Into::into).collect(), false)
+    }
+}
+
+impl<'a, I: Clone + Into<Primary>, const N: usize> From<&'a [I; N]> for Selection {
+    fn from(v: &'a [I; N]) -> Self {
+        Selection::from(&v[..])
     }
 }
 
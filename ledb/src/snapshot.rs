@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use lmdb::ReadTransaction;
+use serde::de::DeserializeOwned;
+
+use super::{
+    Accumulators, Collection, Document, Filter, Identifier, KeyData, Order, Page, Primary, Result,
+    ResultWrap, Storage, TxnDocumentsIterator, Value,
+};
+
+/// Consistent read session
+///
+/// All reading operations which made using the collections of snapshot see the same state of storage, even when the other writers changes it in the meantime.
+///
+/// *Note*: The thread which holds snapshot cannot start another read transaction, so the reading operations of regular collections will fail until snapshot dropped.
+/// Also the long living snapshot prevents the automatic growth of storage map, so the writers may fail when the map is full.
+///
+pub struct Snapshot {
+    txn: ReadTransaction<'static>,
+    storage: Storage,
+}
+
+impl Snapshot {
+    pub(crate) fn new(storage: Storage) -> Result<Self> {
+        let txn = storage.read_txn()?;
+
+        Ok(Self { txn, storage })
+    }
+
+    /// Get collection of documents within snapshot
+    pub fn collection<N: AsRef<str>>(&self, name: N) -> Result<SnapshotCollection<'_>> {
+        let name = name.as_ref();
+
+        let coll = self
+            .storage
+            .get_collection(name)?
+            .ok_or_else(|| format!("Missing collection '{}'", name))
+            .wrap_err()?;
+
+        Ok(SnapshotCollection {
+            txn: &self.txn,
+            coll,
+        })
+    }
+}
+
+/// Collection of documents within snapshot
+///
+/// It works like the regular collection, but allows reading operations only.
+///
+pub struct SnapshotCollection<'txn> {
+    txn: &'txn ReadTransaction<'static>,
+    coll: Collection,
+}
+
+impl<'txn> SnapshotCollection<'txn> {
+    pub fn name(&self) -> &str {
+        self.coll.name()
+    }
+
+    /// Find documents using optional filter and ordering
+    ///
+    /// Iterator across found documents will be returned.
+    ///
+    pub fn find<T: DeserializeOwned + Document>(
+        &self,
+        filter: Option<Filter>,
+        order: Order,
+    ) -> Result<TxnDocumentsIterator<'txn, T>> {
+        self.find_page(filter, order, Page::default())
+    }
+
+    /// Find the page of documents using optional filter and ordering
+    ///
+    /// See `Collection::find_page()` for details.
+    ///
+    pub fn find_page<T: DeserializeOwned + Document>(
+        &self,
+        filter: Option<Filter>,
+        order: Order,
+        page: Page,
+    ) -> Result<TxnDocumentsIterator<'txn, T>> {
+        let ids = self.coll.find_txn(self.txn, filter, order, page)?;
+
+        TxnDocumentsIterator::new(self.txn, self.coll.clone(), ids)
+    }
+
+    /// Find documents using optional filter and ordering
+    ///
+    /// The vector with found documents will be returned.
+    pub fn find_all<T: DeserializeOwned + Document>(
+        &self,
+        filter: Option<Filter>,
+        order: Order,
+    ) -> Result<Vec<T>> {
+        self.find(filter, order)?.collect::<Result<Vec<_>>>()
+    }
+
+    pub fn find_ids(&self, filter: Option<Filter>) -> Result<HashSet<Primary>> {
+        self.coll.find_ids_txn(self.txn, filter)
+    }
+
+    /// Count documents using optional filter
+    pub fn count(&self, filter: Option<Filter>) -> Result<usize> {
+        self.coll.count_txn(self.txn, filter)
+    }
+
+    /// Aggregate documents using optional filter
+    pub fn aggregate(
+        &self,
+        filter: Option<Filter>,
+        group: Vec<Identifier>,
+        fields: Accumulators,
+    ) -> Result<Vec<Value>> {
+        self.coll.aggregate_txn(self.txn, filter, &group, &fields)
+    }
+
+    /// Get the distinct keys of indexed field with the numbers of documents
+    pub fn distinct<P: AsRef<str>>(
+        &self,
+        field: P,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        self.coll.distinct_txn(self.txn, field.as_ref(), filter)
+    }
+
+    /// Get the most frequent keys of indexed field with the numbers of documents
+    pub fn facets<P: AsRef<str>>(
+        &self,
+        field: P,
+        filter: Option<Filter>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        self.coll
+            .facets_txn(self.txn, field.as_ref(), filter, limit)
+    }
+
+    /// Checks the collection contains document with specified primary key
+    pub fn has(&self, id: impl Into<Primary>) -> Result<bool> {
+        self.coll.has_txn(self.txn, &id.into())
+    }
+
+    /// Get document from collection using primary key/identifier
+    pub fn get<T: DeserializeOwned + Document>(&self, id: impl Into<Primary>) -> Result<Option<T>> {
+        self.coll.get_txn(self.txn, &id.into())
+    }
+
+    /// Get the last primary key/identifier of inserted document
+    pub fn last_id(&self) -> Result<Primary> {
+        self.coll.last_id_txn(self.txn)
+    }
+}
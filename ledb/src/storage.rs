@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     env::current_dir,
     fs::create_dir_all,
+    io::{Read, Write},
     ops::Deref,
     path::{Path, PathBuf},
     sync::{Arc, RwLock},
@@ -10,16 +11,18 @@ use std::{
 use dirs::home_dir;
 use dunce::canonicalize;
 use lmdb::{
-    self, open as OpenFlag, open::Flags as OpenFlags, Cursor, CursorIter, Database,
-    DatabaseOptions, EnvBuilder, Environment, MaybeOwned, ReadTransaction,
+    self, copy as CopyFlag, copy::Flags as CopyFlags, db::Flags as DbFlags, open as OpenFlag,
+    open::Flags as OpenFlags, Cursor, CursorIter, Database, DatabaseOptions, EnvBuilder,
+    Environment, LmdbResultExt, MaybeOwned, ReadTransaction, WriteTransaction,
 };
-use ron::de::from_str as from_db_name;
+use ron::{de::from_str as from_db_name, ser::to_string as to_db_name};
 use serde::{Deserialize, Serialize};
 use supercow::{ext::ConstDeref, NonSyncSupercow, Supercow};
 
 use super::{
-    Collection, CollectionDef, Enumerable, IndexDef, Pool, Result, ResultWrap, Serial,
-    SerialGenerator,
+    archive, load_catalog, now, Collection, CollectionDef, CollectionMeta, Enumerable, Error,
+    IndexDef, IndexMeta, Pool, PrimaryType, Result, ResultWrap, Serial, SerialGenerator, Snapshot,
+    StorageDescription, StorageMeta, Transaction, TxnEnv, TxnGate,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -28,6 +31,8 @@ pub(crate) enum DatabaseDef {
     Collection(CollectionDef),
     #[serde(rename = "i")]
     Index(IndexDef),
+    #[serde(rename = "k")]
+    Catalog,
 }
 
 /// Storage stats data
@@ -94,7 +99,25 @@ impl From<lmdb::EnvInfo> for Info {
     }
 }
 
+/// Storage usage data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Usage {
+    /// The current size of memory map in bytes
+    pub map_size: usize,
+    /// The size of used pages in bytes
+    pub used_size: usize,
+    /// The maximum size which memory map can be grown to (`None` means unlimited)
+    pub map_size_limit: Option<usize>,
+}
+
 /// Database options
+///
+/// The automatic growth of memory map is enabled when `map_grow_factor` is set.
+/// In that case the map will be multiplied by factor each time it becomes full, until `map_size_limit` reached.
+///
+/// *Note*: The map can be grown only when no transactions is active, so the growth waits for the snapshots and transactions of other threads to finish.
+/// When they isn't finished in time the writing fails with map full error.
+///
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Options {
     // options
@@ -104,6 +127,11 @@ pub struct Options {
     max_readers: Option<u32>,
     #[serde(default)]
     max_dbs: Option<u32>,
+    // growth
+    #[serde(default)]
+    map_grow_factor: Option<f64>,
+    #[serde(default)]
+    map_size_limit: Option<usize>,
     // flags
     #[serde(default)]
     map_async: Option<bool>,
@@ -140,6 +168,19 @@ impl Options {
         Ok(bld)
     }
 
+    fn map_growth(&self) -> Result<Option<MapGrowth>> {
+        Ok(match self.map_grow_factor {
+            Some(factor) if factor > 1.0 => Some(MapGrowth {
+                factor,
+                limit: self.map_size_limit,
+            }),
+            Some(factor) => {
+                return Err(format!("Invalid map grow factor {}", factor)).wrap_err();
+            }
+            None => None,
+        })
+    }
+
     fn open_flags(&self) -> OpenFlags {
         self.fill_flags(None)
     }
@@ -219,11 +260,20 @@ impl Options {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+struct MapGrowth {
+    factor: f64,
+    limit: Option<usize>,
+}
+
 pub(crate) struct StorageData {
     path: PathBuf,
-    env: Environment,
+    env: Arc<Environment>,
+    catalog: Option<Database<'static>>,
     gen: SerialGenerator,
     collections: RwLock<Vec<Collection>>,
+    gate: TxnGate,
+    growth: RwLock<Option<MapGrowth>>,
 }
 
 /// Storage of documents
@@ -245,6 +295,9 @@ impl Storage {
 
         if let Some(storage) = Pool::get(&path)? {
             opts.config_env(&storage.env)?;
+            if let Some(growth) = opts.map_growth()? {
+                *storage.growth.write().wrap_err()? = Some(growth);
+            }
             Ok(Storage(storage))
         } else {
             Self::open(path, opts)
@@ -252,7 +305,11 @@ impl Storage {
     }
 
     fn open(path: PathBuf, opts: Options) -> Result<Self> {
-        let env = open_env(&path, opts)?;
+        let growth = RwLock::new(opts.map_growth()?);
+
+        let env = Arc::new(open_env(&path, opts)?);
+
+        let catalog = open_catalog(env.clone())?;
 
         let gen = SerialGenerator::new();
 
@@ -261,8 +318,11 @@ impl Storage {
         let storage = Storage(Arc::new(StorageData {
             path: path.clone(),
             env,
+            catalog,
             gen,
             collections,
+            gate: TxnGate::default(),
+            growth,
         }));
 
         storage.load_collections()?;
@@ -273,11 +333,7 @@ impl Storage {
     }
 
     fn load_collections(&self) -> Result<()> {
-        let env = &self.0.env;
-
-        let db = Database::open(env, None, &DatabaseOptions::defaults()).wrap_err()?;
-
-        let (last_serial, db_def) = load_databases(&env, &db)?;
+        let (last_serial, db_def) = load_catalog(self)?;
 
         self.0.gen.set(last_serial);
 
@@ -291,6 +347,100 @@ impl Storage {
         Ok(())
     }
 
+    /// Get the catalog database
+    ///
+    /// The catalog is missing when the storage created by older version has been opened read-only.
+    ///
+    pub(crate) fn catalog(&self) -> Option<&Database<'static>> {
+        self.0.catalog.as_ref()
+    }
+
+    pub(crate) fn is_read_only(&self) -> Result<bool> {
+        Ok(self.0.env.flags().wrap_err()?.contains(OpenFlag::RDONLY))
+    }
+
+    pub(crate) fn gate(&self) -> &TxnGate {
+        &self.0.gate
+    }
+
+    /// Open or create database
+    ///
+    /// The opening of database uses transaction internally so it should be gated too.
+    ///
+    pub(crate) fn open_db(&self, name: &str, opts: &DatabaseOptions) -> Result<Database<'static>> {
+        let _env = TxnEnv::new(self.clone())?;
+
+        Database::open(self.clone(), Some(name), opts).wrap_err()
+    }
+
+    pub(crate) fn read_txn(&self) -> Result<ReadTransaction<'static>> {
+        ReadTransaction::new(TxnEnv::new(self.clone())?).wrap_err()
+    }
+
+    /// Run the function using new write transaction and commit it
+    ///
+    /// The function will be called again when the map is full and it has been grown.
+    ///
+    pub(crate) fn write<R>(
+        &self,
+        mut func: impl FnMut(&WriteTransaction<'static>) -> Result<R>,
+    ) -> Result<R> {
+        self.writing(|| {
+            // the environment must outlive the transaction
+            let env = TxnEnv::new(self.clone())?;
+            let txn = WriteTransaction::new(env.clone()).wrap_err()?;
+            let res = func(&txn)?;
+            txn.commit().wrap_err()?;
+            Ok(res)
+        })
+    }
+
+    /// Run the writing function and retry it when the map has been grown
+    pub(crate) fn writing<R>(&self, mut func: impl FnMut() -> Result<R>) -> Result<R> {
+        loop {
+            match func() {
+                Err(Error::DbError(lmdb::Error::Code(lmdb::error::MAP_FULL)))
+                    if self.grow_map()? => {}
+                res => return res,
+            }
+        }
+    }
+
+    /// Grow the map of environment when it is allowed
+    ///
+    /// Returns `false` when the map cannot be grown.
+    ///
+    fn grow_map(&self) -> Result<bool> {
+        let MapGrowth { factor, limit } = match *self.0.growth.read().wrap_err()? {
+            Some(growth) => growth,
+            None => return Ok(false),
+        };
+
+        let env = &*self.0.env;
+        let map_size = env.info().wrap_err()?.mapsize;
+        let page_size = env.stat().wrap_err()?.psize as usize;
+        let limit = limit.unwrap_or(usize::MAX) / page_size * page_size;
+
+        let new_size = usize::max((map_size as f64 * factor) as usize, map_size + page_size);
+        // round up to the whole pages
+        let new_size = new_size.saturating_add(page_size - 1) / page_size * page_size;
+        let new_size = usize::min(new_size, limit);
+
+        if new_size <= map_size {
+            return Ok(false);
+        }
+
+        let grown = self.0.gate.exclusive(|| {
+            if env.info().wrap_err()?.mapsize > map_size {
+                // already grown concurrently
+                return Ok(());
+            }
+            unsafe { env.set_mapsize(new_size) }.wrap_err()
+        })?;
+
+        grown.map(|res| res.map(|_| true)).unwrap_or(Ok(false))
+    }
+
     pub(crate) fn enumerate<E: Enumerable>(&self, data: E) -> E {
         self.0.gen.enumerate(data)
     }
@@ -313,23 +463,57 @@ impl Storage {
     pub fn collection<N: AsRef<str>>(&self, name: N) -> Result<Collection> {
         let name = name.as_ref();
 
-        {
-            let collections = self.0.collections.read().wrap_err()?;
-            // search alive collection
-            if let Some(collection) = collections
-                .iter()
-                .find(|collection| collection.name() == name)
-            {
-                return Ok(collection.clone());
-            }
+        if let Some(collection) = self.get_collection(name)? {
+            return Ok(collection);
         }
 
+        self.create_collection(name, PrimaryType::default())
+    }
+
+    /// Get collection for documents with specified type of primary key
+    ///
+    /// *Note*: The collection will be created automatically when is does not exists.
+    /// The existing collection must have the same type of primary key.
+    ///
+    pub fn collection_with_key<N: AsRef<str>>(
+        &self,
+        name: N,
+        primary: PrimaryType,
+    ) -> Result<Collection> {
+        let name = name.as_ref();
+
+        if let Some(collection) = self.get_collection(name)? {
+            return if collection.primary_type() == primary {
+                Ok(collection)
+            } else {
+                Err(format!(
+                    "Collection '{}' has primary key of type {} but {} expected",
+                    name,
+                    collection.primary_type(),
+                    primary
+                ))
+                .wrap_err()
+            };
+        }
+
+        self.create_collection(name, primary)
+    }
+
+    fn create_collection(&self, name: &str, primary: PrimaryType) -> Result<Collection> {
+        let def = self.enumerate(CollectionDef::new(name));
+
+        self.write(|txn| {
+            CollectionMeta {
+                serial: def.0,
+                primary,
+                created: now(),
+                ..CollectionMeta::default()
+            }
+            .save(txn, self, name)
+        })?;
+
         // create new collection
-        let collection = Collection::new(
-            self.clone(),
-            self.enumerate(CollectionDef::new(name)),
-            Vec::new(),
-        )?;
+        let collection = Collection::new(self.clone(), def.clone(), Vec::new())?;
 
         let mut collections = self.0.collections.write().wrap_err()?;
         collections.push(collection.clone());
@@ -337,6 +521,143 @@ impl Storage {
         Ok(collection)
     }
 
+    pub(crate) fn get_collection<N: AsRef<str>>(&self, name: N) -> Result<Option<Collection>> {
+        let name = name.as_ref();
+        let collections = self.0.collections.read().wrap_err()?;
+        // search alive collection
+        Ok(collections
+            .iter()
+            .find(|collection| collection.name() == name)
+            .cloned())
+    }
+
+    /// Run multiple operations using single write transaction
+    ///
+    /// The changes will be committed when the function returns `Ok` or discarded when it returns `Err`.
+    ///
+    /// *Note*: Don't use the regular collections inside the function to modify data, because it will stuck waiting for the transaction to finish.
+    ///
+    /// When the map of storage is full the error will be returned, but the map will be grown automatically when it is allowed, so the transaction can be retried.
+    ///
+    pub fn transaction<R, F>(&self, func: F) -> Result<R>
+    where
+        F: FnOnce(&Transaction) -> Result<R>,
+    {
+        let txn = Transaction::new(self.clone())?;
+
+        // the transaction must be finished before growing
+        let res = func(&txn).and_then(|res| txn.commit().map(|_| res));
+
+        if let Err(Error::DbError(lmdb::Error::Code(lmdb::error::MAP_FULL))) = &res {
+            self.grow_map()?;
+        }
+
+        res
+    }
+
+    /// Start consistent read session
+    ///
+    /// All reading operations using the snapshot see the state of storage at the moment when snapshot has been taken.
+    ///
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        Snapshot::new(self.clone())
+    }
+
+    /// Rename collection
+    ///
+    /// The documents, indexes and primary key sequence will be moved to the collection with new name atomically.
+    ///
+    /// *Note*: The collection with new name must not exist.
+    ///
+    pub fn rename_collection<O: AsRef<str>, N: AsRef<str>>(&self, old: O, new: N) -> Result<()> {
+        self.transfer_collection(old.as_ref(), new.as_ref(), true)
+    }
+
+    /// Copy collection
+    ///
+    /// The new collection will be created using same type of primary key and indexes, then the documents and primary key sequence will be copied atomically.
+    ///
+    /// *Note*: The target collection must not exist.
+    ///
+    pub fn copy_collection<S: AsRef<str>, D: AsRef<str>>(&self, src: S, dst: D) -> Result<()> {
+        self.transfer_collection(src.as_ref(), dst.as_ref(), false)
+    }
+
+    fn transfer_collection(&self, src: &str, dst: &str, rename: bool) -> Result<()> {
+        let source = self
+            .get_collection(src)?
+            .ok_or_else(|| format!("Missing collection '{}'", src))
+            .wrap_err()?;
+
+        if self.has_collection(dst)? {
+            return Err(format!("Collection '{}' already exists", dst)).wrap_err();
+        }
+
+        let primary = source.primary_type();
+        let def = self.enumerate(CollectionDef::new(dst));
+        let index_defs = source
+            .get_indexes()?
+            .iter()
+            .map(|field| {
+                self.enumerate(IndexDef::new(
+                    dst,
+                    &field.path,
+                    field.kind,
+                    field.key,
+                    field.rest.clone(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let created = now();
+
+        // the target is published only when the transfer is committed
+        let target =
+            Collection::with_primary(self.clone(), def.clone(), primary, index_defs.clone())?;
+
+        let res = self.write(|txn| {
+            CollectionMeta {
+                serial: def.0,
+                primary,
+                created,
+                ..CollectionMeta::default()
+            }
+            .save(txn, self, dst)?;
+            for IndexDef(serial, _coll, path, kind, key, rest) in &index_defs {
+                IndexMeta {
+                    serial: *serial,
+                    kind: *kind,
+                    key: *key,
+                    rest: rest.clone(),
+                    created,
+                }
+                .save(txn, self, dst, path)?;
+            }
+            source.copy_txn(txn, &target)?;
+            if rename {
+                source.to_delete_txn(txn)?;
+            }
+            Ok(())
+        });
+
+        if let Err(err) = res {
+            // remove the databases of incomplete collection
+            let _ = target.set_deleted();
+            return Err(err);
+        }
+
+        let mut collections = self.0.collections.write().wrap_err()?;
+
+        if rename {
+            // the source data is deleted only when the transfer is committed
+            source.set_deleted()?;
+            collections.retain(|collection| collection.name() != src);
+        }
+
+        collections.push(target);
+
+        Ok(())
+    }
+
     pub fn drop_collection<N: AsRef<str>>(&self, name: N) -> Result<bool> {
         let name = name.as_ref();
 
@@ -365,6 +686,27 @@ impl Storage {
             .collect())
     }
 
+    /// Get the description of storage
+    ///
+    /// It includes the descriptions of all collections which taken from the same snapshot.
+    ///
+    pub fn describe(&self) -> Result<StorageDescription> {
+        let collections = self.0.collections.read().wrap_err()?.clone();
+
+        let txn = self.read_txn()?;
+
+        let StorageMeta { version, created } = StorageMeta::load(&txn, self)?.unwrap_or_default();
+
+        Ok(StorageDescription {
+            version,
+            created,
+            collections: collections
+                .iter()
+                .map(|collection| collection.describe_txn(&txn))
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+
     pub fn get_stats(&self) -> Result<Stats> {
         self.0.env.stat().map(Stats::from).wrap_err()
     }
@@ -373,6 +715,114 @@ impl Storage {
         self.0.env.info().map(Info::from).wrap_err()
     }
 
+    /// Get the current usage of storage map
+    pub fn get_usage(&self) -> Result<Usage> {
+        let info = self.0.env.info().wrap_err()?;
+        let stat = self.0.env.stat().wrap_err()?;
+        let growth = *self.0.growth.read().wrap_err()?;
+
+        Ok(Usage {
+            map_size: info.mapsize,
+            used_size: (info.last_pgno + 1) * stat.psize as usize,
+            map_size_limit: match growth {
+                Some(growth) => growth.limit,
+                None => Some(info.mapsize),
+            },
+        })
+    }
+
+    /// Make a backup copy of storage using specified path
+    ///
+    /// The backup is consistent and it can be made while storage is used by other readers and writers.
+    ///
+    /// When `compact` is `true` the free pages will be omitted and the pages will be renumbered sequentially.
+    /// This takes more time, but the size of copy will be reduced, for example after removing a lot of documents.
+    ///
+    /// *Note*: The target directory will be created when it does not exists, but it should not contain another storage.
+    ///
+    pub fn backup<P: AsRef<Path>>(&self, path: P, compact: bool) -> Result<()> {
+        let path = realpath(path.as_ref())?;
+
+        if self.0.env.flags().wrap_err()?.contains(OpenFlag::NOSUBDIR) {
+            if let Some(dir) = path.parent() {
+                create_dir_all(dir).wrap_err()?;
+            }
+        } else {
+            create_dir_all(&path).wrap_err()?;
+        }
+
+        let path = path.to_str().ok_or("Invalid backup path").wrap_err()?;
+
+        let _env = TxnEnv::new(self.clone())?;
+
+        self.0.env.copy(path, copy_flags(compact)).wrap_err()
+    }
+
+    /// Make a backup copy of storage data using specified writer
+    ///
+    /// The written data is the contents of `data.mdb` file of the storage copy.
+    /// See [`Storage::backup`] for details.
+    ///
+    /// Returns the number of written bytes.
+    ///
+    #[cfg(unix)]
+    pub fn backup_to<W: Write>(&self, out: &mut W, compact: bool) -> Result<u64> {
+        use std::{
+            io::copy,
+            os::unix::{io::AsRawFd, net::UnixStream},
+            thread::spawn,
+        };
+
+        // the connected pair of sockets is used as pipe
+        let (mut reader, writer) = UnixStream::pair().wrap_err()?;
+        let storage = self.clone();
+
+        let copier = spawn(move || {
+            let _env = TxnEnv::new(storage.clone())?;
+
+            storage
+                .0
+                .env
+                .copyfd(writer.as_raw_fd(), copy_flags(compact))
+                .wrap_err()
+        });
+
+        let copied = copy(&mut reader, out);
+        // unblock the copying when writing failed
+        drop(reader);
+
+        copier
+            .join()
+            .map_err(|_| "Backup thread panicked")
+            .wrap_err()??;
+
+        copied.wrap_err()
+    }
+
+    /// Export all collections of storage into portable archive
+    ///
+    /// The archive contains the documents with primary keys, the indexes and the primary key sequences of collections.
+    /// All collections will be exported from the same consistent state of storage.
+    ///
+    /// Returns the number of exported documents.
+    ///
+    pub fn export<W: Write>(&self, out: W) -> Result<usize> {
+        archive::export(self, out)
+    }
+
+    /// Import collections from portable archive
+    ///
+    /// The collections from archive will be created with its indexes, documents and primary key sequences.
+    ///
+    /// *Note*: The collections which already exists in storage cannot be imported.
+    /// The collections which has been created by failed import will be removed.
+    ///
+    /// Returns the number of imported documents.
+    ///
+    pub fn import<R: Read>(&self, input: R) -> Result<usize> {
+        archive::import(self, input)
+    }
+
     /// Get openned storages
     pub fn openned() -> Result<Vec<PathBuf>> {
         Pool::lst()
@@ -418,9 +868,9 @@ impl<'env> Into<NonSyncSupercow<'env, Environment>> for Storage {
 }
 
 /// The list of collection and index definitions
-type Definitions = Vec<(CollectionDef, Vec<IndexDef>)>;
+pub(crate) type Definitions = Vec<(CollectionDef, Vec<IndexDef>)>;
 
-fn load_databases(env: &Environment, db: &Database) -> Result<(Serial, Definitions)> {
+pub(crate) fn load_databases(env: &Environment, db: &Database) -> Result<(Serial, Definitions)> {
     let txn = ReadTransaction::new(env).wrap_err()?;
     let cursor = txn.cursor(db).wrap_err()?;
     let access = txn.access();
@@ -453,6 +903,7 @@ fn load_databases(env: &Environment, db: &Database) -> Result<(Serial, Definitio
                     .1
                     .push(def);
             }
+            Ok(DatabaseDef::Catalog) => (),
             Err(e) => return Err(e),
         }
     }
@@ -463,6 +914,14 @@ fn load_databases(env: &Environment, db: &Database) -> Result<(Serial, Definitio
     ))
 }
 
+fn copy_flags(compact: bool) -> CopyFlags {
+    if compact {
+        CopyFlag::COMPACT
+    } else {
+        CopyFlags::empty()
+    }
+}
+
 fn open_env(path: &Path, opts: Options) -> Result<Environment> {
     let path = path.to_str().ok_or("Invalid db path").wrap_err()?;
 
@@ -474,6 +933,25 @@ fn open_env(path: &Path, opts: Options) -> Result<Environment> {
     unsafe { bld.open(path, flags, 0o600) }.wrap_err()
 }
 
+fn open_catalog(env: Arc<Environment>) -> Result<Option<Database<'static>>> {
+    let db_name = to_db_name(&DatabaseDef::Catalog).wrap_err()?;
+
+    if env.flags().wrap_err()?.contains(OpenFlag::RDONLY) {
+        // the catalog cannot be created in read-only mode
+        let db_opts = DatabaseOptions::new(DbFlags::empty());
+
+        Database::open(Supercow::shared(env), Some(&db_name), &db_opts)
+            .to_opt()
+            .wrap_err()
+    } else {
+        let db_opts = DatabaseOptions::create_map::<str>();
+
+        Database::open(Supercow::shared(env), Some(&db_name), &db_opts)
+            .map(Some)
+            .wrap_err()
+    }
+}
+
 fn realpath(path: &Path) -> Result<PathBuf> {
     let path = if path.has_root() {
         path.to_path_buf()
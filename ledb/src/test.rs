@@ -1,6 +1,6 @@
 use std::{
     fs::remove_dir_all,
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use super::{Options, Result, Storage};
@@ -32,9 +32,17 @@ macro_rules! test_build {
 static DB_DIR: &'static str = "test_db";
 
 pub fn test_db(id: &'static str) -> Result<Storage> {
+    test_db_with(id, Options::default())
+}
+
+pub fn test_db_with(id: &'static str, opts: Options) -> Result<Storage> {
+    Storage::new(test_path(id), opts)
+}
+
+pub fn test_path(id: &'static str) -> PathBuf {
     let path = Path::new(DB_DIR).join(Path::new(id));
 
     let _ = remove_dir_all(&path);
 
-    Storage::new(&path, Options::default())
+    path
 }
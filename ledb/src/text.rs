@@ -0,0 +1,45 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The common english words which usually not helps to search
+pub const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// Check that the term is stop word
+pub fn is_stop_word(term: &str) -> bool {
+    STOP_WORDS.contains(&term)
+}
+
+/// Split text to the lowercased terms
+///
+/// The words are found using Unicode word boundaries, so the punctuation and spaces are skipped.
+///
+pub fn text_terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.unicode_words().map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_stop_word, text_terms};
+
+    #[test]
+    fn split_terms() {
+        assert_eq!(
+            text_terms("The quick (\"brown\") fox can't jump 32.3 feet, right?")
+                .collect::<Vec<_>>(),
+            vec!["the", "quick", "brown", "fox", "can't", "jump", "32.3", "feet", "right"]
+        );
+        assert_eq!(
+            text_terms("Привет, Мир!").collect::<Vec<_>>(),
+            vec!["привет", "мир"]
+        );
+    }
+
+    #[test]
+    fn stop_words() {
+        assert!(is_stop_word("the"));
+        assert!(!is_stop_word("fox"));
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    convert::TryFrom,
+    result::Result as StdResult,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de, Deserialize, Deserializer, Serializer};
+
+use super::Value;
+
+/// The number of microseconds in second
+const MICROS: i64 = 1_000_000;
+
+/// The number of seconds in day
+const DAY_SECS: i64 = 86_400;
+
+// The timestamps are the numbers of microseconds since Unix epoch
+
+/// Convert system time into timestamp
+pub(crate) fn from_system_time(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(time) => time.as_micros() as i64,
+        Err(err) => -(err.duration().as_micros() as i64),
+    }
+}
+
+/// Convert the number of seconds since epoch into timestamp
+pub(crate) fn from_secs(secs: i64) -> Option<i64> {
+    secs.checked_mul(MICROS)
+}
+
+/// Convert the fractional number of seconds since epoch into timestamp
+pub(crate) fn from_secs_f64(secs: f64) -> Option<i64> {
+    let time = (secs * MICROS as f64).round();
+    if time.is_finite() && time.abs() < i64::MAX as f64 {
+        Some(time as i64)
+    } else {
+        None
+    }
+}
+
+/// Convert timestamp into the number of seconds since epoch
+pub(crate) fn to_secs(time: i64) -> i64 {
+    time.div_euclid(MICROS)
+}
+
+/// Convert timestamp into the fractional number of seconds since epoch
+pub(crate) fn to_secs_f64(time: i64) -> f64 {
+    time as f64 / MICROS as f64
+}
+
+/// Extract timestamp from generic value
+///
+/// The numbers are treated as seconds since epoch and the strings are parsed as RFC 3339 dates. The standard datetimes of CBOR (tags 0 and 1) and the serialized `SystemTime` (`{"secs_since_epoch": .., "nanos_since_epoch": ..}`) are supported too.
+///
+pub(crate) fn from_val(val: &Value) -> Option<i64> {
+    match val {
+        Value::Integer(secs) => from_secs(i64::try_from(*secs).ok()?),
+        Value::Float(secs) => from_secs_f64(*secs),
+        Value::Text(time) => parse(time),
+        Value::Tag(0, val) => match &**val {
+            Value::Text(time) => parse(time),
+            _ => None,
+        },
+        Value::Tag(1, val) => match &**val {
+            Value::Integer(..) | Value::Float(..) => from_val(val),
+            _ => None,
+        },
+        Value::Map(val) => {
+            let secs = val.get(&Value::Text("secs_since_epoch".into()))?;
+            let nanos = val.get(&Value::Text("nanos_since_epoch".into()))?;
+            match (secs, nanos) {
+                (Value::Integer(secs), Value::Integer(nanos)) => {
+                    from_secs(i64::try_from(*secs).ok()?)?
+                        .checked_add(i64::try_from(*nanos / 1000).ok()?)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parse timestamp from RFC 3339 date and time
+///
+/// The dates without time, the times without offset (treated as UTC) and the space instead of `T` are accepted too. The fractions of seconds are truncated to microseconds.
+///
+pub(crate) fn parse(time: &str) -> Option<i64> {
+    let mut raw = time.as_bytes();
+
+    let year = take_num(&mut raw, 4)?;
+    take_char(&mut raw, b'-')?;
+    let month = take_num(&mut raw, 2)?;
+    take_char(&mut raw, b'-')?;
+    let day = take_num(&mut raw, 2)?;
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut secs = days_from_civil(year, month, day) * DAY_SECS;
+    let mut micros = 0;
+
+    if let Some((b'T' | b't' | b' ', rest)) = raw.split_first() {
+        raw = rest;
+
+        let hour = take_num(&mut raw, 2)?;
+        take_char(&mut raw, b':')?;
+        let minute = take_num(&mut raw, 2)?;
+        take_char(&mut raw, b':')?;
+        // the leap second is allowed
+        let second = take_num(&mut raw, 2)?;
+
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        secs += hour * 3600 + minute * 60 + second;
+
+        if let Some((b'.', rest)) = raw.split_first() {
+            raw = rest;
+            let len = raw.iter().take_while(|c| c.is_ascii_digit()).count();
+            if len == 0 {
+                return None;
+            }
+            micros = raw[..len]
+                .iter()
+                .chain(b"00000".iter())
+                .take(6)
+                .fold(0, |micros, c| micros * 10 + (c - b'0') as i64);
+            raw = &raw[len..];
+        }
+
+        match raw.split_first() {
+            None => (),
+            Some((b'Z' | b'z', rest)) => raw = rest,
+            Some((sign @ (b'+' | b'-'), rest)) => {
+                raw = rest;
+                let hours = take_num(&mut raw, 2)?;
+                take_char(&mut raw, b':')?;
+                let minutes = take_num(&mut raw, 2)?;
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                let offset = hours * 3600 + minutes * 60;
+                if *sign == b'+' {
+                    secs -= offset;
+                } else {
+                    secs += offset;
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    if !raw.is_empty() {
+        return None;
+    }
+
+    from_secs(secs)?.checked_add(micros)
+}
+
+/// Format timestamp as RFC 3339 date and time in UTC
+///
+/// The fraction of seconds is omitted when it is zero.
+///
+pub(crate) fn format(time: i64) -> String {
+    let secs = to_secs(time);
+    let micros = time.rem_euclid(MICROS);
+    let (year, month, day) = civil_from_days(secs.div_euclid(DAY_SECS));
+    let secs = secs.rem_euclid(DAY_SECS);
+
+    let mut out = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    );
+    if micros != 0 {
+        out.push('.');
+        out.push_str(format!("{:06}", micros).trim_end_matches('0'));
+    }
+    out.push('Z');
+    out
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub(crate) fn serialize<S: Serializer>(time: &i64, serializer: S) -> StdResult<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*time))
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> StdResult<i64, D::Error> {
+    let time = String::deserialize(deserializer)?;
+    parse(&time).ok_or_else(|| de::Error::custom(format!("Invalid RFC 3339 timestamp: {}", time)))
+}
+
+fn take_num(raw: &mut &[u8], len: usize) -> Option<i64> {
+    if raw.len() < len || !raw[..len].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let num = raw[..len]
+        .iter()
+        .fold(0, |num, c| num * 10 + (c - b'0') as i64);
+    *raw = &raw[len..];
+    Some(num)
+}
+
+fn take_char(raw: &mut &[u8], chr: u8) -> Option<()> {
+    match raw.split_first() {
+        Some((c, rest)) if *c == chr => {
+            *raw = rest;
+            Some(())
+        }
+        _ => None,
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Get the number of days since epoch by date
+///
+/// See http://howardhinnant.github.io/date_algorithms.html
+///
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Get the date by the number of days since epoch
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format, from_val, parse};
+    use crate::Value;
+
+    #[test]
+    fn parse_timestamp() {
+        assert_eq!(parse("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse("2009-02-13T23:31:30Z"), Some(1_234_567_890_000_000));
+        assert_eq!(
+            parse("2009-02-14T01:31:30+02:00"),
+            Some(1_234_567_890_000_000)
+        );
+        assert_eq!(
+            parse("2009-02-13t20:31:30.25-03:00"),
+            Some(1_234_567_890_250_000)
+        );
+        assert_eq!(
+            parse("2009-02-13 23:31:30.1234567"),
+            Some(1_234_567_890_123_456)
+        );
+        assert_eq!(parse("1969-12-31T23:59:59.5Z"), Some(-500_000));
+        assert_eq!(parse("2000-02-29"), Some(951_782_400_000_000));
+        assert_eq!(parse("2001-02-29"), None);
+        assert_eq!(parse("2009-02-13T24:00:00Z"), None);
+        assert_eq!(parse("2009-02-13T23:31:30."), None);
+        assert_eq!(parse("2009-02-13T23:31"), None);
+        assert_eq!(parse("2009-02-13T23:31:30Z+"), None);
+        assert_eq!(parse("1234567890"), None);
+    }
+
+    #[test]
+    fn format_timestamp() {
+        assert_eq!(format(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format(1_234_567_890_000_000), "2009-02-13T23:31:30Z");
+        assert_eq!(format(1_234_567_890_250_000), "2009-02-13T23:31:30.25Z");
+        assert_eq!(format(-500_000), "1969-12-31T23:59:59.5Z");
+        assert_eq!(format(951_782_400_000_000), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamp_from_val() {
+        assert_eq!(
+            from_val(&Value::Integer(1_234_567_890)),
+            Some(1_234_567_890_000_000)
+        );
+        assert_eq!(from_val(&Value::Float(-0.5)), Some(-500_000));
+        assert_eq!(
+            from_val(&Value::Text("1970-01-01T00:00:01Z".into())),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            from_val(&Value::Tag(
+                0,
+                Box::new(Value::Text("1970-01-01T00:00:01Z".into()))
+            )),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            from_val(&Value::Tag(1, Box::new(Value::Integer(1)))),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            from_val(&Value::Tag(1, Box::new(Value::Text("1".into())))),
+            None
+        );
+        assert_eq!(
+            from_val(&Value::Map(
+                vec![
+                    (Value::Text("secs_since_epoch".into()), Value::Integer(1)),
+                    (
+                        Value::Text("nanos_since_epoch".into()),
+                        Value::Integer(500_000_000)
+                    ),
+                ]
+                .into_iter()
+                .collect()
+            )),
+            Some(1_500_000)
+        );
+        assert_eq!(from_val(&Value::Bool(true)), None);
+    }
+}
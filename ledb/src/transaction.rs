@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+
+use lmdb::WriteTransaction;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    Accumulators, Collection, Document, Filter, Identifier, KeyData, Modify, Order, Page, Primary,
+    RawDocument, Result, ResultWrap, Storage, TxnDocumentsIterator, TxnEnv, Value,
+};
+
+/// Write transaction across collections
+///
+/// All changes which made using the collections of transaction will be applied atomically when transaction committed, or discarded when it aborted.
+///
+/// *Note*: Only one write transaction can be active at same time, so the other writers will wait until it finished.
+///
+pub struct Transaction {
+    txn: WriteTransaction<'static>,
+    // must be dropped after transaction
+    _env: TxnEnv,
+    storage: Storage,
+}
+
+impl Transaction {
+    pub(crate) fn new(storage: Storage) -> Result<Self> {
+        let env = TxnEnv::new(storage.clone())?;
+        let txn = WriteTransaction::new(env.clone()).wrap_err()?;
+
+        Ok(Self {
+            txn,
+            _env: env,
+            storage,
+        })
+    }
+
+    /// Get collection of documents within transaction
+    ///
+    /// *Note*: The collection must exists before transaction begins.
+    ///
+    pub fn collection<N: AsRef<str>>(&self, name: N) -> Result<TransactionCollection<'_>> {
+        let name = name.as_ref();
+
+        let coll = self
+            .storage
+            .get_collection(name)?
+            .ok_or_else(|| format!("Missing collection '{}'", name))
+            .wrap_err()?;
+
+        Ok(TransactionCollection {
+            txn: &self.txn,
+            coll,
+        })
+    }
+
+    pub(crate) fn commit(self) -> Result<()> {
+        self.txn.commit().wrap_err()
+    }
+}
+
+/// Collection of documents within transaction
+///
+/// It works like the regular collection, but all operations use same transaction.
+///
+pub struct TransactionCollection<'txn> {
+    txn: &'txn WriteTransaction<'static>,
+    coll: Collection,
+}
+
+impl<'txn> TransactionCollection<'txn> {
+    pub fn name(&self) -> &str {
+        self.coll.name()
+    }
+
+    /// Insert document into collection
+    ///
+    /// Primary key/identifier of new inserted document will be returned.
+    ///
+    pub fn insert<T: Serialize + Document>(&self, doc: T) -> Result<Primary> {
+        self.coll.insert_txn(self.txn, RawDocument::from_doc(&doc)?)
+    }
+
+    /// Find documents using optional filter and ordering
+    ///
+    /// Iterator across found documents will be returned.
+    ///
+    pub fn find<T: DeserializeOwned + Document>(
+        &self,
+        filter: Option<Filter>,
+        order: Order,
+    ) -> Result<TxnDocumentsIterator<'txn, T>> {
+        self.find_page(filter, order, Page::default())
+    }
+
+    /// Find the page of documents using optional filter and ordering
+    ///
+    /// See `Collection::find_page()` for details.
+    ///
+    pub fn find_page<T: DeserializeOwned + Document>(
+        &self,
+        filter: Option<Filter>,
+        order: Order,
+        page: Page,
+    ) -> Result<TxnDocumentsIterator<'txn, T>> {
+        let ids = self.coll.find_txn(self.txn, filter, order, page)?;
+
+        TxnDocumentsIterator::new(self.txn, self.coll.clone(), ids)
+    }
+
+    /// Find documents using optional filter and ordering
+    ///
+    /// The vector with found documents will be returned.
+    pub fn find_all<T: DeserializeOwned + Document>(
+        &self,
+        filter: Option<Filter>,
+        order: Order,
+    ) -> Result<Vec<T>> {
+        self.find(filter, order)?.collect::<Result<Vec<_>>>()
+    }
+
+    pub fn find_ids(&self, filter: Option<Filter>) -> Result<HashSet<Primary>> {
+        self.coll.find_ids_txn(self.txn, filter)
+    }
+
+    /// Count documents using optional filter
+    pub fn count(&self, filter: Option<Filter>) -> Result<usize> {
+        self.coll.count_txn(self.txn, filter)
+    }
+
+    /// Aggregate documents using optional filter
+    pub fn aggregate(
+        &self,
+        filter: Option<Filter>,
+        group: Vec<Identifier>,
+        fields: Accumulators,
+    ) -> Result<Vec<Value>> {
+        self.coll.aggregate_txn(self.txn, filter, &group, &fields)
+    }
+
+    /// Get the distinct keys of indexed field with the numbers of documents
+    pub fn distinct<P: AsRef<str>>(
+        &self,
+        field: P,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        self.coll.distinct_txn(self.txn, field.as_ref(), filter)
+    }
+
+    /// Get the most frequent keys of indexed field with the numbers of documents
+    pub fn facets<P: AsRef<str>>(
+        &self,
+        field: P,
+        filter: Option<Filter>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KeyData, usize)>> {
+        self.coll
+            .facets_txn(self.txn, field.as_ref(), filter, limit)
+    }
+
+    /// Update documents using optional filter and modifier
+    ///
+    /// Returns the number of affected documents.
+    ///
+    pub fn update(&self, filter: Option<Filter>, modify: Modify) -> Result<usize> {
+        self.coll.update_txn(self.txn, filter, &modify)
+    }
+
+    /// Remove documents using optional filter
+    ///
+    /// Returns the number of affected documents.
+    ///
+    pub fn remove(&self, filter: Option<Filter>) -> Result<usize> {
+        self.coll.remove_txn(self.txn, filter)
+    }
+
+    /// Checks the collection contains document with specified primary key
+    pub fn has(&self, id: impl Into<Primary>) -> Result<bool> {
+        self.coll.has_txn(self.txn, &id.into())
+    }
+
+    /// Get document from collection using primary key/identifier
+    pub fn get<T: DeserializeOwned + Document>(&self, id: impl Into<Primary>) -> Result<Option<T>> {
+        self.coll.get_txn(self.txn, &id.into())
+    }
+
+    /// Replace document in the collection
+    ///
+    /// *Note*: The document must have primary key/identifier.
+    ///
+    pub fn put<T: Serialize + Document>(&self, doc: T) -> Result<()> {
+        self.coll.put_txn(self.txn, RawDocument::from_doc(&doc)?)
+    }
+
+    /// Delete document with specified primary key/identifier from the collection
+    pub fn delete(&self, id: impl Into<Primary>) -> Result<bool> {
+        self.coll.delete_txn(self.txn, &id.into())
+    }
+
+    /// Get the last primary key/identifier of inserted document
+    pub fn last_id(&self) -> Result<Primary> {
+        self.coll.last_id_txn(self.txn)
+    }
+}
@@ -1,13 +1,15 @@
 use std::{
     borrow::Cow,
+    cmp::Ordering,
     str::from_utf8,
+    time::SystemTime,
 };
 
 use byteorder::{ByteOrder, NativeEndian};
 use ordered_float::OrderedFloat;
 use serde::{Serialize, Deserialize};
 
-use super::{KeyType, Result, ResultWrap, Value};
+use super::{timestamp, GeoPoint, KeyType, Result, ResultWrap, Value};
 
 /// The data of key
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -19,6 +21,10 @@ pub enum KeyData {
     String(String),
     Binary(Vec<u8>),
     Bool(bool),
+    Geo(GeoPoint),
+    /// The number of microseconds since Unix epoch
+    #[serde(with = "timestamp")]
+    Timestamp(i64),
 }
 
 mod float {
@@ -65,6 +71,13 @@ impl KeyData {
                 }
                 Bool(raw[0] != 0)
             }
+            KeyType::Geo => Geo(GeoPoint::from_raw(raw)?),
+            KeyType::Timestamp => {
+                if raw.len() != 8 {
+                    return Err("Timestamp key must be 8 bytes length".into());
+                }
+                Timestamp(NativeEndian::read_i64(raw))
+            }
         })
     }
 
@@ -72,7 +85,7 @@ impl KeyData {
     pub fn as_raw(&self) -> &[u8] {
         use self::KeyData::*;
         match self {
-            Int(val) => unsafe { &*(val as *const i64 as *const [u8; 8]) },
+            Int(val) | Timestamp(val) => unsafe { &*(val as *const i64 as *const [u8; 8]) },
             Float(val) => unsafe { &*(val as *const ordered_float::OrderedFloat<f64> as *const [u8; 8]) },
             String(val) => if val.is_empty() {
                 b"\0"
@@ -85,6 +98,7 @@ impl KeyData {
                 val.as_slice()
             },
             Bool(val) => unsafe { &*(val as *const bool as *const [u8; 1]) },
+            Geo(val) => val.as_raw(),
         }
     }
 
@@ -97,10 +111,25 @@ impl KeyData {
             Bytes(val) => KeyData::Binary(val.clone()),
             Text(val) => KeyData::String(val.clone()),
             Bool(val) => KeyData::Bool(*val),
+            Tag(..) => KeyData::Timestamp(timestamp::from_val(val)?),
             _ => return None,
         })
     }
 
+    /// Converts key data into generic value
+    pub fn into_val(self) -> Value {
+        use self::KeyData::*;
+        match self {
+            Int(val) => Value::Integer(val as i128),
+            Float(val) => Value::Float(val.0),
+            Binary(val) => Value::Bytes(val),
+            String(val) => Value::Text(val),
+            Bool(val) => Value::Bool(val),
+            Geo(val) => val.into_val(),
+            Timestamp(val) => Value::Tag(0, Box::new(Value::Text(timestamp::format(val)))),
+        }
+    }
+
     /// Value is empty
     pub fn is_empty(&self) -> bool {
         use self::KeyData::*;
@@ -119,7 +148,9 @@ impl KeyData {
             | (KeyType::Float, Float(..))
             | (KeyType::Binary, Binary(..))
             | (KeyType::String, String(..))
-            | (KeyType::Bool, Bool(..)) => self,
+            | (KeyType::Bool, Bool(..))
+            | (KeyType::Geo, Geo(..))
+            | (KeyType::Timestamp, Timestamp(..)) => self,
             _ => return None,
         })
     }
@@ -151,6 +182,12 @@ impl KeyData {
                 } else {
                     return None;
                 }),
+                (KeyType::Timestamp, Int(v)) => Timestamp(timestamp::from_secs(*v)?),
+                (KeyType::Timestamp, Float(v)) => Timestamp(timestamp::from_secs_f64(v.0)?),
+                (KeyType::Timestamp, String(v)) => Timestamp(timestamp::parse(v)?),
+                (KeyType::Int, Timestamp(v)) => Int(timestamp::to_secs(*v)),
+                (KeyType::Float, Timestamp(v)) => Float(OrderedFloat(timestamp::to_secs_f64(*v))),
+                (KeyType::String, Timestamp(v)) => String(timestamp::format(*v)),
                 _ => return None,
             })
         })
@@ -165,6 +202,23 @@ impl KeyData {
             String(..) => KeyType::String,
             Binary(..) => KeyType::Binary,
             Bool(..) => KeyType::Bool,
+            Geo(..) => KeyType::Geo,
+            Timestamp(..) => KeyType::Timestamp,
+        }
+    }
+
+    /// Compare key data with the key of maybe different type
+    ///
+    /// The other key will be converted into the type of this key, except the numbers which always compared as floats, and the timestamps which any key converted to.
+    pub fn compare(&self, other: &KeyData) -> Option<Ordering> {
+        use self::KeyData::*;
+        match (self, other) {
+            (Int(a), Float(b)) => OrderedFloat(*a as f64).partial_cmp(b),
+            (Float(a), Int(b)) => a.partial_cmp(&OrderedFloat(*b as f64)),
+            (_, Timestamp(..)) => self
+                .to_type(KeyType::Timestamp)
+                .map(|this| this.as_ref().cmp(other)),
+            _ => other.to_type(self.get_type()).map(|other| self.cmp(&other)),
         }
     }
 }
@@ -241,9 +295,28 @@ impl From<bool> for KeyData {
     }
 }
 
+impl From<GeoPoint> for KeyData {
+    fn from(v: GeoPoint) -> Self {
+        KeyData::Geo(v)
+    }
+}
+
+impl<'a> From<&'a SystemTime> for KeyData {
+    fn from(v: &'a SystemTime) -> Self {
+        KeyData::Timestamp(timestamp::from_system_time(*v))
+    }
+}
+
+impl From<SystemTime> for KeyData {
+    fn from(v: SystemTime) -> Self {
+        KeyData::Timestamp(timestamp::from_system_time(v))
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{KeyData, KeyType};
+    use super::{GeoPoint, KeyData, KeyType};
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn get_type() {
@@ -252,6 +325,8 @@ mod test {
         assert_eq!(KeyData::from("abc").get_type(), KeyType::String);
         assert_eq!(KeyData::from(vec![1u8, 2, 3]).get_type(), KeyType::Binary);
         assert_eq!(KeyData::from(true).get_type(), KeyType::Bool);
+        assert_eq!(KeyData::from(GeoPoint::new(55.75, 37.62)).get_type(), KeyType::Geo);
+        assert_eq!(KeyData::from(UNIX_EPOCH).get_type(), KeyType::Timestamp);
     }
 
     #[test]
@@ -375,5 +450,63 @@ mod test {
                 .into_owned(),
             KeyData::from("true")
         );
+        assert_eq!(
+            KeyData::from(1_234_567_890)
+                .to_type(KeyType::Timestamp)
+                .unwrap()
+                .into_owned(),
+            KeyData::Timestamp(1_234_567_890_000_000)
+        );
+        assert_eq!(
+            KeyData::from(1_234_567_890.5)
+                .to_type(KeyType::Timestamp)
+                .unwrap()
+                .into_owned(),
+            KeyData::Timestamp(1_234_567_890_500_000)
+        );
+        assert_eq!(
+            KeyData::from("2009-02-14T01:31:30.5+02:00")
+                .to_type(KeyType::Timestamp)
+                .unwrap()
+                .into_owned(),
+            KeyData::Timestamp(1_234_567_890_500_000)
+        );
+        assert_eq!(KeyData::from("abc").to_type(KeyType::Timestamp), None);
+        assert_eq!(
+            KeyData::Timestamp(1_234_567_890_500_000)
+                .to_type(KeyType::Int)
+                .unwrap()
+                .into_owned(),
+            KeyData::from(1_234_567_890)
+        );
+        assert_eq!(
+            KeyData::Timestamp(1_234_567_890_500_000)
+                .to_type(KeyType::String)
+                .unwrap()
+                .into_owned(),
+            KeyData::from("2009-02-13T23:31:30.5Z")
+        );
+        assert_eq!(
+            KeyData::from(UNIX_EPOCH + Duration::from_millis(1500)),
+            KeyData::Timestamp(1_500_000)
+        );
+    }
+
+    #[test]
+    fn compare() {
+        use std::cmp::Ordering::*;
+        assert_eq!(KeyData::from(2).compare(&KeyData::from(2.5)), Some(Less));
+        assert_eq!(KeyData::from(2.5).compare(&KeyData::from(2)), Some(Greater));
+        assert_eq!(KeyData::from(2).compare(&KeyData::from("2")), Some(Equal));
+        assert_eq!(KeyData::from("abc").compare(&KeyData::from("abd")), Some(Less));
+        assert_eq!(KeyData::from("10").compare(&KeyData::from(9)), Some(Less));
+        assert_eq!(KeyData::from(1).compare(&KeyData::from("abc")), None);
+        assert_eq!(KeyData::from(true).compare(&KeyData::from(1)), None);
+        let time = KeyData::Timestamp(1_234_567_890_500_000);
+        assert_eq!(KeyData::from(1_234_567_890).compare(&time), Some(Less));
+        assert_eq!(KeyData::from(1_234_567_890.5).compare(&time), Some(Equal));
+        assert_eq!(KeyData::from("2009-02-14T00:00:00Z").compare(&time), Some(Greater));
+        assert_eq!(time.compare(&KeyData::from("2009-02-13T23:31:30.5Z")), Some(Equal));
+        assert_eq!(KeyData::from("abc").compare(&time), None);
     }
 }